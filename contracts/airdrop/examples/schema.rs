@@ -3,7 +3,7 @@ use std::fs::create_dir_all;
 
 use cosmwasm_schema::{export_schema, remove_schemas, schema_for};
 
-use airdrop::{AirdropClaimResponse, HandleMsg, InitMsg, QueryMsg};
+use airdrop::{AirdropClaimResponse, HandleMsg, InitMsg, QueryMsg, RoundResponse};
 
 fn main() {
     let mut out_dir = current_dir().unwrap();
@@ -15,4 +15,5 @@ fn main() {
     export_schema(&schema_for!(HandleMsg), &out_dir);
     export_schema(&schema_for!(QueryMsg), &out_dir);
     export_schema(&schema_for!(AirdropClaimResponse), &out_dir);
+    export_schema(&schema_for!(RoundResponse), &out_dir);
 }
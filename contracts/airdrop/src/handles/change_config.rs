@@ -1,6 +1,6 @@
 use cosmwasm_std::{log, Api, Env, Extern, HandleResponse, Querier, StdResult, Storage};
 
-use crate::state::Config;
+use crate::state::{set_querier, Config};
 
 use super::NewConfig;
 
@@ -14,13 +14,16 @@ pub fn change_config<S: Storage, A: Api, Q: Querier>(
 
     config.admin = new_config.admin.unwrap_or(config.admin);
     config.confirmer = new_config.confirmer.unwrap_or(config.confirmer);
+    config.treasury = new_config.treasury.unwrap_or(config.treasury);
     config.platform = new_config.platform.unwrap_or(config.platform);
     config.token = new_config.token.unwrap_or(config.token);
+    if let Some(vesting) = new_config.vesting {
+        config.vesting = Some(vesting);
+    }
 
-    // reset quest contract
+    // kept for existing deployment scripts, same as `AddQueriers`
     if let Some(quest_contract) = new_config.quest_contract {
-        config.quest_contract = Some(quest_contract);
-        config.quest_password = None;
+        set_querier(&mut deps.storage, &quest_contract, true)?;
     }
 
     config.save(&mut deps.storage)?;
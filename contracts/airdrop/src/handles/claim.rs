@@ -0,0 +1,130 @@
+use cosmwasm_std::{
+    plaintext_log, Api, Binary, Env, Extern, HandleResponse, Querier, StdError, StdResult, Storage,
+    Uint128,
+};
+
+use crate::merkle::{leaf_hash, to_hash, verify_proof};
+use crate::state::{
+    add_allocation_for_address, get_allocation_for_account, is_claimed, set_claimed, Config, Round,
+};
+use crate::types::deposit::{airdrop_msg, Deposit};
+
+/// Self-service alternative to `confirm_airdrop`. The sender proves its allocation against the
+/// merkle root of the round, and the airdrop is deposited in the platform on its behalf
+pub fn claim<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    config: Config,
+    round: u32,
+    amount: Uint128,
+    proof: Vec<Binary>,
+) -> StdResult<HandleResponse> {
+    let mut round = Round::load(&deps.storage, round)?;
+    round.assert_active(env.block.time)?;
+
+    let root = match &round.merkle_root {
+        Some(root) => to_hash(root)?,
+        None => {
+            return Err(StdError::generic_err(format!(
+                "Merkle root is not set for round {}",
+                round.id
+            )))
+        }
+    };
+
+    let sender = env.message.sender;
+    if is_claimed(&deps.storage, round.id, &sender.0) {
+        return Err(StdError::generic_err(format!(
+            "Airdrop for {} was already claimed in round {}",
+            sender, round.id
+        )));
+    }
+
+    if !verify_proof(&root, leaf_hash(&sender, amount), &proof)? {
+        return Err(StdError::generic_err("Invalid merkle proof"));
+    }
+
+    round.add_claimed(amount.u128())?;
+    round.save(&mut deps.storage)?;
+    set_claimed(&mut deps.storage, round.id, &sender.0)?;
+
+    // add total amount to tracker
+    let cur_amount = get_allocation_for_account(&deps.storage, round.id, &sender);
+    add_allocation_for_address(
+        &mut deps.storage,
+        round.id,
+        &sender,
+        cur_amount + amount.u128(),
+    )?;
+
+    let log = vec![plaintext_log("airdropped_to", &sender)];
+    let distribute_msg = airdrop_msg(config, &round, amount, vec![Deposit::new(sender, amount)])?;
+
+    Ok(HandleResponse {
+        messages: vec![distribute_msg],
+        log,
+        data: None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::deposit::batch_deposit_msg;
+    use crate::unittest_utils::{
+        claim_helper, contract, create_round_helper, extract_error_msg, init_helper,
+    };
+    use cosmwasm_std::HumanAddr;
+
+    #[test]
+    fn test_claim() {
+        let mut deps = init_helper();
+        create_round_helper(&mut deps, 1_000, None).unwrap();
+
+        let res = claim_helper(&mut deps, "alice", 0, 100, 150).unwrap();
+        let deposit = Deposit::new(HumanAddr::from("alice"), Uint128(100));
+        let expected_msg = batch_deposit_msg(
+            contract("platform"),
+            contract("token"),
+            Uint128(100),
+            vec![deposit],
+        )
+        .unwrap();
+        assert_eq!(res.messages, vec![expected_msg]);
+
+        assert!(is_claimed(&deps.storage, 0, "alice"));
+        assert!(!is_claimed(&deps.storage, 0, "bob"));
+        let alice = HumanAddr::from("alice");
+        assert_eq!(get_allocation_for_account(&deps.storage, 0, &alice), 100);
+        assert_eq!(Round::load(&deps.storage, 0).unwrap().claimed, Uint128(100));
+    }
+
+    #[test]
+    fn test_claim_invalid_proof() {
+        let mut deps = init_helper();
+        create_round_helper(&mut deps, 1_000, None).unwrap();
+
+        assert_eq!(
+            extract_error_msg(claim_helper(&mut deps, "alice", 0, 200, 150)),
+            "Invalid merkle proof"
+        );
+        assert_eq!(
+            extract_error_msg(claim_helper(&mut deps, "dave", 0, 100, 150)),
+            "Invalid merkle proof"
+        );
+        assert!(!is_claimed(&deps.storage, 0, "alice"));
+    }
+
+    #[test]
+    fn test_claim_twice() {
+        let mut deps = init_helper();
+        create_round_helper(&mut deps, 1_000, None).unwrap();
+
+        claim_helper(&mut deps, "alice", 0, 100, 150).unwrap();
+        assert_eq!(
+            extract_error_msg(claim_helper(&mut deps, "alice", 0, 100, 160)),
+            "Airdrop for alice was already claimed in round 0"
+        );
+        assert_eq!(Round::load(&deps.storage, 0).unwrap().claimed, Uint128(100));
+    }
+}
@@ -0,0 +1,132 @@
+use cosmwasm_std::{
+    log, Api, Binary, Env, Extern, HandleResponse, Querier, StdError, StdResult, Storage, Uint128,
+};
+
+use crate::merkle::to_hash;
+use crate::state::{Config, Round};
+use crate::types::vesting::VestingOptions;
+
+/// Opens a new airdrop round. Claim state is kept separately for each round, so the same
+/// address can receive an airdrop once per round
+#[allow(clippy::too_many_arguments)]
+pub fn create_round<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    config: Config,
+    budget: Uint128,
+    start_time: u64,
+    end_time: u64,
+    merkle_root: Option<Binary>,
+    vesting: Option<VestingOptions>,
+) -> StdResult<HandleResponse> {
+    config.assert_admin(&env.message.sender)?;
+
+    if let Some(root) = &merkle_root {
+        to_hash(root)?;
+    }
+
+    if let Some(options) = &vesting {
+        options.validate()?;
+        if config.vesting.is_none() {
+            return Err(StdError::generic_err(
+                "Cannot create a vested round before vesting contract is set up",
+            ));
+        }
+    }
+
+    let round = Round::create(
+        &mut deps.storage,
+        budget,
+        start_time,
+        end_time,
+        merkle_root,
+        vesting,
+    )?;
+
+    Ok(HandleResponse {
+        log: vec![log("created_round", round.id)],
+        ..Default::default()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::handles::{handle, HandleMsg};
+    use crate::state::is_claimed;
+    use crate::unittest_utils::{
+        claim_helper, create_round_helper, extract_error_msg, init_helper, mock_env_with_time,
+    };
+
+    #[test]
+    fn test_create_round() {
+        let mut deps = init_helper();
+
+        let msg = HandleMsg::CreateRound {
+            budget: Uint128(1_000),
+            start_time: 200,
+            end_time: 200,
+            merkle_root: None,
+            vesting: None,
+        };
+        assert_eq!(
+            extract_error_msg(handle(&mut deps, mock_env_with_time("admin", 0), msg)),
+            "Round must end after it starts"
+        );
+
+        let res = create_round_helper(&mut deps, 1_000, None).unwrap();
+        assert_eq!(res.log, vec![log("created_round", 0)]);
+        let res = create_round_helper(&mut deps, 1_000, None).unwrap();
+        assert_eq!(res.log, vec![log("created_round", 1)]);
+    }
+
+    #[test]
+    fn test_claims_per_round() {
+        let mut deps = init_helper();
+        create_round_helper(&mut deps, 1_000, None).unwrap();
+        create_round_helper(&mut deps, 1_000, None).unwrap();
+
+        claim_helper(&mut deps, "alice", 0, 100, 150).unwrap();
+        assert!(!is_claimed(&deps.storage, 1, "alice"));
+        claim_helper(&mut deps, "alice", 1, 100, 150).unwrap();
+        assert!(claim_helper(&mut deps, "alice", 1, 100, 150).is_err());
+
+        assert_eq!(Round::load(&deps.storage, 0).unwrap().claimed, Uint128(100));
+        assert_eq!(Round::load(&deps.storage, 1).unwrap().claimed, Uint128(100));
+    }
+
+    #[test]
+    fn test_round_budget() {
+        let mut deps = init_helper();
+        create_round_helper(&mut deps, 350, None).unwrap();
+
+        claim_helper(&mut deps, "alice", 0, 100, 150).unwrap();
+        claim_helper(&mut deps, "bob", 0, 200, 150).unwrap();
+        assert_eq!(
+            extract_error_msg(claim_helper(&mut deps, "carol", 0, 300, 150)),
+            "Airdrop would exceed the budget of round 0"
+        );
+        assert!(!is_claimed(&deps.storage, 0, "carol"));
+        assert_eq!(
+            Round::load(&deps.storage, 0).unwrap().unclaimed(),
+            Uint128(50)
+        );
+    }
+
+    #[test]
+    fn test_round_expiry() {
+        let mut deps = init_helper();
+        create_round_helper(&mut deps, 1_000, None).unwrap();
+
+        assert_eq!(
+            extract_error_msg(claim_helper(&mut deps, "alice", 0, 100, 99)),
+            "Round 0 has not started yet"
+        );
+        assert_eq!(
+            extract_error_msg(claim_helper(&mut deps, "alice", 0, 100, 200)),
+            "Round 0 has ended"
+        );
+        claim_helper(&mut deps, "alice", 0, 100, 100).unwrap();
+        claim_helper(&mut deps, "bob", 0, 200, 199).unwrap();
+    }
+}
@@ -0,0 +1,27 @@
+use cosmwasm_std::{log, Api, Env, Extern, HandleResponse, Querier, StdError, StdResult, Storage};
+
+use crate::migration::{load_admin, migrate, STORAGE_VERSION};
+
+/// Upgrades the storage left by an older version of the contract. Can't take the `Config`, as it
+/// can only be loaded once the storage is up to date
+pub fn migrate_storage<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+) -> StdResult<HandleResponse> {
+    if env.message.sender != load_admin(&deps.storage)? {
+        return Err(StdError::generic_err(format!(
+            "Address {} is not allowed to perform this operation",
+            env.message.sender
+        )));
+    }
+
+    let from = migrate(&mut deps.storage)?;
+
+    Ok(HandleResponse {
+        log: vec![
+            log("migrated_from", from),
+            log("migrated_to", STORAGE_VERSION),
+        ],
+        ..Default::default()
+    })
+}
@@ -1,41 +1,106 @@
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
-use cosmwasm_std::{Api, Env, Extern, HandleResponse, HumanAddr, Querier, StdResult, Storage};
+use cosmwasm_std::{
+    Api, Binary, Env, Extern, HandleResponse, HumanAddr, Querier, StdResult, Storage, Uint128,
+};
 
+use crate::migration::require_current_version;
 use crate::state::Config;
 use crate::types::airdrop::AirdropClaimSubmit;
 use crate::types::secret_contract::SecretContract;
+use crate::types::vesting::VestingOptions;
 
 mod confirm_airdrop;
 use confirm_airdrop::confirm_airdrop;
 
 mod change_config;
-mod set_quest_password;
-
-use crate::handles::set_quest_password::handle_set_quest_password;
 use change_config::change_config;
 
+mod queriers;
+use queriers::{add_queriers, remove_queriers, set_querier_key};
+
 mod set_airdrop_vk;
 use set_airdrop_vk::set_airdrop_vk;
 
+mod claim;
+use claim::claim;
+
+mod set_merkle_root;
+use set_merkle_root::handle_set_merkle_root;
+
+mod create_round;
+use create_round::create_round;
+
+mod reclaim_unclaimed;
+use reclaim_unclaimed::reclaim_unclaimed;
+
+mod migrate;
+use migrate::migrate_storage;
+
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 #[serde(rename_all = "snake_case")]
 pub struct NewConfig {
     admin: Option<HumanAddr>,
     confirmer: Option<HumanAddr>,
+    treasury: Option<HumanAddr>,
     platform: Option<SecretContract>,
     token: Option<SecretContract>,
+    vesting: Option<SecretContract>,
+    /// Deprecated: registered as a querier. Use `AddQueriers` instead
     quest_contract: Option<HumanAddr>,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 #[serde(rename_all = "snake_case")]
 pub enum HandleMsg {
-    ConfirmAirdrop { airdrops: Vec<AirdropClaimSubmit> },
+    ConfirmAirdrop {
+        round: u32,
+        airdrops: Vec<AirdropClaimSubmit>,
+    },
     ChangeConfig(NewConfig),
-    SetQuestPassword { password: String },
+    /// Allow contracts to read allocations through `GetAccountInfo`.
+    ///
+    /// This replaces the quest password: once the quest tracker is added here, registering
+    /// the airdrop contract again in the quest tracker makes it set its querier key
+    AddQueriers {
+        addresses: Vec<HumanAddr>,
+    },
+    RemoveQueriers {
+        addresses: Vec<HumanAddr>,
+    },
+    /// Set the key an authorized querier uses to authenticate its queries
+    SetQuerierKey {
+        key: String,
+    },
     SetAirdropVk(String),
+    /// Open a new round that can airdrop up to `budget` between `start_time` and `end_time`.
+    /// If `vesting` is set, airdrops are vested instead of deposited in the platform
+    CreateRound {
+        budget: Uint128,
+        start_time: u64,
+        end_time: u64,
+        merkle_root: Option<Binary>,
+        vesting: Option<VestingOptions>,
+    },
+    /// Set the sha256 merkle root of all `(address, amount)` allocations that can be claimed
+    SetMerkleRoot {
+        round: u32,
+        root: Binary,
+    },
+    /// Claim the sender's allocation by proving it against the merkle root of the round
+    Claim {
+        round: u32,
+        amount: Uint128,
+        proof: Vec<Binary>,
+    },
+    /// Send the unclaimed budget of a round that ended to the treasury
+    ReclaimUnclaimed {
+        round: u32,
+    },
+    /// Upgrade the storage of an older version of the contract. Every other message fails until
+    /// the admin did it
+    Migrate {},
 }
 
 pub fn handle<S: Storage, A: Api, Q: Querier>(
@@ -45,12 +110,43 @@ pub fn handle<S: Storage, A: Api, Q: Querier>(
 ) -> StdResult<HandleResponse> {
     use HandleMsg::*;
 
+    if let Migrate {} = msg {
+        return migrate_storage(deps, env);
+    }
+
+    require_current_version(&deps.storage)?;
     let config = Config::load(&deps.storage)?;
 
     match msg {
-        ConfirmAirdrop { airdrops } => confirm_airdrop(deps, env, config, airdrops),
+        ConfirmAirdrop { round, airdrops } => confirm_airdrop(deps, env, config, round, airdrops),
         ChangeConfig(new_config) => change_config(deps, env, config, new_config),
         SetAirdropVk(viewing_key) => set_airdrop_vk(env, config, viewing_key),
-        SetQuestPassword { password } => handle_set_quest_password(deps, env, config, password),
+        AddQueriers { addresses } => add_queriers(deps, env, config, addresses),
+        RemoveQueriers { addresses } => remove_queriers(deps, env, config, addresses),
+        SetQuerierKey { key } => set_querier_key(deps, env, key),
+        CreateRound {
+            budget,
+            start_time,
+            end_time,
+            merkle_root,
+            vesting,
+        } => create_round(
+            deps,
+            env,
+            config,
+            budget,
+            start_time,
+            end_time,
+            merkle_root,
+            vesting,
+        ),
+        SetMerkleRoot { round, root } => handle_set_merkle_root(deps, env, config, round, root),
+        Claim {
+            round,
+            amount,
+            proof,
+        } => claim(deps, env, config, round, amount, proof),
+        ReclaimUnclaimed { round } => reclaim_unclaimed(deps, env, config, round),
+        Migrate {} => unreachable!("storage migrations are handled before loading the config"),
     }
 }
@@ -0,0 +1,56 @@
+use cosmwasm_std::{log, Api, Env, Extern, HandleResponse, HumanAddr, Querier, StdResult, Storage};
+use secret_toolkit::viewing_key::{ViewingKey, ViewingKeyStore};
+
+use crate::state::{assert_querier, set_querier, Config};
+
+pub fn add_queriers<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    config: Config,
+    addresses: Vec<HumanAddr>,
+) -> StdResult<HandleResponse> {
+    config.assert_admin(&env.message.sender)?;
+
+    for address in addresses {
+        set_querier(&mut deps.storage, &address, true)?;
+    }
+
+    Ok(HandleResponse {
+        log: vec![log("changed", "queriers")],
+        ..Default::default()
+    })
+}
+
+pub fn remove_queriers<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    config: Config,
+    addresses: Vec<HumanAddr>,
+) -> StdResult<HandleResponse> {
+    config.assert_admin(&env.message.sender)?;
+
+    for address in addresses {
+        set_querier(&mut deps.storage, &address, false)?;
+    }
+
+    Ok(HandleResponse {
+        log: vec![log("changed", "queriers")],
+        ..Default::default()
+    })
+}
+
+/// Only authorized queriers can set a key, so a key alone is never enough to read allocations
+pub fn set_querier_key<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    key: String,
+) -> StdResult<HandleResponse> {
+    assert_querier(&deps.storage, &env.message.sender)?;
+
+    ViewingKey::set(&mut deps.storage, &env.message.sender, &key);
+
+    Ok(HandleResponse {
+        log: vec![log("changed", "querier key")],
+        ..Default::default()
+    })
+}
@@ -0,0 +1,112 @@
+use cosmwasm_std::{log, Api, Env, Extern, HandleResponse, Querier, StdError, StdResult, Storage};
+
+use crate::state::{Config, Round};
+
+/// Sends whatever is left of the budget of a round that ended to the treasury
+pub fn reclaim_unclaimed<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    config: Config,
+    round: u32,
+) -> StdResult<HandleResponse> {
+    config.assert_admin(&env.message.sender)?;
+
+    let mut round = Round::load(&deps.storage, round)?;
+    if env.block.time < round.end_time {
+        return Err(StdError::generic_err(format!(
+            "Round {} has not ended yet",
+            round.id
+        )));
+    }
+    if round.reclaimed {
+        return Err(StdError::generic_err(format!(
+            "Round {} was already reclaimed",
+            round.id
+        )));
+    }
+
+    round.reclaimed = true;
+    round.save(&mut deps.storage)?;
+
+    let amount = round.unclaimed();
+    let mut messages = vec![];
+    if amount.u128() > 0 {
+        messages.push(secret_toolkit::snip20::transfer_msg(
+            config.treasury,
+            amount,
+            None,
+            None,
+            64,
+            config.token.hash,
+            config.token.address,
+        )?);
+    }
+
+    Ok(HandleResponse {
+        messages,
+        log: vec![log("reclaimed", amount)],
+        data: None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::handles::{handle, HandleMsg};
+    use crate::unittest_utils::{
+        claim_helper, create_round_helper, extract_error_msg, init_helper, mock_env_with_time,
+    };
+    use cosmwasm_std::{HumanAddr, Uint128};
+
+    fn reclaim_helper<S: Storage, A: Api, Q: Querier>(
+        deps: &mut Extern<S, A, Q>,
+        sender: &str,
+        time: u64,
+    ) -> StdResult<HandleResponse> {
+        let msg = HandleMsg::ReclaimUnclaimed { round: 0 };
+        handle(deps, mock_env_with_time(sender, time), msg)
+    }
+
+    #[test]
+    fn test_reclaim_unclaimed() {
+        let mut deps = init_helper();
+        create_round_helper(&mut deps, 1_000, None).unwrap();
+        claim_helper(&mut deps, "alice", 0, 100, 150).unwrap();
+
+        assert_eq!(
+            extract_error_msg(reclaim_helper(&mut deps, "admin", 199)),
+            "Round 0 has not ended yet"
+        );
+        assert!(reclaim_helper(&mut deps, "alice", 200).is_err());
+
+        let res = reclaim_helper(&mut deps, "admin", 200).unwrap();
+        let expected_msg = secret_toolkit::snip20::transfer_msg(
+            HumanAddr::from("treasury"),
+            Uint128(900),
+            None,
+            None,
+            64,
+            "token_hash".to_string(),
+            HumanAddr::from("token"),
+        )
+        .unwrap();
+        assert_eq!(res.messages, vec![expected_msg]);
+        assert!(Round::load(&deps.storage, 0).unwrap().reclaimed);
+
+        assert_eq!(
+            extract_error_msg(reclaim_helper(&mut deps, "admin", 300)),
+            "Round 0 was already reclaimed"
+        );
+    }
+
+    #[test]
+    fn test_reclaim_fully_claimed_round() {
+        let mut deps = init_helper();
+        create_round_helper(&mut deps, 100, None).unwrap();
+        claim_helper(&mut deps, "alice", 0, 100, 150).unwrap();
+
+        let res = reclaim_helper(&mut deps, "admin", 200).unwrap();
+        assert!(res.messages.is_empty());
+        assert_eq!(res.log, vec![log("reclaimed", Uint128(0))]);
+    }
+}
@@ -0,0 +1,25 @@
+use cosmwasm_std::{log, Api, Binary, Env, Extern, HandleResponse, Querier, StdResult, Storage};
+
+use crate::merkle::to_hash;
+use crate::state::{Config, Round};
+
+pub fn handle_set_merkle_root<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    config: Config,
+    round: u32,
+    root: Binary,
+) -> StdResult<HandleResponse> {
+    config.assert_admin(&env.message.sender)?;
+
+    to_hash(&root)?;
+
+    let mut round = Round::load(&deps.storage, round)?;
+    round.merkle_root = Some(root);
+    round.save(&mut deps.storage)?;
+
+    Ok(HandleResponse {
+        log: vec![log("changed", "merkle root")],
+        ..Default::default()
+    })
+}
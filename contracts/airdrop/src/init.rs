@@ -1,7 +1,8 @@
 use schemars::JsonSchema;
 use serde::Deserialize;
 
-use crate::state::Config;
+use crate::migration::{save_version, STORAGE_VERSION};
+use crate::state::{set_querier, Config};
 use cosmwasm_std::{Api, Env, Extern, HumanAddr, InitResponse, Querier, StdResult, Storage};
 
 use crate::types::secret_contract::SecretContract;
@@ -9,8 +10,15 @@ use crate::types::secret_contract::SecretContract;
 #[derive(Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 pub struct InitMsg {
     pub admin: Option<HumanAddr>,
+    /// Receives the unclaimed budget of rounds that ended. Defaults to the admin
+    pub treasury: Option<HumanAddr>,
     pub platform: SecretContract,
     pub token: SecretContract,
+    pub vesting: Option<SecretContract>,
+    /// Contracts allowed to read allocations through `GetAccountInfo`
+    #[serde(default)]
+    pub queriers: Vec<HumanAddr>,
+    /// Deprecated: registered as a querier. Use `queriers` instead
     pub quest_contract: Option<HumanAddr>,
 }
 
@@ -21,16 +29,23 @@ pub fn init<S: Storage, A: Api, Q: Querier>(
 ) -> StdResult<InitResponse> {
     let clone_sender = || env.message.sender.clone();
     let admin = msg.admin.clone().unwrap_or_else(clone_sender);
-    let confirmer = msg.admin.unwrap_or_else(clone_sender);
+    let confirmer = msg.admin.clone().unwrap_or_else(clone_sender);
+    let treasury = msg.treasury.or(msg.admin).unwrap_or_else(clone_sender);
 
     Config::new(
         admin,
         confirmer,
+        treasury,
         msg.platform,
         msg.token,
-        msg.quest_contract,
+        msg.vesting,
     )
     .save(&mut deps.storage)?;
+    save_version(&mut deps.storage, STORAGE_VERSION)?;
+
+    for querier in msg.queriers.iter().chain(msg.quest_contract.iter()) {
+        set_querier(&mut deps.storage, querier, true)?;
+    }
 
     Ok(InitResponse::default())
 }
@@ -1,15 +1,21 @@
 mod handles;
 mod init;
+mod merkle;
+mod migration;
 mod queries;
 mod state;
 mod types;
+#[cfg(test)]
+mod unittest_utils;
 
 pub use handles::handle;
 pub use handles::HandleMsg;
 pub use init::init;
 pub use init::InitMsg;
 pub use queries::query;
-pub use queries::{AccountInfo, AccountInfoResponse, AirdropClaimResponse, QueryMsg};
+pub use queries::{
+    AccountInfo, AccountInfoResponse, AirdropClaimResponse, QueryMsg, RoundResponse,
+};
 pub use types::deposit::{Deposit, LgndReceiveMsg};
 
 pub use crate as contract;
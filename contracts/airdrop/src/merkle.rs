@@ -0,0 +1,89 @@
+use sha2::{Digest, Sha256};
+
+use cosmwasm_std::{Binary, HumanAddr, StdError, StdResult, Uint128};
+
+pub const HASH_SIZE: usize = 32;
+
+/// Leaves are `sha256("{address}{amount}")`, e.g. `sha256("secret1...1000000")`
+pub fn leaf_hash(address: &HumanAddr, amount: Uint128) -> [u8; HASH_SIZE] {
+    Sha256::digest(format!("{}{}", address, amount).as_bytes()).into()
+}
+
+/// Walks the proof up to the root. Each pair of nodes is sorted before hashing, so the proof
+/// does not need to carry left/right positions
+pub fn verify_proof(
+    root: &[u8; HASH_SIZE],
+    leaf: [u8; HASH_SIZE],
+    proof: &[Binary],
+) -> StdResult<bool> {
+    let mut node = leaf;
+
+    for sibling in proof {
+        let sibling = to_hash(sibling)?;
+
+        let mut hasher = Sha256::new();
+        if node <= sibling {
+            hasher.update(node);
+            hasher.update(sibling);
+        } else {
+            hasher.update(sibling);
+            hasher.update(node);
+        }
+        node = hasher.finalize().into();
+    }
+
+    Ok(&node == root)
+}
+
+pub fn to_hash(bytes: &Binary) -> StdResult<[u8; HASH_SIZE]> {
+    if bytes.len() != HASH_SIZE {
+        return Err(StdError::generic_err(format!(
+            "Expected a {} byte sha256 hash, got {} bytes",
+            HASH_SIZE,
+            bytes.len()
+        )));
+    }
+
+    let mut hash = [0_u8; HASH_SIZE];
+    hash.copy_from_slice(bytes.as_slice());
+    Ok(hash)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::unittest_utils::{binary, proof, ALICE_LEAF, MERKLE_ROOT};
+
+    fn root() -> [u8; HASH_SIZE] {
+        to_hash(&binary(MERKLE_ROOT)).unwrap()
+    }
+
+    #[test]
+    fn test_leaf_hash() {
+        let leaf = leaf_hash(&HumanAddr::from("alice"), Uint128(100));
+        assert_eq!(leaf, to_hash(&binary(ALICE_LEAF)).unwrap());
+    }
+
+    #[test]
+    fn test_verify_known_proofs() {
+        for (address, amount) in [("alice", 100), ("bob", 200), ("carol", 300)] {
+            let leaf = leaf_hash(&HumanAddr::from(address), Uint128(amount));
+            assert!(verify_proof(&root(), leaf, &proof(address)).unwrap());
+        }
+    }
+
+    #[test]
+    fn test_verify_bad_proofs() {
+        let alice = HumanAddr::from("alice");
+
+        // wrong amount, someone else's proof, incomplete proof
+        let leaf = leaf_hash(&alice, Uint128(101));
+        assert!(!verify_proof(&root(), leaf, &proof("alice")).unwrap());
+        let leaf = leaf_hash(&alice, Uint128(100));
+        assert!(!verify_proof(&root(), leaf, &proof("carol")).unwrap());
+        assert!(!verify_proof(&root(), leaf, &proof("alice")[..1]).unwrap());
+
+        let short_sibling = vec![Binary(vec![0; HASH_SIZE - 1])];
+        assert!(verify_proof(&root(), leaf, &short_sibling).is_err());
+    }
+}
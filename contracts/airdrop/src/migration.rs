@@ -0,0 +1,195 @@
+use cosmwasm_std::{HumanAddr, ReadonlyStorage, StdError, StdResult, Storage};
+use secret_toolkit::storage::{TypedStore, TypedStoreMut};
+use serde::{Deserialize, Serialize};
+
+use crate::state::{set_querier, Config};
+use crate::types::secret_contract::SecretContract;
+
+const KEY_STORAGE_VERSION: &[u8] = b"storage_version";
+
+/// Version of the storage layout this code works with. Contracts instantiated before airdrop
+/// rounds have no version stored, which is version 0
+pub const STORAGE_VERSION: u32 = 1;
+
+/// `Config` as stored before airdrop rounds
+#[derive(Serialize, Deserialize)]
+struct ConfigV0 {
+    admin: HumanAddr,
+    confirmer: HumanAddr,
+    platform: SecretContract,
+    token: SecretContract,
+    quest_password: Option<String>,
+    quest_contract: Option<HumanAddr>,
+}
+
+impl ConfigV0 {
+    fn load<S: ReadonlyStorage>(store: &S) -> StdResult<Self> {
+        TypedStore::attach(store).load(b"config")
+    }
+}
+
+pub fn load_version<S: ReadonlyStorage>(storage: &S) -> StdResult<u32> {
+    Ok(TypedStore::attach(storage)
+        .may_load(KEY_STORAGE_VERSION)?
+        .unwrap_or_default())
+}
+
+pub fn save_version<S: Storage>(storage: &mut S, version: u32) -> StdResult<()> {
+    TypedStoreMut::attach(storage).store(KEY_STORAGE_VERSION, &version)
+}
+
+/// Handles (but `Migrate`) require the storage to be up to date, `Config::load` fails on an older
+/// layout
+pub fn require_current_version<S: ReadonlyStorage>(storage: &S) -> StdResult<()> {
+    let version = load_version(storage)?;
+    if version != STORAGE_VERSION {
+        return Err(StdError::generic_err(format!(
+            "storage is at version {}, expected {}. The admin has to migrate it first",
+            version, STORAGE_VERSION
+        )));
+    }
+
+    Ok(())
+}
+
+/// The admin, read with the layout of the stored version
+pub fn load_admin<S: ReadonlyStorage>(storage: &S) -> StdResult<HumanAddr> {
+    match load_version(storage)? {
+        0 => Ok(ConfigV0::load(storage)?.admin),
+        1 => Ok(Config::load(storage)?.admin),
+        version => Err(unknown_version(version)),
+    }
+}
+
+/// Upgrades the storage one version at a time up to `STORAGE_VERSION`. Returns the version it
+/// started from
+pub fn migrate<S: Storage>(storage: &mut S) -> StdResult<u32> {
+    let from = load_version(storage)?;
+    if from > STORAGE_VERSION {
+        return Err(unknown_version(from));
+    }
+
+    for version in from..STORAGE_VERSION {
+        match version {
+            0 => migrate_v0_to_v1(storage)?,
+            _ => return Err(unknown_version(version)),
+        }
+        save_version(storage, version + 1)?;
+    }
+
+    Ok(from)
+}
+
+fn unknown_version(version: u32) -> StdError {
+    StdError::generic_err(format!("can't migrate from storage version {}", version))
+}
+
+/// Adds the rounds to the config, with the admin as treasury. The quest password is dropped, and
+/// the quest contract becomes an authorized querier that has to set its querier key.
+///
+/// The claim flags and allocations stored before rounds can't be enumerated to copy them, so
+/// they stay where they are and `is_claimed` and `get_allocation_for_account` read them as the
+/// ones of round 0, which continues the airdrop from before rounds
+fn migrate_v0_to_v1<S: Storage>(storage: &mut S) -> StdResult<()> {
+    let legacy = ConfigV0::load(storage)?;
+    if let Some(quest_contract) = &legacy.quest_contract {
+        set_querier(storage, quest_contract, true)?;
+    }
+
+    Config::new(
+        legacy.admin.clone(),
+        legacy.confirmer,
+        legacy.admin,
+        legacy.platform,
+        legacy.token,
+        None,
+    )
+    .save(storage)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::handles::{handle, HandleMsg};
+    use crate::state::{
+        get_allocation_for_account, is_claimed, is_querier, ACCOUNT_TOTALS, AIRDROP_CLAIMS,
+    };
+    use crate::unittest_utils::{contract, extract_error_msg, mock_env_with_time};
+    use cosmwasm_std::testing::mock_dependencies;
+    use cosmwasm_storage::PrefixedStorage;
+
+    #[test]
+    fn test_migrate_v0() {
+        let mut deps = mock_dependencies(20, &[]);
+        let legacy = ConfigV0 {
+            admin: HumanAddr::from("admin"),
+            confirmer: HumanAddr::from("confirmer"),
+            platform: contract("platform"),
+            token: contract("token"),
+            quest_password: Some("password".to_string()),
+            quest_contract: Some(HumanAddr::from("quest")),
+        };
+        TypedStoreMut::attach(&mut deps.storage)
+            .store(b"config", &legacy)
+            .unwrap();
+        // claims and allocations were keyed by the address alone
+        let mut claims = PrefixedStorage::new(AIRDROP_CLAIMS, &mut deps.storage);
+        TypedStoreMut::attach(&mut claims)
+            .store(b"alice", &true)
+            .unwrap();
+        let mut totals = PrefixedStorage::new(ACCOUNT_TOTALS, &mut deps.storage);
+        TypedStoreMut::attach(&mut totals)
+            .store(b"alice", &100_u128)
+            .unwrap();
+
+        let msg = HandleMsg::AddQueriers { addresses: vec![] };
+        assert_eq!(
+            extract_error_msg(handle(&mut deps, mock_env_with_time("admin", 0), msg)),
+            "storage is at version 0, expected 1. The admin has to migrate it first"
+        );
+        assert_eq!(
+            extract_error_msg(handle(
+                &mut deps,
+                mock_env_with_time("confirmer", 0),
+                HandleMsg::Migrate {}
+            )),
+            "Address confirmer is not allowed to perform this operation"
+        );
+
+        handle(
+            &mut deps,
+            mock_env_with_time("admin", 0),
+            HandleMsg::Migrate {},
+        )
+        .unwrap();
+        assert_eq!(load_version(&deps.storage).unwrap(), STORAGE_VERSION);
+        assert_eq!(
+            Config::load(&deps.storage).unwrap(),
+            Config::new(
+                HumanAddr::from("admin"),
+                HumanAddr::from("confirmer"),
+                HumanAddr::from("admin"),
+                contract("platform"),
+                contract("token"),
+                None,
+            )
+        );
+        assert!(is_querier(&deps.storage, &HumanAddr::from("quest")));
+
+        // the airdrop from before rounds continues as round 0
+        let alice = HumanAddr::from("alice");
+        assert!(is_claimed(&deps.storage, 0, "alice"));
+        assert!(!is_claimed(&deps.storage, 1, "alice"));
+        assert_eq!(get_allocation_for_account(&deps.storage, 0, &alice), 100);
+        assert_eq!(get_allocation_for_account(&deps.storage, 1, &alice), 0);
+
+        // migrating again is a no-op
+        let res = handle(
+            &mut deps,
+            mock_env_with_time("admin", 0),
+            HandleMsg::Migrate {},
+        )
+        .unwrap();
+        assert_eq!(res.log[0], cosmwasm_std::log("migrated_from", 1));
+    }
+}
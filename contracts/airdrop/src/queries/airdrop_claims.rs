@@ -6,9 +6,10 @@ use super::AirdropClaimResponse;
 
 pub fn query_airdrop_claims<S: Storage, A: Api, Q: Querier>(
     deps: &Extern<S, A, Q>,
+    round: u32,
     address: String,
 ) -> StdResult<AirdropClaimResponse> {
-    let result = is_claimed(&deps.storage, &address);
+    let result = is_claimed(&deps.storage, round, &address);
 
     Ok(AirdropClaimResponse { claimed: result })
 }
@@ -1,17 +1,20 @@
 use crate::queries::{AccountInfo, AccountInfoResponse};
-use crate::state::{get_allocation_for_account, Config};
+use crate::state::{assert_querier, get_allocation_for_account};
 use cosmwasm_std::{Api, Extern, HumanAddr, Querier, StdResult, Storage, Uint128};
+use secret_toolkit::viewing_key::{ViewingKey, ViewingKeyStore};
 
 pub fn query_get_account_info<S: Storage, A: Api, Q: Querier>(
     deps: &Extern<S, A, Q>,
-    password: String,
+    round: u32,
+    querier: HumanAddr,
+    key: String,
     address: HumanAddr,
 ) -> StdResult<AccountInfoResponse> {
-    let config = Config::load(&deps.storage)?;
-    config.assert_password(&password)?;
+    assert_querier(&deps.storage, &querier)?;
+    ViewingKey::check(&deps.storage, &querier, &key)?;
 
     let account = AccountInfo {
-        amount: Uint128(get_allocation_for_account(&deps.storage, &address)),
+        amount: Uint128(get_allocation_for_account(&deps.storage, round, &address)),
         address,
     };
 
@@ -25,3 +28,71 @@ pub fn query_get_account_info<S: Storage, A: Api, Q: Querier>(
 
     Ok(AccountInfoResponse { account })
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::handles::{handle, HandleMsg};
+    use crate::queries::{query, QueryMsg};
+    use crate::unittest_utils::{
+        claim_helper, create_round_helper, extract_error_msg, init_helper, mock_env_with_time,
+    };
+    use cosmwasm_std::from_binary;
+
+    fn account_info<S: Storage, A: Api, Q: Querier>(
+        deps: &Extern<S, A, Q>,
+        querier: &str,
+        key: &str,
+    ) -> StdResult<u128> {
+        let msg = QueryMsg::GetAccountInfo {
+            round: 0,
+            querier: HumanAddr::from(querier),
+            key: key.to_string(),
+            address: HumanAddr::from("alice"),
+        };
+        let response: AccountInfoResponse = from_binary(&query(deps, msg)?)?;
+        Ok(response.account.amount.u128())
+    }
+
+    #[test]
+    fn test_querier_auth() {
+        let mut deps = init_helper();
+        create_round_helper(&mut deps, 1_000, None).unwrap();
+        claim_helper(&mut deps, "alice", 0, 100, 150).unwrap();
+
+        // only authorized queriers can set a key
+        let set_key = |key: &str| HandleMsg::SetQuerierKey {
+            key: key.to_string(),
+        };
+        assert_eq!(
+            extract_error_msg(handle(
+                &mut deps,
+                mock_env_with_time("quest", 0),
+                set_key("key")
+            )),
+            "Address quest is not an authorized querier"
+        );
+        assert_eq!(
+            extract_error_msg(account_info(&deps, "quest", "key")),
+            "Address quest is not an authorized querier"
+        );
+
+        let msg = HandleMsg::AddQueriers {
+            addresses: vec![HumanAddr::from("quest")],
+        };
+        assert!(handle(&mut deps, mock_env_with_time("quest", 0), msg.clone()).is_err());
+        handle(&mut deps, mock_env_with_time("admin", 0), msg).unwrap();
+        assert!(account_info(&deps, "quest", "key").is_err());
+
+        handle(&mut deps, mock_env_with_time("quest", 0), set_key("key")).unwrap();
+        assert_eq!(account_info(&deps, "quest", "key").unwrap(), 100);
+        assert!(account_info(&deps, "quest", "other key").is_err());
+
+        // the key stops working once the querier is removed
+        let msg = HandleMsg::RemoveQueriers {
+            addresses: vec![HumanAddr::from("quest")],
+        };
+        handle(&mut deps, mock_env_with_time("admin", 0), msg).unwrap();
+        assert!(account_info(&deps, "quest", "key").is_err());
+    }
+}
@@ -0,0 +1,14 @@
+use cosmwasm_std::{Api, Extern, Querier, StdResult, Storage};
+
+use crate::state::Round;
+
+use super::RoundResponse;
+
+pub fn query_get_round<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    round: u32,
+) -> StdResult<RoundResponse> {
+    let round = Round::load(&deps.storage, round)?;
+
+    Ok(RoundResponse { round })
+}
@@ -7,22 +7,31 @@ use cosmwasm_std::{
 
 mod airdrop_claims;
 mod get_account_info;
+mod get_round;
 
 use crate::queries::get_account_info::query_get_account_info;
+use crate::state::Round;
 use airdrop_claims::query_airdrop_claims;
+use get_round::query_get_round;
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 #[serde(rename_all = "snake_case")]
 pub enum QueryMsg {
     AirdropWasClaimed {
+        round: u32,
         address: String,
     },
-    /// Function that reads airdrop numbers for each account. Can only be read by the quest contract,
-    /// which sets a password that only it knows
+    /// Function that reads airdrop numbers for each account. Can only be read by authorized
+    /// queriers, such as the quest contract, using the key they set with `SetQuerierKey`
     GetAccountInfo {
-        password: String,
+        round: u32,
+        querier: HumanAddr,
+        key: String,
         address: HumanAddr,
     },
+    GetRound {
+        round: u32,
+    },
 }
 
 // We define a custom struct for each query response
@@ -31,6 +40,11 @@ pub struct AirdropClaimResponse {
     pub claimed: bool,
 }
 
+#[derive(Serialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct RoundResponse {
+    pub round: Round,
+}
+
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 pub struct AccountInfoResponse {
     pub account: AccountInfo,
@@ -47,9 +61,15 @@ pub fn query<S: Storage, A: Api, Q: Querier>(
     msg: QueryMsg,
 ) -> StdResult<Binary> {
     match msg {
-        QueryMsg::AirdropWasClaimed { address } => to_binary(&query_airdrop_claims(deps, address)?),
-        QueryMsg::GetAccountInfo { password, address } => {
-            to_binary(&query_get_account_info(deps, password, address)?)
+        QueryMsg::AirdropWasClaimed { round, address } => {
+            to_binary(&query_airdrop_claims(deps, round, address)?)
         }
+        QueryMsg::GetAccountInfo {
+            round,
+            querier,
+            key,
+            address,
+        } => to_binary(&query_get_account_info(deps, round, querier, key, address)?),
+        QueryMsg::GetRound { round } => to_binary(&query_get_round(deps, round)?),
     }
 }
@@ -2,22 +2,28 @@ use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
 use crate::types::secret_contract::SecretContract;
-use cosmwasm_std::{HumanAddr, ReadonlyStorage, StdError, StdResult, Storage};
+use crate::types::vesting::VestingOptions;
+use cosmwasm_std::{Binary, HumanAddr, ReadonlyStorage, StdError, StdResult, Storage, Uint128};
 
 use cosmwasm_storage::{PrefixedStorage, ReadonlyPrefixedStorage};
 use secret_toolkit::storage::{TypedStore, TypedStoreMut};
 
 pub static AIRDROP_CLAIMS: &[u8] = b"airdrop_claims";
 pub static ACCOUNT_TOTALS: &[u8] = b"account_totals";
+pub static ROUNDS: &[u8] = b"rounds";
+pub static ROUND_COUNT: &[u8] = b"round_count";
+pub static QUERIERS: &[u8] = b"queriers";
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 pub struct Config {
     pub admin: HumanAddr,
     pub confirmer: HumanAddr,
+    /// Receives the unclaimed budget of rounds that ended
+    pub treasury: HumanAddr,
     pub platform: SecretContract,
     pub token: SecretContract,
-    pub quest_password: Option<String>,
-    pub quest_contract: Option<HumanAddr>,
+    /// Vesting contract that receives the airdrops of vested rounds
+    pub vesting: Option<SecretContract>,
 }
 
 impl Config {
@@ -26,17 +32,18 @@ impl Config {
     pub fn new(
         admin: HumanAddr,
         confirmer: HumanAddr,
+        treasury: HumanAddr,
         platform: SecretContract,
         token: SecretContract,
-        quest_contract: Option<HumanAddr>,
+        vesting: Option<SecretContract>,
     ) -> Self {
         Self {
             admin,
             confirmer,
+            treasury,
             platform,
             token,
-            quest_password: None,
-            quest_contract,
+            vesting,
         }
     }
 
@@ -58,44 +65,116 @@ impl Config {
         Ok(())
     }
 
-    pub fn assert_quest_contract(&self, address: &HumanAddr) -> StdResult<()> {
-        if let Some(config_quest_contract) = &self.quest_contract {
-            if config_quest_contract == address {
-                return Ok(());
-            }
+    pub fn assert_confirmer(&self, address: &HumanAddr) -> StdResult<()> {
+        if address != &self.confirmer {
+            return Err(StdError::generic_err(format!(
+                "Address {} is not allowed to perform this operation",
+                address
+            )));
         }
-
-        Err(StdError::generic_err(format!(
-            "Address {} is not allowed to perform this operation",
-            address
-        )))
+        Ok(())
     }
+}
 
-    pub fn assert_password(&self, password: &str) -> StdResult<()> {
-        if let Some(config_quest_password) = &self.quest_password {
-            if config_quest_password == password {
-                return Ok(());
-            }
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct Round {
+    pub id: u32,
+    /// Maximum amount that can be airdropped in this round
+    pub budget: Uint128,
+    /// Amount that was airdropped so far
+    pub claimed: Uint128,
+    pub start_time: u64,
+    pub end_time: u64,
+    /// sha256 merkle root of the `(address, amount)` allocations of this round
+    pub merkle_root: Option<Binary>,
+    /// When set, airdrops are vested in the vesting contract instead of deposited in the platform
+    pub vesting: Option<VestingOptions>,
+    /// Set once the unclaimed budget was sent back to the treasury
+    pub reclaimed: bool,
+}
+
+impl Round {
+    pub fn create<S: Storage>(
+        store: &mut S,
+        budget: Uint128,
+        start_time: u64,
+        end_time: u64,
+        merkle_root: Option<Binary>,
+        vesting: Option<VestingOptions>,
+    ) -> StdResult<Self> {
+        if end_time <= start_time {
+            return Err(StdError::generic_err("Round must end after it starts"));
         }
 
-        Err(StdError::generic_err(
-            "Password does not match or is not set",
-        ))
+        let mut typed_store = TypedStoreMut::attach(store);
+        let id: u32 = typed_store.may_load(ROUND_COUNT)?.unwrap_or_default();
+        typed_store.store(ROUND_COUNT, &(id + 1))?;
+
+        let round = Self {
+            id,
+            budget,
+            claimed: Uint128::zero(),
+            start_time,
+            end_time,
+            merkle_root,
+            vesting,
+            reclaimed: false,
+        };
+        round.save(store)?;
+
+        Ok(round)
     }
 
-    pub fn assert_confirmer(&self, address: &HumanAddr) -> StdResult<()> {
-        if address != &self.confirmer {
+    pub fn load<S: ReadonlyStorage>(store: &S, id: u32) -> StdResult<Self> {
+        let store = ReadonlyPrefixedStorage::new(ROUNDS, store);
+        TypedStore::attach(&store)
+            .may_load(&id.to_be_bytes())?
+            .ok_or_else(|| StdError::generic_err(format!("Round {} does not exist", id)))
+    }
+
+    pub fn save<S: Storage>(&self, store: &mut S) -> StdResult<()> {
+        let mut store = PrefixedStorage::new(ROUNDS, store);
+        TypedStoreMut::attach(&mut store).store(&self.id.to_be_bytes(), self)
+    }
+
+    pub fn assert_active(&self, time: u64) -> StdResult<()> {
+        if time < self.start_time {
             return Err(StdError::generic_err(format!(
-                "Address {} is not allowed to perform this operation",
-                address
+                "Round {} has not started yet",
+                self.id
+            )));
+        }
+        if time >= self.end_time {
+            return Err(StdError::generic_err(format!(
+                "Round {} has ended",
+                self.id
             )));
         }
         Ok(())
     }
+
+    /// Records an airdrop of `amount`, failing if it would go over the budget of the round
+    pub fn add_claimed(&mut self, amount: u128) -> StdResult<()> {
+        let claimed = self.claimed.u128().checked_add(amount);
+        match claimed {
+            Some(claimed) if claimed <= self.budget.u128() => {
+                self.claimed = Uint128(claimed);
+                Ok(())
+            }
+            _ => Err(StdError::generic_err(format!(
+                "Airdrop would exceed the budget of round {}",
+                self.id
+            ))),
+        }
+    }
+
+    pub fn unclaimed(&self) -> Uint128 {
+        Uint128(self.budget.u128().saturating_sub(self.claimed.u128()))
+    }
 }
 
-pub fn set_claimed<S: Storage>(store: &mut S, address: &str) -> StdResult<()> {
-    let mut store = PrefixedStorage::new(AIRDROP_CLAIMS, store);
+pub fn set_claimed<S: Storage>(store: &mut S, round: u32, address: &str) -> StdResult<()> {
+    let mut store = PrefixedStorage::multilevel(&[AIRDROP_CLAIMS, &round.to_be_bytes()], store);
     let mut store = TypedStoreMut::attach(&mut store);
 
     store.store(address.as_bytes(), &true)?;
@@ -103,33 +182,94 @@ pub fn set_claimed<S: Storage>(store: &mut S, address: &str) -> StdResult<()> {
     Ok(())
 }
 
-pub fn is_claimed<S: ReadonlyStorage>(store: &S, address: &str) -> bool {
+pub fn is_claimed<S: ReadonlyStorage>(store: &S, round: u32, address: &str) -> bool {
+    let round_store =
+        ReadonlyPrefixedStorage::multilevel(&[AIRDROP_CLAIMS, &round.to_be_bytes()], store);
+    let append_store = TypedStore::attach(&round_store);
+
+    let claimed = append_store.load(address.as_bytes()).unwrap_or_default();
+    // claims from before rounds existed are claims of round 0
+    claimed || (round == 0 && is_claimed_before_rounds(store, address))
+}
+
+fn is_claimed_before_rounds<S: ReadonlyStorage>(store: &S, address: &str) -> bool {
     let store = ReadonlyPrefixedStorage::new(AIRDROP_CLAIMS, store);
     let append_store = TypedStore::attach(&store);
 
     append_store.load(address.as_bytes()).unwrap_or_default()
 }
 
+/// Contracts that are allowed to read allocations through `GetAccountInfo`, e.g. the quest tracker
+pub fn set_querier<S: Storage>(store: &mut S, address: &HumanAddr, allowed: bool) -> StdResult<()> {
+    let mut store = PrefixedStorage::new(QUERIERS, store);
+    let mut typed_store = TypedStoreMut::attach(&mut store);
+
+    if allowed {
+        typed_store.store(address_to_bytes(address), &true)
+    } else {
+        typed_store.remove(address_to_bytes(address));
+        Ok(())
+    }
+}
+
+pub fn is_querier<S: ReadonlyStorage>(store: &S, address: &HumanAddr) -> bool {
+    let store = ReadonlyPrefixedStorage::new(QUERIERS, store);
+    let typed_store = TypedStore::attach(&store);
+
+    typed_store
+        .may_load(address_to_bytes(address))
+        .unwrap_or(None)
+        .unwrap_or(false)
+}
+
+pub fn assert_querier<S: ReadonlyStorage>(store: &S, address: &HumanAddr) -> StdResult<()> {
+    if !is_querier(store, address) {
+        return Err(StdError::generic_err(format!(
+            "Address {} is not an authorized querier",
+            address
+        )));
+    }
+    Ok(())
+}
+
 pub fn address_to_bytes(address: &HumanAddr) -> &[u8] {
     address.0.as_bytes()
 }
 
 pub fn add_allocation_for_address<S: Storage>(
     store: &mut S,
+    round: u32,
     address: &HumanAddr,
     amount: u128,
 ) -> StdResult<()> {
-    let mut store = PrefixedStorage::new(ACCOUNT_TOTALS, store);
+    let mut store = PrefixedStorage::multilevel(&[ACCOUNT_TOTALS, &round.to_be_bytes()], store);
     let mut typed_store = TypedStoreMut::attach(&mut store);
     typed_store.store(address_to_bytes(address), &amount)
 }
 
-pub fn get_allocation_for_account<S: ReadonlyStorage>(store: &S, address: &HumanAddr) -> u128 {
-    let store = ReadonlyPrefixedStorage::new(ACCOUNT_TOTALS, store);
-    let typed_store = TypedStore::attach(&store);
+pub fn get_allocation_for_account<S: ReadonlyStorage>(
+    store: &S,
+    round: u32,
+    address: &HumanAddr,
+) -> u128 {
+    let round_store =
+        ReadonlyPrefixedStorage::multilevel(&[ACCOUNT_TOTALS, &round.to_be_bytes()], store);
+    let typed_store = TypedStore::attach(&round_store);
     let result = typed_store.may_load(address_to_bytes(address));
 
-    result.unwrap_or(None).unwrap_or(0)
+    match result.unwrap_or(None) {
+        Some(amount) => amount,
+        // allocations from before rounds existed are allocations of round 0
+        None if round == 0 => {
+            let store = ReadonlyPrefixedStorage::new(ACCOUNT_TOTALS, store);
+            let typed_store = TypedStore::attach(&store);
+            typed_store
+                .may_load(address_to_bytes(address))
+                .unwrap_or(None)
+                .unwrap_or(0)
+        }
+        None => 0,
+    }
 }
 
 // pub fn change_allocation<S: Storage>(
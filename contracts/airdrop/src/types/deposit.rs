@@ -1,6 +1,10 @@
 use serde::Serialize;
 
-use cosmwasm_std::{HumanAddr, Uint128};
+use cosmwasm_std::{to_binary, CosmosMsg, HumanAddr, StdError, StdResult, Uint128};
+
+use crate::state::{Config, Round};
+use crate::types::secret_contract::SecretContract;
+use crate::types::vesting::vesting_msg;
 
 #[derive(Serialize, Clone, Debug, PartialEq)]
 #[serde(rename_all = "snake_case")]
@@ -26,3 +30,42 @@ impl Deposit {
         Self { to, amount }
     }
 }
+
+/// Sends `amount` LGND to the platform, to be split between `deposits`
+pub fn batch_deposit_msg(
+    platform: SecretContract,
+    token: SecretContract,
+    amount: Uint128,
+    deposits: Vec<Deposit>,
+) -> StdResult<CosmosMsg> {
+    secret_toolkit::snip20::send_msg_with_code_hash(
+        platform.address,
+        Some(platform.hash),
+        amount,
+        Some(to_binary(&LgndReceiveMsg::BatchDeposit(deposits))?),
+        Some("LGND Airdrop".to_string()),
+        None,
+        64,
+        token.hash,
+        token.address,
+    )
+}
+
+/// Sends the airdrop of a round either to the platform or, for vested rounds, to the vesting
+/// contract
+pub fn airdrop_msg(
+    config: Config,
+    round: &Round,
+    amount: Uint128,
+    deposits: Vec<Deposit>,
+) -> StdResult<CosmosMsg> {
+    match &round.vesting {
+        Some(options) => {
+            let vesting = config.vesting.ok_or_else(|| {
+                StdError::generic_err("Cannot vest airdrops before vesting contract is set up")
+            })?;
+            vesting_msg(vesting, config.token, round, options, amount, deposits)
+        }
+        None => batch_deposit_msg(config.platform, config.token, amount, deposits),
+    }
+}
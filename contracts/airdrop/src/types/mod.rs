@@ -1,3 +1,4 @@
 pub(crate) mod airdrop;
 pub(crate) mod deposit;
 pub(crate) mod secret_contract;
+pub(crate) mod vesting;
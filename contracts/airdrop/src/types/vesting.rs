@@ -0,0 +1,146 @@
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use cosmwasm_std::{to_binary, CosmosMsg, HumanAddr, StdError, StdResult, Uint128};
+
+use crate::state::Round;
+use crate::types::deposit::Deposit;
+use crate::types::secret_contract::SecretContract;
+
+pub const RATE_DENOMINATOR: u128 = 10_000;
+
+/// How airdrops of a round are vested instead of being deposited in the platform
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct VestingOptions {
+    /// Share of the airdrop released on each release, in basis points
+    pub rate: u16,
+    /// Days between each release, as in the vesting contract's schedules
+    pub releases: Vec<u16>,
+    /// Seconds between the end of the round and the first release
+    pub cliff: u64,
+}
+
+impl VestingOptions {
+    pub fn validate(&self) -> StdResult<()> {
+        if self.rate == 0 || self.rate as u128 > RATE_DENOMINATOR {
+            return Err(StdError::generic_err(format!(
+                "Vesting rate must be between 1 and {} basis points",
+                RATE_DENOMINATOR
+            )));
+        }
+        // the first release comes at the start time, then one after each of the `releases`
+        if (self.rate as u128) * (self.releases.len() as u128 + 1) < RATE_DENOMINATOR {
+            return Err(StdError::generic_err(
+                "Vesting rate and releases must release the whole airdrop",
+            ));
+        }
+        Ok(())
+    }
+
+    /// All the airdrops of a round start vesting at the same time, so the vesting contract can
+    /// add them up in a single schedule
+    fn schedule(&self, round_end_time: u64, amount: Uint128) -> StdResult<NewSchedule> {
+        // round up so that small airdrops still finish vesting
+        let rate = amount
+            .u128()
+            .checked_mul(self.rate as u128)
+            .map(|total| total.div_ceil(RATE_DENOMINATOR))
+            .ok_or_else(|| StdError::generic_err("Vesting rate calculation overflowed"))?;
+
+        Ok(NewSchedule {
+            start_time: round_end_time + self.cliff,
+            allocation: amount,
+            rate: Uint128(rate),
+            releases: self.releases.clone(),
+        })
+    }
+}
+
+// Copied from the vesting contract
+#[derive(Serialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub struct NewSchedule {
+    pub start_time: u64,
+    pub allocation: Uint128,
+    pub rate: Uint128,
+    pub releases: Vec<u16>,
+}
+
+#[derive(Serialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum VestingReceiveMsg {
+    AddAirdropSchedules {
+        round: u32,
+        schedules: Vec<(HumanAddr, NewSchedule)>,
+    },
+}
+
+/// Sends `amount` LGND to the vesting contract, creating a schedule of the round for each of the
+/// `deposits`
+pub fn vesting_msg(
+    vesting: SecretContract,
+    token: SecretContract,
+    round: &Round,
+    options: &VestingOptions,
+    amount: Uint128,
+    deposits: Vec<Deposit>,
+) -> StdResult<CosmosMsg> {
+    let schedules = deposits
+        .into_iter()
+        .map(|deposit| {
+            let schedule = options.schedule(round.end_time, deposit.amount)?;
+            Ok((deposit.to, schedule))
+        })
+        .collect::<StdResult<Vec<_>>>()?;
+
+    secret_toolkit::snip20::send_msg_with_code_hash(
+        vesting.address,
+        Some(vesting.hash),
+        amount,
+        Some(to_binary(&VestingReceiveMsg::AddAirdropSchedules {
+            round: round.id,
+            schedules,
+        })?),
+        Some("LGND Airdrop".to_string()),
+        None,
+        64,
+        token.hash,
+        token.address,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn options(rate: u16, releases: Vec<u16>) -> VestingOptions {
+        VestingOptions {
+            rate,
+            releases,
+            cliff: 100,
+        }
+    }
+
+    #[test]
+    fn test_validate_releases_everything() {
+        assert!(options(0, vec![30]).validate().is_err());
+        assert!(options(10_001, vec![]).validate().is_err());
+        assert!(options(1_000, vec![30]).validate().is_err());
+        assert!(options(3_333, vec![30, 30]).validate().is_err());
+        assert!(options(3_334, vec![30, 30]).validate().is_ok());
+        assert!(options(10_000, vec![]).validate().is_ok());
+    }
+
+    #[test]
+    fn test_schedules_of_a_round_share_timing() {
+        let options = options(2_500, vec![30, 30, 30]);
+        let first = options.schedule(1_000, Uint128(1_000)).unwrap();
+        let second = options.schedule(1_000, Uint128(3)).unwrap();
+
+        assert_eq!(first.start_time, 1_100);
+        assert_eq!(second.start_time, 1_100);
+        assert_eq!(first.rate, Uint128(250));
+        // rounded up so that the whole airdrop vests with the last release
+        assert_eq!(second.rate, Uint128(1));
+    }
+}
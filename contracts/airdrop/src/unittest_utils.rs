@@ -0,0 +1,98 @@
+use cosmwasm_std::testing::{mock_dependencies, mock_env, MockApi, MockQuerier, MockStorage};
+use cosmwasm_std::{Binary, Env, Extern, HandleResponse, HumanAddr, StdResult, Uint128};
+
+use crate::handles::{handle, HandleMsg};
+use crate::init::{init, InitMsg};
+use crate::types::secret_contract::SecretContract;
+use crate::types::vesting::VestingOptions;
+
+/// Merkle tree of the allocations alice: 100, bob: 200 and carol: 300, with alice and bob paired
+/// under the same node. Computed off-chain, as the claim tooling would
+pub const MERKLE_ROOT: &str = "UebKNEE60qXcD6GakTz2GYota55D6p2YaFBG4uh21HQ=";
+pub const ALICE_LEAF: &str = "HqzGYmfhanFczFbcbMq1r1vDd4i/IHEAG2cTSIpkQcg=";
+pub const BOB_LEAF: &str = "QOZO43olop2z8V+N6M99PXgXQPP1roLHb/y4qsRpZ0A=";
+pub const CAROL_LEAF: &str = "/FxouposROS/6AuJvGs9I2WjrePfdntxjxUQVdMMdFA=";
+pub const ALICE_BOB_NODE: &str = "yZ+pT0TqmgKQtJhLHmlOEgYRUgrUXJZZeZMhI1gFlvc=";
+
+pub fn binary(base64: &str) -> Binary {
+    Binary::from_base64(base64).unwrap()
+}
+
+/// The proof of the allocation of `address` in the tree of `MERKLE_ROOT`
+pub fn proof(address: &str) -> Vec<Binary> {
+    let proof = match address {
+        "alice" => vec![BOB_LEAF, CAROL_LEAF],
+        "bob" => vec![ALICE_LEAF, CAROL_LEAF],
+        "carol" => vec![ALICE_BOB_NODE],
+        _ => vec![],
+    };
+    proof.into_iter().map(binary).collect()
+}
+
+pub fn contract(address: &str) -> SecretContract {
+    SecretContract {
+        address: HumanAddr::from(address),
+        hash: format!("{}_hash", address),
+    }
+}
+
+pub fn mock_env_with_time(sender: &str, time: u64) -> Env {
+    let mut env = mock_env(sender, &[]);
+    env.block.time = time;
+    env
+}
+
+pub fn init_helper() -> Extern<MockStorage, MockApi, MockQuerier> {
+    let mut deps = mock_dependencies(20, &[]);
+    let msg = InitMsg {
+        admin: Some(HumanAddr::from("admin")),
+        treasury: Some(HumanAddr::from("treasury")),
+        platform: contract("platform"),
+        token: contract("token"),
+        vesting: Some(contract("vesting")),
+        queriers: vec![],
+        quest_contract: None,
+    };
+    init(&mut deps, mock_env("admin", &[]), msg).unwrap();
+
+    deps
+}
+
+/// Opens a round between the times 100 and 200 with the allocations of `MERKLE_ROOT`
+pub fn create_round_helper(
+    deps: &mut Extern<MockStorage, MockApi, MockQuerier>,
+    budget: u128,
+    vesting: Option<VestingOptions>,
+) -> StdResult<HandleResponse> {
+    let msg = HandleMsg::CreateRound {
+        budget: Uint128(budget),
+        start_time: 100,
+        end_time: 200,
+        merkle_root: Some(binary(MERKLE_ROOT)),
+        vesting,
+    };
+    handle(deps, mock_env_with_time("admin", 0), msg)
+}
+
+pub fn claim_helper(
+    deps: &mut Extern<MockStorage, MockApi, MockQuerier>,
+    claimer: &str,
+    round: u32,
+    amount: u128,
+    time: u64,
+) -> StdResult<HandleResponse> {
+    let msg = HandleMsg::Claim {
+        round,
+        amount: Uint128(amount),
+        proof: proof(claimer),
+    };
+    handle(deps, mock_env_with_time(claimer, time), msg)
+}
+
+pub fn extract_error_msg<T>(result: StdResult<T>) -> String {
+    match result {
+        Err(cosmwasm_std::StdError::GenericErr { msg, .. }) => msg,
+        Err(err) => panic!("unexpected error: {:?}", err),
+        Ok(_) => panic!("expected an error"),
+    }
+}
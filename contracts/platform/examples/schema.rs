@@ -3,9 +3,10 @@ use std::fs::create_dir_all;
 
 use cosmwasm_schema::{export_schema, remove_schemas, schema_for};
 
+use platform::events::Event;
 use platform::msg::{
-    HandleAnswer, HandleMsg, InitMsg, PlatformApi, QueryAnswer, QueryMsg, QueryWithPermit,
-    ReceiveMsg,
+    HandleAnswer, HandleMsg, InitMsg, LootBoxReceiveMsg, PlatformApi, QueryAnswer, QueryMsg,
+    QueryWithPermit, ReceiveMsg,
 };
 
 fn main() {
@@ -20,8 +21,11 @@ fn main() {
     export_schema(&schema_for!(HandleAnswer), &out_dir);
     export_schema(&schema_for!(PlatformApi), &out_dir);
     export_schema(&schema_for!(ReceiveMsg), &out_dir);
+    export_schema(&schema_for!(LootBoxReceiveMsg), &out_dir);
 
     export_schema(&schema_for!(QueryMsg), &out_dir);
     export_schema(&schema_for!(QueryWithPermit), &out_dir);
     export_schema(&schema_for!(QueryAnswer), &out_dir);
+
+    export_schema(&schema_for!(Event), &out_dir);
 }
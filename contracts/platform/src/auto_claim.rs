@@ -1,11 +1,12 @@
 use crate::contract::do_claim;
-use crate::state::{Balances, Config};
+use crate::state::{Balances, Vault};
 use cosmwasm_std::{CosmosMsg, Env, HumanAddr, ReadonlyStorage, StdResult, Storage};
 use cosmwasm_storage::{PrefixedStorage, ReadonlyPrefixedStorage};
 use secret_toolkit::storage::{DequeStore, DequeStoreMut};
 use serde::{Deserialize, Serialize};
 
 const PREFIX_CLAIMS: &[u8] = b"claims";
+const PREFIX_TOKEN_CLAIMS: &[u8] = b"token_claims";
 
 #[derive(Serialize, Deserialize)]
 #[cfg_attr(test, derive(Debug, PartialEq))]
@@ -14,11 +15,17 @@ pub struct Claim {
     end_ts: u64,
 }
 
+/// Claims are queued per token, so that every queue stays sorted by `end_ts` even though the
+/// tokens have different unbonding periods
 pub struct AutoClaims {}
 
 impl AutoClaims {
-    pub fn len<S: ReadonlyStorage>(storage: &S) -> StdResult<u32> {
-        let queue_store = ReadonlyPrefixedStorage::new(PREFIX_CLAIMS, storage);
+    fn namespace(vault: &Vault) -> Vec<&[u8]> {
+        vault.namespace(PREFIX_CLAIMS, PREFIX_TOKEN_CLAIMS)
+    }
+
+    pub fn len<S: ReadonlyStorage>(storage: &S, vault: &Vault) -> StdResult<u32> {
+        let queue_store = ReadonlyPrefixedStorage::multilevel(&Self::namespace(vault), storage);
         let claims = match DequeStore::<Claim, ReadonlyPrefixedStorage<S>>::attach(&queue_store) {
             None => return Ok(0),
             Some(c) => c?,
@@ -27,8 +34,12 @@ impl AutoClaims {
         Ok(claims.len())
     }
 
-    pub fn peek_next_claim<S: ReadonlyStorage>(storage: &S, env: &Env) -> StdResult<Option<Claim>> {
-        let queue_store = ReadonlyPrefixedStorage::new(PREFIX_CLAIMS, storage);
+    pub fn peek_next_claim<S: ReadonlyStorage>(
+        storage: &S,
+        env: &Env,
+        vault: &Vault,
+    ) -> StdResult<Option<Claim>> {
+        let queue_store = ReadonlyPrefixedStorage::multilevel(&Self::namespace(vault), storage);
         let claims = match DequeStore::<Claim, ReadonlyPrefixedStorage<S>>::attach(&queue_store) {
             None => return Ok(None),
             Some(c) => c?,
@@ -47,9 +58,13 @@ impl AutoClaims {
         }
     }
 
-    pub fn pop_next_claim<S: Storage>(storage: &mut S, env: &Env) -> StdResult<Option<Claim>> {
-        if Self::peek_next_claim(storage, env)?.is_some() {
-            let mut queue_store = PrefixedStorage::new(PREFIX_CLAIMS, storage);
+    pub fn pop_next_claim<S: Storage>(
+        storage: &mut S,
+        env: &Env,
+        vault: &Vault,
+    ) -> StdResult<Option<Claim>> {
+        if Self::peek_next_claim(storage, env, vault)?.is_some() {
+            let mut queue_store = PrefixedStorage::multilevel(&Self::namespace(vault), storage);
             let mut claims = DequeStoreMut::attach_or_create(&mut queue_store)?;
 
             return match claims.pop_front() {
@@ -61,8 +76,8 @@ impl AutoClaims {
         Ok(None)
     }
 
-    fn push_claim<S: Storage>(storage: &mut S, claim: Claim) -> StdResult<()> {
-        let mut queue_store = PrefixedStorage::new(PREFIX_CLAIMS, storage);
+    fn push_claim<S: Storage>(storage: &mut S, vault: &Vault, claim: Claim) -> StdResult<()> {
+        let mut queue_store = PrefixedStorage::multilevel(&Self::namespace(vault), storage);
         let mut claims = DequeStoreMut::attach_or_create(&mut queue_store)?;
 
         claims.push_back(&claim)
@@ -71,18 +86,19 @@ impl AutoClaims {
     pub fn new_unbonding<S: Storage>(
         storage: &mut S,
         env: &Env,
-        config: &Config,
+        vault: &Vault,
         user: HumanAddr,
         user_balances: &mut Balances,
         amount: u128,
     ) -> StdResult<()> {
         let (unbonding, is_new) = user_balances
             .pending_redeem
-            .create_or_add_to_existing_unbonding_record(env, config, amount)?;
+            .create_or_add_to_existing_unbonding_record(env, vault.unbonding_period, amount)?;
 
         if is_new {
             Self::push_claim(
                 storage,
+                vault,
                 Claim {
                     account: user,
                     end_ts: unbonding.end_ts,
@@ -93,15 +109,65 @@ impl AutoClaims {
         Ok(())
     }
 
-    pub fn claim_next_pending_msg<S: Storage>(
-        storage: &mut S,
-        env: &Env,
-    ) -> StdResult<Option<Vec<CosmosMsg>>> {
-        let claim = match Self::pop_next_claim(storage, env)? {
+    /// Time (in seconds) the claim at the front of the queue finishes unbonding
+    pub fn next_end_ts<S: ReadonlyStorage>(storage: &S, vault: &Vault) -> StdResult<Option<u64>> {
+        let queue_store = ReadonlyPrefixedStorage::multilevel(&Self::namespace(vault), storage);
+        let claims = match DequeStore::<Claim, ReadonlyPrefixedStorage<S>>::attach(&queue_store) {
             None => return Ok(None),
-            Some(c) => c,
+            Some(c) => c?,
         };
 
-        do_claim(storage, env, &claim.account)
+        match claims.iter().next() {
+            None => Ok(None),
+            Some(c) => Ok(Some(c?.end_ts)),
+        }
+    }
+
+    /// Pays up to `limit` matured claims, of all tokens. Returns the messages and the number of
+    /// claims processed
+    pub fn claim_pending_msgs<S: Storage>(
+        storage: &mut S,
+        env: &Env,
+        limit: u32,
+    ) -> StdResult<(Vec<CosmosMsg>, u32)> {
+        let mut messages = vec![];
+        let mut processed = 0;
+
+        for vault in Vault::load_all(storage)? {
+            let (vault_messages, vault_processed) =
+                Self::claim_vault_msgs(storage, env, &vault, limit - processed)?;
+            messages.extend(vault_messages);
+            processed += vault_processed;
+        }
+
+        Ok((messages, processed))
+    }
+
+    /// Pays up to `limit` matured claims of the `vault`. Returns the messages and the number of
+    /// claims processed
+    pub fn claim_vault_msgs<S: Storage>(
+        storage: &mut S,
+        env: &Env,
+        vault: &Vault,
+        limit: u32,
+    ) -> StdResult<(Vec<CosmosMsg>, u32)> {
+        let mut messages = vec![];
+        let mut processed = 0;
+
+        while processed < limit {
+            let claim = match Self::pop_next_claim(storage, env, vault)? {
+                None => break,
+                Some(c) => c,
+            };
+            processed += 1;
+
+            // The account may have already claimed manually. No event is logged, as it would be
+            // readable by whoever sent the transaction
+            if let Some((claim_messages, _)) = do_claim(storage, env, vault, &claim.account)? {
+                messages.extend(claim_messages);
+            }
+        }
+
+        Ok((messages, processed))
     }
 }
@@ -1,3 +1,9 @@
 pub const RESPONSE_BLOCK_SIZE: usize = 256;
 pub const FIVE_MINUTES: u64 = 60;
 pub const PREFIX_REVOKED_PERMITS: &str = "revoked_permits";
+/// Matured claims of the primary vault paid as a side effect of every handle
+pub const AUTO_CLAIMS_PER_HANDLE: u32 = 3;
+pub const DEFAULT_CLAIMS_PER_CRANK: u32 = 10;
+pub const MAX_CLAIMS_PER_CRANK: u32 = 50;
+/// Larger pages of the paginated queries are truncated
+pub const MAX_PAGE_SIZE: u32 = 50;
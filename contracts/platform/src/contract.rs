@@ -1,19 +1,33 @@
 use std::{vec, result};
 
 use crate::auto_claim::AutoClaims;
-use crate::constants::{PREFIX_REVOKED_PERMITS, RESPONSE_BLOCK_SIZE, FIVE_MINUTES};
+use crate::events::{transaction_logs, Event};
+use crate::constants::{
+    AUTO_CLAIMS_PER_HANDLE, DEFAULT_CLAIMS_PER_CRANK, FIVE_MINUTES, MAX_CLAIMS_PER_CRANK,
+    MAX_PAGE_SIZE, PREFIX_REVOKED_PERMITS, RESPONSE_BLOCK_SIZE,
+};
+use crate::loot_box::OpenLootBoxPayload;
+use crate::migration::{
+    load_admin, load_version, migrate, require_current_version, save_version, STORAGE_VERSION,
+};
+use crate::loot_table::{
+    loot_box_type, set_loot_box_types, LootDraw, LootOutcome, LootRng, LootTable,
+};
 use crate::msg::ResponseStatus::Success;
 use crate::msg::{
     Deposit, HandleAnswer, HandleMsg, InitMsg, PlatformApi, QueryAnswer, QueryMsg, QueryWithPermit,
-    ReceiveMsg, ResponseStatus, OpenLootBoxMessage
+    ReceiveMsg, ResponseStatus, LootBoxReceiveMsg, ResponseBalances, ResponseClaimQueue,
+    ResponseLootBoxHistory, ResponseLootTable, ResponseReceivingContracts, ResponseTokenBalances,
 };
 use crate::state::{
-    BalanceChange, Balances, Config, Features, ReceivingContracts, TotalBalances, Nonces, SECONDS_IN_DAY,
+    BalanceChange, Balances, Config, Features, InstantRedeem, LootBoxHistory, NativeToken,
+    PenaltySink, PlatformAllowances, ReceivingContract, ReceivingContracts, StakerPenalties,
+    SupportedToken, TotalBalances, Nonces, Signer, Signers, Vault, SECONDS_IN_DAY,
 };
 use cosmwasm_std::{
     to_binary, Api, BankMsg, Binary, Coin, CosmosMsg, Env, Extern, HandleResponse, HandleResult,
     HumanAddr, InitResponse, InitResult, Querier, QueryResult, StdError, StdResult, Storage,
-    Uint128, QueryRequest, Empty, from_slice,
+    Uint128, QueryRequest, Empty, from_slice, from_binary,
 };
 use secret_toolkit::permit::{validate, Permit, RevokedPermits, TokenPermissions};
 use secret_toolkit::snip20;
@@ -27,6 +41,8 @@ use snafu::NoneError;
 use crate::msgs::update_nft::{change_nft_type, burn_loot_box, change_nft_metadata};
 use crate::msgs::mint_nft::{mint_nft_msg};
 use crate::msgs::nft_info::{get_token_type};
+use crate::msgs::transfer_nft::{register_receive_nft_msg, transfer_nft_msg};
+use crate::msgs::wrap_native::deposit_native_msg;
 
 use crate::snip721::metadata::Metadata;
 
@@ -37,7 +53,6 @@ use crate::snip721::snip721_handle_msg::{TokenTypeRespone};
 
 // use sha3::{Keccak256, Digest};
 // use std::ops::Deref;
-use sha2::{Digest, Sha256};
 
 pub fn init<S: Storage, A: Api, Q: Querier>(
     deps: &mut Extern<S, A, Q>,
@@ -50,15 +65,24 @@ pub fn init<S: Storage, A: Api, Q: Querier>(
         legen_dao_nft: msg.legen_dao_nft.clone(),
         native_token_denom: msg.token_native_denom,
         unbonding_period: msg.unbonding_period.unwrap_or(SECONDS_IN_DAY * 21),
-        self_contract_addr: env.contract.address,
+        self_contract_addr: env.contract.address.clone(),
         distribute_address: msg.distribute_address.clone(),
-        signer_address: msg.signer_address,
+        signer_address: msg.signer_address.clone(),
         ts_now: 0,
     }
     .save(&mut deps.storage)?;
 
+    Signers {
+        signers: vec![Signer::new(msg.signer_address)],
+        threshold: 1,
+    }
+    .save(&mut deps.storage)?;
+
+    LootRng::new(msg.random_seed.as_slice())?.save(&mut deps.storage)?;
+    save_version(&mut deps.storage, STORAGE_VERSION)?;
+
     if let Some(receiving_contracts) = msg.receiving_contracts {
-        ReceivingContracts::set_multiple(&mut deps.storage, receiving_contracts)?;
+        ReceivingContracts::add_multiple(&mut deps.storage, receiving_contracts)?;
     }
 
     FeatureToggle::init_features(
@@ -87,7 +111,7 @@ pub fn init<S: Storage, A: Api, Q: Querier>(
     Ok(InitResponse {
         messages: vec![
             snip20::register_receive_msg(
-                env.contract_code_hash,
+                env.contract_code_hash.clone(),
                 None,
                 1,
                 msg.token.hash.clone(),
@@ -100,6 +124,7 @@ pub fn init<S: Storage, A: Api, Q: Querier>(
                 msg.token.hash,
                 msg.token.address,
             )?,
+            register_receive_nft_msg(msg.legen_dao_nft, env.contract_code_hash)?,
         ],
         log: vec![],
     })
@@ -110,11 +135,32 @@ pub fn handle<S: Storage, A: Api, Q: Querier>(
     env: Env,
     msg: HandleMsg,
 ) -> HandleResult {
+    if !matches!(msg, HandleMsg::Migrate { .. }) {
+        require_current_version(&deps.storage)?;
+    }
+
+    let is_crank = matches!(msg, HandleMsg::ProcessClaims { .. });
+    let admin_action = admin_action(&msg);
+
     let mut result = match msg {
-        HandleMsg::Redeem { amount } => redeem(deps, &env, amount.map(|a| a.u128())),
+        HandleMsg::DepositNative {} => deposit_native(deps, &env),
+        HandleMsg::Redeem { token, amount } => {
+            redeem(deps, &env, token, amount.map(|a| a.u128()))
+        }
+        HandleMsg::RedeemInstant { amount } => {
+            redeem_instant(deps, &env, amount.map(|a| a.u128()))
+        }
+        HandleMsg::CancelUnbonding {
+            token,
+            end_ts,
+            amount,
+        } => cancel_unbonding(deps, &env, token, end_ts, amount.map(|a| a.u128())),
         HandleMsg::ClaimRedeemed {} => claim(deps, &env),
+        HandleMsg::ProcessClaims { limit } => process_claims(deps, &env, limit),
         HandleMsg::SendFromPlatform {
             contract_addr,
+            owner,
+            token,
             amount,
             memo,
             msg,
@@ -122,6 +168,8 @@ pub fn handle<S: Storage, A: Api, Q: Querier>(
             deps,
             &env,
             contract_addr,
+            owner,
+            token,
             amount.map(|a| a.u128()),
             memo,
             msg,
@@ -133,8 +181,7 @@ pub fn handle<S: Storage, A: Api, Q: Querier>(
             open_nft_uri,
             nonce,
             ts_execute,
-            message,
-            signature,
+            signatures,
             memo
         } => open_loot_box(
             deps, 
@@ -145,13 +192,25 @@ pub fn handle<S: Storage, A: Api, Q: Querier>(
             open_nft_uri,
             nonce,
             ts_execute,
-            message,
-            signature,
+            signatures,
             memo
         ),
+        HandleMsg::IncreasePlatformAllowance {
+            spender,
+            token,
+            amount,
+            expiration,
+            ..
+        } => increase_platform_allowance(deps, &env, spender, token, amount.u128(), expiration),
+        HandleMsg::RevokePlatformAllowance { spender, token, .. } => {
+            revoke_platform_allowance(deps, &env, spender, token)
+        }
         HandleMsg::AddReceivingContracts { addresses } => {
             add_receiving_contracts(deps, &env, addresses)
         }
+        HandleMsg::SetReceivingContracts { contracts } => {
+            set_receiving_contracts(deps, &env, contracts)
+        }
         HandleMsg::RemoveReceivingContracts { addresses } => {
             remove_receiving_contracts(deps, &env, addresses)
         }
@@ -175,11 +234,55 @@ pub fn handle<S: Storage, A: Api, Q: Querier>(
         HandleMsg::ChangeConfig {
             admin,
             unbonding_period,
-        } => change_config(deps, &env, admin, unbonding_period),
+            instant_redeem,
+            native_token,
+        } => change_config(
+            deps,
+            &env,
+            admin,
+            unbonding_period,
+            instant_redeem,
+            native_token,
+        ),
+        HandleMsg::AddSupportedTokens { tokens } => add_supported_tokens(deps, &env, tokens),
+        HandleMsg::AddSigners { signers } => add_signers(deps, &env, signers),
+        HandleMsg::RemoveSigners { public_keys } => remove_signers(deps, &env, public_keys),
+        HandleMsg::SetSignerThreshold { threshold } => {
+            set_signer_threshold(deps, &env, threshold)
+        }
+        HandleMsg::SetLootTable { box_type, outcomes } => {
+            set_loot_table(deps, &env, box_type, outcomes)
+        }
+        HandleMsg::RemoveLootTable { box_type } => remove_loot_table(deps, &env, box_type),
+        HandleMsg::SetLootBoxTypes {
+            box_type,
+            loot_box_ids,
+        } => set_loot_box_types_handle(deps, &env, box_type, loot_box_ids),
+        HandleMsg::BatchReceiveNft {
+            from,
+            token_ids,
+            msg,
+            ..
+        } => batch_receive_nft(deps, &env, from, token_ids, msg),
+        HandleMsg::Migrate {
+            random_seed,
+            legacy_receiving_contracts,
+        } => migrate_storage(
+            deps,
+            &env,
+            random_seed,
+            legacy_receiving_contracts.unwrap_or_default(),
+        ),
     };
 
-    let claim_msg = AutoClaims::claim_next_pending_msg(&mut deps.storage, &env)?;
-    if let Some(claim_messages) = claim_msg {
+    // Paused claims aren't paid automatically either, and the crank already did its share. Only
+    // the primary vault's, so that users don't pay for going through every supported token
+    if !is_crank
+        && FeatureToggle::require_not_paused(&deps.storage, vec![Features::Claim]).is_ok()
+    {
+        let vault = Vault::load(&deps.storage, None)?;
+        let (claim_messages, _) =
+            AutoClaims::claim_vault_msgs(&mut deps.storage, &env, &vault, AUTO_CLAIMS_PER_HANDLE)?;
         if let Ok(HandleResponse {
             ref mut messages, ..
         }) = result
@@ -188,9 +291,46 @@ pub fn handle<S: Storage, A: Api, Q: Querier>(
         }
     }
 
+    if let Ok(HandleResponse { ref mut log, .. }) = result {
+        if let Some(action) = admin_action {
+            log.push(
+                Event::Admin {
+                    action: action.to_string(),
+                }
+                .log()?,
+            );
+        }
+        log.extend(transaction_logs(&mut deps.storage)?);
+    }
+
     result
 }
 
+/// Name of the `Admin` event of handles that only the admin (or the pausers) may call
+fn admin_action(msg: &HandleMsg) -> Option<&'static str> {
+    let action = match msg {
+        HandleMsg::AddReceivingContracts { .. } => "add_receiving_contracts",
+        HandleMsg::SetReceivingContracts { .. } => "set_receiving_contracts",
+        HandleMsg::RemoveReceivingContracts { .. } => "remove_receiving_contracts",
+        HandleMsg::Features(FeatureToggleHandleMsg::Pause { .. }) => "pause",
+        HandleMsg::Features(FeatureToggleHandleMsg::Unpause { .. }) => "unpause",
+        HandleMsg::Features(FeatureToggleHandleMsg::SetPauser { .. }) => "set_pauser",
+        HandleMsg::Features(FeatureToggleHandleMsg::RemovePauser { .. }) => "remove_pauser",
+        HandleMsg::ChangeConfig { .. } => "change_config",
+        HandleMsg::AddSupportedTokens { .. } => "add_supported_tokens",
+        HandleMsg::AddSigners { .. } => "add_signers",
+        HandleMsg::RemoveSigners { .. } => "remove_signers",
+        HandleMsg::SetSignerThreshold { .. } => "set_signer_threshold",
+        HandleMsg::SetLootTable { .. } => "set_loot_table",
+        HandleMsg::RemoveLootTable { .. } => "remove_loot_table",
+        HandleMsg::SetLootBoxTypes { .. } => "set_loot_box_types",
+        HandleMsg::Migrate { .. } => "migrate",
+        _ => return None,
+    };
+
+    Some(action)
+}
+
 fn open_loot_box<S: Storage, A: Api, Q: Querier>(
     deps: &mut Extern<S, A, Q>,
     env: &Env,
@@ -200,90 +340,99 @@ fn open_loot_box<S: Storage, A: Api, Q: Querier>(
     open_nft_uri: Option<String>,
     nonce: u64,
     ts_execute: u64,
-    message: Binary,
-    signature: Binary,
+    signatures: Vec<Binary>,
     memo: Option<String>,
 ) -> StdResult<HandleResponse> {
-
-    
-
     let mut config = Config::get_unchecked(&deps.storage)?;
     let mut user_noce = Nonces::load(&deps.storage, &env.message.sender)?.unwrap_or_default();
 
-    let mut messages = vec![];
-
-    let message_u8 = message.as_slice();
-    let signature_u8 = signature.as_slice();
-    let signer_address_u8 = config.signer_address.as_slice();
-
     // ============ Verify Signature
-    // Hashing
-    let hash = Sha256::digest(message_u8.clone());
-
-    // Verification
-    let result = deps.api.secp256k1_verify(hash.as_ref(), signature_u8, signer_address_u8);
+    let payload = OpenLootBoxPayload::new(
+        env,
+        loot_box_id.clone(),
+        open_lgnd_amount,
+        open_nft_contract.clone(),
+        open_nft_uri.clone(),
+        nonce,
+        ts_execute,
+    );
+    let signers = Signers::load(&deps.storage)?;
+    payload.verify(&deps.api, &signatures, &signers, env.block.time)?;
 
-    if let Err(err) = result {
-        return Err(StdError::generic_err(format!("Error is {}", err)));
+    if user_noce.nonce != nonce {
+        return Err(StdError::generic_err("Wrong nonce"));
     }
-    else {
-        if !result.unwrap() {
-            return Err(StdError::generic_err(format!("Wrong Signature")));
-        }
-        else {
-            if user_noce.nonce != nonce {
-                return Err(StdError::generic_err(format!("Wrong nonce",)))
-            }
 
-            if  ts_execute + FIVE_MINUTES < env.block.time {
-                return Err(StdError::generic_err(format!("Timestamp is overdue")));
-            }
+    if ts_execute + FIVE_MINUTES < env.block.time {
+        return Err(StdError::generic_err("Timestamp is overdue"));
+    }
 
-            config.ts_now = env.block.time;
-        
-            user_noce.nonce = user_noce.nonce + 1;
-            user_noce.save(&mut deps.storage, &env.message.sender)?;
-
-            // let open_box_msg: HandleMsg = from_slice(message_u8.clone())?;
-
-            // match open_box_msg {
-            //     HandleMsg::OpenLootBox{
-            //         loot_box_id,
-            //         open_lgnd_amount,
-            //         open_nft_contract,
-            //         open_nft_uri,
-            //         nonce,
-            //         message,
-            //         signature,
-            //         memo,
-            //     } => {
-                    
-            //     }
-            //     _ => return Err(StdError::generic_err(format!("Wrong message",)))
-            // }
-        }
+    config.ts_now = env.block.time;
 
-        // return Err(StdError::generic_err(format!("Error is wrong {}", result.unwrap())));
-    }
+    user_noce.nonce += 1;
+    user_noce.save(&mut deps.storage, &env.message.sender)?;
     // ============ Verify Signature
     
 
+    let draw = LootDraw {
+        lgnd_amount: open_lgnd_amount,
+        nft_contract: open_nft_contract,
+        nft_uri: open_nft_uri,
+    };
+    let event = Event::open_loot_box(&env.message.sender, &loot_box_id, &draw);
+    LootBoxHistory::push(
+        &mut deps.storage,
+        &env.message.sender,
+        &draw.to_record(env, loot_box_id.clone(), Some(nonce)),
+    )?;
+
+    let messages = loot_box_msgs(
+        deps,
+        &config,
+        &env.message.sender,
+        loot_box_id,
+        draw,
+        memo,
+        false,
+    )?;
+
+    config.save(&mut deps.storage)?;
+
+    Ok(HandleResponse {
+        messages,
+        log: vec![event.log()?],
+        data: Some(to_binary(&HandleAnswer::OpenLootBox {
+            status: ResponseStatus::Success,
+        })?),
+    })
+}
+
+/// Messages handing the contents of a loot box to its owner. When the box was sent to the platform
+/// (i.e. the platform holds it), a box that turns into an item is transferred back to the owner
+fn loot_box_msgs<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    config: &Config,
+    owner: &HumanAddr,
+    loot_box_id: String,
+    draw: LootDraw,
+    memo: Option<String>,
+    held_by_platform: bool,
+) -> StdResult<Vec<CosmosMsg>> {
+    let mut messages = vec![];
+
     // check if token id is Loot Box
     let query_nft_type = get_token_type(config.legen_dao_nft.clone(), loot_box_id.clone())?;
     let result_nft_type: TokenTypeRespone = deps.querier.query(&query_nft_type)?;
-    
+
     if result_nft_type.token_type != 3 {
-        return Err(StdError::generic_err(format!(
-            "Only lootbox can be open",
-        )));
+        return Err(StdError::generic_err("Only lootbox can be open"));
     }
 
-    if open_lgnd_amount > Uint128(0) {
-
+    if draw.lgnd_amount > Uint128(0) {
         let send_msg = snip20::transfer_from_msg(
             config.distribute_address.clone(),
-            env.message.sender.clone(),
-            open_lgnd_amount,
+            owner.clone(),
+            draw.lgnd_amount,
             memo,
             None,
             RESPONSE_BLOCK_SIZE,
@@ -294,85 +443,211 @@ fn open_loot_box<S: Storage, A: Api, Q: Querier>(
         messages.push(send_msg);
     }
 
-    if open_nft_contract.is_some() {
+    let public_metadata = Some(Metadata {
+        token_uri: draw.nft_uri,
+        extension: None,
+    });
 
-        let compare_nft_contract = open_nft_contract.clone();
-
-        if config.legen_dao_nft.eq(&compare_nft_contract.unwrap()) {
-            
-            let public_metadata = Some(
-                Metadata {
-                    //token_uri: Some(uri.clone()),
-                    token_uri: open_nft_uri,
-                    extension: None,
-                }
-            );
-            
+    match draw.nft_contract {
+        Some(nft_contract) if nft_contract == config.legen_dao_nft => {
             // change metadata of nft
             let change_meta_data_message = change_nft_metadata(
-                config.legen_dao_nft.clone(), 
-                loot_box_id.clone(), 
-                public_metadata, 
-                None, 
-                None)?;
-    
+                config.legen_dao_nft.clone(),
+                loot_box_id.clone(),
+                public_metadata,
+                None,
+                None,
+            )?;
             messages.push(change_meta_data_message);
 
             // change type of nft
-            let change_message = change_nft_type(config.legen_dao_nft.clone(), loot_box_id, 2)?;
+            let change_message =
+                change_nft_type(config.legen_dao_nft.clone(), loot_box_id.clone(), 2)?;
             messages.push(change_message);
-        }
-        else {
-
-            let public_metadata = Some(
-                Metadata {
-                    //token_uri: Some(uri.clone()),
-                    token_uri: open_nft_uri,
-                    extension: None,
-                }
-            );
 
+            // give the item back to the user, if the box was sent to the platform
+            if held_by_platform {
+                let transfer_message = transfer_nft_msg(
+                    config.legen_dao_nft.clone(),
+                    owner.clone(),
+                    loot_box_id,
+                    None,
+                )?;
+                messages.push(transfer_message);
+            }
+        }
+        Some(nft_contract) => {
             // mint nft of other collection to user
             let mint_message = mint_nft_msg(
-                None, 
-                Some(env.message.sender.clone()),
+                None,
+                Some(owner.clone()),
                 public_metadata,
                 None,
                 None,
                 None,
                 RESPONSE_BLOCK_SIZE,
-                open_nft_contract.unwrap()
+                nft_contract,
             )?;
-
             messages.push(mint_message);
 
             // burn loot box of legenDAO collection
-            let burn_message = burn_loot_box(config.legen_dao_nft.clone(), loot_box_id, None, None)?;
+            let burn_message =
+                burn_loot_box(config.legen_dao_nft.clone(), loot_box_id, None, None)?;
+            messages.push(burn_message);
+        }
+        None => {
+            // burn loot box of legenDAO collection
+            let burn_message =
+                burn_loot_box(config.legen_dao_nft.clone(), loot_box_id, None, None)?;
             messages.push(burn_message);
         }
-    } 
-    else {
-        // burn loot box of legenDAO collection
-        let burn_message = burn_loot_box(config.legen_dao_nft.clone(), loot_box_id, None, None)?;
-        messages.push(burn_message);
     }
 
-    config.save(&mut deps.storage)?;
+    Ok(messages)
+}
+
+fn batch_receive_nft<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: &Env,
+    from: HumanAddr,
+    token_ids: Vec<String>,
+    msg: Option<Binary>,
+) -> HandleResult {
+    let config = Config::get_unchecked(&deps.storage)?;
+    if env.message.sender != config.legen_dao_nft.address {
+        return Err(StdError::generic_err(format!(
+            "only loot boxes of {} can be opened, got {}",
+            config.legen_dao_nft.address, env.message.sender
+        )));
+    }
+
+    let (entropy, memo) = match msg {
+        Some(msg) => match from_binary(&msg)? {
+            LootBoxReceiveMsg::OpenLootBox { entropy, memo } => (entropy, memo),
+        },
+        None => return Err(StdError::generic_err("missing open loot box message")),
+    };
+
+    let mut rng = LootRng::load(&deps.storage)?;
+    let mut messages = vec![];
+    let mut logs = vec![];
+
+    for loot_box_id in token_ids {
+        let loot_table = LootTable::for_loot_box(&deps.storage, &loot_box_id)?;
+        let draw = loot_table.draw(&mut rng.prng(env, &from, &loot_box_id, entropy.as_bytes()));
+
+        logs.push(Event::open_loot_box(&from, &loot_box_id, &draw).log()?);
+        LootBoxHistory::push(
+            &mut deps.storage,
+            &from,
+            &draw.to_record(env, loot_box_id.clone(), None),
+        )?;
+
+        messages.extend(loot_box_msgs(
+            deps,
+            &config,
+            &from,
+            loot_box_id,
+            draw,
+            memo.clone(),
+            true,
+        )?);
+    }
+
+    rng.save(&mut deps.storage)?;
+
+    Ok(HandleResponse {
+        messages,
+        log: logs,
+        data: Some(to_binary(&HandleAnswer::BatchReceiveNft {
+            status: ResponseStatus::Success,
+        })?),
+    })
+}
+
+fn set_loot_table<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: &Env,
+    box_type: String,
+    outcomes: Vec<LootOutcome>,
+) -> HandleResult {
+    let config = Config::get_unchecked(&deps.storage)?;
+    config.require_admin(env)?;
+
+    let loot_table = LootTable { outcomes };
+    loot_table.validate()?;
+    loot_table.save(&mut deps.storage, &box_type)?;
 
     Ok(HandleResponse {
-        messages: messages,
+        // Platforms instantiated before loot tables existed still have to register
+        messages: vec![register_receive_nft_msg(
+            config.legen_dao_nft,
+            env.contract_code_hash.clone(),
+        )?],
         log: vec![],
-        data: Some(to_binary(&HandleAnswer::OpenLootBox {
+        data: Some(to_binary(&HandleAnswer::SetLootTable {
+            status: ResponseStatus::Success,
+        })?),
+    })
+}
+
+fn remove_loot_table<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: &Env,
+    box_type: String,
+) -> HandleResult {
+    Config::get_unchecked(&deps.storage)?.require_admin(env)?;
+
+    LootTable::remove(&mut deps.storage, &box_type);
+
+    Ok(HandleResponse {
+        messages: vec![],
+        log: vec![],
+        data: Some(to_binary(&HandleAnswer::RemoveLootTable {
+            status: ResponseStatus::Success,
+        })?),
+    })
+}
+
+fn set_loot_box_types_handle<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: &Env,
+    box_type: String,
+    loot_box_ids: Vec<String>,
+) -> HandleResult {
+    Config::get_unchecked(&deps.storage)?.require_admin(env)?;
+
+    set_loot_box_types(&mut deps.storage, &box_type, loot_box_ids)?;
+
+    Ok(HandleResponse {
+        messages: vec![],
+        log: vec![],
+        data: Some(to_binary(&HandleAnswer::SetLootBoxTypes {
             status: ResponseStatus::Success,
         })?),
     })
 }
 
 pub fn query<S: Storage, A: Api, Q: Querier>(deps: &Extern<S, A, Q>, msg: QueryMsg) -> QueryResult {
+    if !matches!(msg, QueryMsg::StorageVersion {}) {
+        require_current_version(&deps.storage)?;
+    }
+
     match msg {
         QueryMsg::Config {} => query_config(deps),
+        QueryMsg::Signers {} => query_signers(deps),
+        QueryMsg::InstantRedeem {} => query_instant_redeem(deps),
+        QueryMsg::NativeToken {} => query_native_token(deps),
+        QueryMsg::LootTable { box_type } => query_loot_table(deps, box_type),
+        QueryMsg::LootBoxType { loot_box_id } => query_loot_box_type(deps, loot_box_id),
         QueryMsg::NumOfPendingClaims {} => query_num_of_pending_claims(deps),
-        QueryMsg::TotalBalances {} => query_total_balances(deps),
+        QueryMsg::ClaimQueue {} => query_claim_queue(deps),
+        QueryMsg::TotalBalances { token } => query_total_balances(deps, token),
+        QueryMsg::SupportedTokens {} => query_supported_tokens(deps),
+        QueryMsg::ReceivingContracts { page, page_size } => {
+            query_receiving_contracts(deps, page.unwrap_or(0), page_size)
+        }
+        QueryMsg::StorageVersion {} => query_storage_version(deps),
         QueryMsg::WithPermit { permit, query } => permit_queries(deps, permit, query),
         QueryMsg::Features(m) => match m {
             FeatureToggleQueryMsg::Status { features } => {
@@ -395,6 +670,12 @@ pub fn authenticated_queries<S: Storage, A: Api, Q: Querier>(
 
     match msg {
         QueryMsg::Balance { address, .. } => query_balance(deps, address),
+        QueryMsg::LootBoxHistory {
+            address,
+            page,
+            page_size,
+            ..
+        } => query_loot_box_history(deps, address, page.unwrap_or(0), page_size),
         _ => panic!("This should never happen"),
     }
 }
@@ -422,24 +703,49 @@ fn permit_queries<S: Storage, A: Api, Q: Querier>(
 
             query_balance(deps, account)
         }
+        QueryWithPermit::LootBoxHistory { page, page_size } => {
+            if !permit.check_permission(&TokenPermissions::History) {
+                return Err(StdError::generic_err(format!(
+                    "No permission to query loot box history, got permissions {:?}",
+                    permit.params.permissions
+                )));
+            }
+
+            query_loot_box_history(deps, account, page.unwrap_or(0), page_size)
+        }
+        QueryWithPermit::Allowances {} => {
+            if !permit.check_permission(&TokenPermissions::Allowance) {
+                return Err(StdError::generic_err(format!(
+                    "No permission to query allowances, got permissions {:?}",
+                    permit.params.permissions
+                )));
+            }
+
+            query_allowances(deps, account)
+        }
     }
 }
 
 fn deposit_impl<S: Storage, A: Api, Q: Querier>(
     deps: &mut Extern<S, A, Q>,
+    vault: &Vault,
     to: HumanAddr,
     amount: u128,
-) -> StdResult<()> {
+) -> StdResult<Event> {
     // check that the destination address is valid
     let _canonical = deps.api.canonical_address(&to).map_err(|_| {
         StdError::generic_err(format!("Can not deposit to {}. Not a valid address", to))
     })?;
 
-    let mut user_balance = Balances::load(&deps.storage, &to)?.unwrap_or_default();
+    let mut user_balance = Balances::load_settled(&mut deps.storage, vault, &to)?;
     user_balance.staked += amount;
-    user_balance.save(&mut deps.storage, &to)?;
+    user_balance.save(&mut deps.storage, vault, &to)?;
 
-    Ok(())
+    Ok(Event::Deposit {
+        account: to,
+        token: vault.id(),
+        amount: Uint128(amount),
+    })
 }
 
 fn deposit<S: Storage, A: Api, Q: Querier>(
@@ -459,13 +765,7 @@ fn batch_deposit<S: Storage, A: Api, Q: Querier>(
 ) -> HandleResult {
     FeatureToggle::require_not_paused(&deps.storage, vec![Features::Deposit])?;
 
-    let config = Config::get_unchecked(&deps.storage)?;
-    if env.message.sender != config.token.address {
-        return Err(StdError::generic_err(format!(
-            "this token is not supported. Supported: {}, got: {}",
-            config.token.address, env.message.sender
-        )));
-    }
+    let vault = Vault::load(&deps.storage, Some(&env.message.sender))?;
 
     // Check that the deposits reach the right sum
     let mut sum = amount.u128();
@@ -479,30 +779,81 @@ fn batch_deposit<S: Storage, A: Api, Q: Querier>(
         return Err(sum_err());
     }
 
+    let mut logs = vec![];
     for deposit in deposits {
-        deposit_impl(deps, deposit.to, deposit.amount.u128())?;
+        logs.push(deposit_impl(deps, &vault, deposit.to, deposit.amount.u128())?.log()?);
     }
 
-    TotalBalances::handle_balance_change(&mut deps.storage, BalanceChange::Deposit, amount.u128())?;
+    TotalBalances::handle_balance_change(
+        &mut deps.storage,
+        &vault,
+        BalanceChange::Deposit,
+        amount.u128(),
+    )?;
 
     Ok(HandleResponse {
         messages: vec![],
-        log: vec![],
+        log: logs,
         data: Some(to_binary(&HandleAnswer::Deposit {
             status: ResponseStatus::Success,
         })?),
     })
 }
 
+fn deposit_native<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: &Env,
+) -> HandleResult {
+    FeatureToggle::require_not_paused(&deps.storage, vec![Features::Deposit])?;
+
+    let native_token = NativeToken::load(&deps.storage)?;
+    let amount = match env.message.sent_funds.as_slice() {
+        [coin] if coin.denom == native_token.denom && !coin.amount.is_zero() => coin.amount,
+        _ => {
+            return Err(StdError::generic_err(format!(
+                "expected a deposit of {} only, got: {:?}",
+                native_token.denom, env.message.sent_funds
+            )))
+        }
+    };
+
+    let wrap_msg = deposit_native_msg(
+        native_token.wrapper.clone(),
+        Coin {
+            denom: native_token.denom.clone(),
+            amount,
+        },
+    )?;
+
+    let vault = Vault::native(native_token);
+    let event = deposit_impl(deps, &vault, env.message.sender.clone(), amount.u128())?;
+    TotalBalances::handle_balance_change(
+        &mut deps.storage,
+        &vault,
+        BalanceChange::Deposit,
+        amount.u128(),
+    )?;
+
+    Ok(HandleResponse {
+        messages: vec![wrap_msg],
+        log: vec![event.log()?],
+        data: Some(to_binary(&HandleAnswer::DepositNative {
+            status: ResponseStatus::Success,
+        })?),
+    })
+}
+
 fn redeem<S: Storage, A: Api, Q: Querier>(
     deps: &mut Extern<S, A, Q>,
     env: &Env,
+    token: Option<HumanAddr>,
     amount: Option<u128>,
 ) -> HandleResult {
     FeatureToggle::require_not_paused(&deps.storage, vec![Features::Redeem])?;
 
+    let vault = Vault::load(&deps.storage, token.as_ref())?;
     let user_address = env.message.sender.clone();
-    let mut user_balance = Balances::load(&deps.storage, &user_address)?.unwrap_or_default();
+    let mut user_balance = Balances::load_settled(&mut deps.storage, &vault, &user_address)?;
     let amount = amount.unwrap_or(user_balance.staked);
     if amount > user_balance.staked {
         return Err(StdError::generic_err(format!(
@@ -512,105 +863,400 @@ fn redeem<S: Storage, A: Api, Q: Querier>(
     }
     user_balance.pending_redeem.refresh(env); // This refresh is important, because it should prevent auto-claim DoS
 
-    let config = Config::get_unchecked(&deps.storage)?;
     AutoClaims::new_unbonding(
         &mut deps.storage,
         env,
-        &config,
+        &vault,
         user_address.clone(),
         &mut user_balance,
         amount,
     )?;
 
     user_balance.staked -= amount;
-    user_balance.save(&mut deps.storage, &user_address)?;
+    user_balance.save(&mut deps.storage, &vault, &user_address)?;
+
+    TotalBalances::handle_balance_change(&mut deps.storage, &vault, BalanceChange::Redeem, amount)?;
 
-    TotalBalances::handle_balance_change(&mut deps.storage, BalanceChange::Redeem, amount)?;
+    let event = Event::Redeem {
+        account: user_address,
+        token: vault.id(),
+        amount: Uint128(amount),
+    };
 
     Ok(HandleResponse {
         messages: vec![],
-        log: vec![],
+        log: vec![event.log()?],
         data: Some(to_binary(&HandleAnswer::Redeem {
             status: ResponseStatus::Success,
         })?),
     })
 }
 
-fn claim<S: Storage, A: Api, Q: Querier>(deps: &mut Extern<S, A, Q>, env: &Env) -> HandleResult {
-    FeatureToggle::require_not_paused(&deps.storage, vec![Features::Claim])?;
-
-    if let Some(messages) = do_claim(&mut deps.storage, env, &env.message.sender)? {
-        return Ok(HandleResponse {
-            messages,
-            log: vec![],
-            data: Some(to_binary(&HandleAnswer::ClaimRedeemed {
-                status: ResponseStatus::Success,
-            })?),
-        });
-    }
-
-    Err(StdError::generic_err("nothing to claim"))
-}
-
-fn send_from_platform<S: Storage, A: Api, Q: Querier>(
+fn redeem_instant<S: Storage, A: Api, Q: Querier>(
     deps: &mut Extern<S, A, Q>,
     env: &Env,
-    contract_addr: HumanAddr,
     amount: Option<u128>,
-    memo: Option<String>,
-    msg: Binary,
 ) -> HandleResult {
-    FeatureToggle::require_not_paused(&deps.storage, vec![Features::SendFromPlatform])?;
-    ReceivingContracts::require_receiving(&deps.storage, &contract_addr)?;
+    FeatureToggle::require_not_paused(&deps.storage, vec![Features::Redeem])?;
 
-    let mut user_balance = Balances::load(&deps.storage, &env.message.sender)?.unwrap_or_default();
-    let amount = amount.unwrap_or(user_balance.staked);
+    let instant_redeem = InstantRedeem::load(&deps.storage)?
+        .ok_or_else(|| StdError::generic_err("instant redeem is not enabled"))?;
 
+    let config = Config::get_unchecked(&deps.storage)?;
+    let vault = Vault::primary(&config);
+    let user_address = env.message.sender.clone();
+    let mut user_balance = Balances::load_settled(&mut deps.storage, &vault, &user_address)?;
+    let amount = amount.unwrap_or(user_balance.staked);
     if amount > user_balance.staked {
         return Err(StdError::generic_err(format!(
-            "insufficient staked funds to send: balance={}, required={}",
+            "insufficient staked funds to redeem: balance={}, required={}",
             user_balance.staked, amount,
         )));
     }
 
     user_balance.staked -= amount;
-    user_balance.save(&mut deps.storage, &env.message.sender)?;
-
-    TotalBalances::handle_balance_change(&mut deps.storage, BalanceChange::Send, amount)?;
+    user_balance.save(&mut deps.storage, &vault, &user_address)?;
 
-    let config = Config::get_unchecked(&deps.storage)?;
-    let inner_msg = to_binary(&PlatformApi::ReceiveFromPlatform {
-        from: env.message.sender.clone(),
-        msg,
-    })?;
-    let send_msg = snip20::send_msg(
-        contract_addr,
-        Uint128(amount),
-        Some(inner_msg),
-        memo,
-        None,
-        RESPONSE_BLOCK_SIZE,
-        config.token.hash,
-        config.token.address,
+    TotalBalances::handle_balance_change(
+        &mut deps.storage,
+        &vault,
+        BalanceChange::RedeemInstant,
+        amount,
     )?;
 
-    Ok(HandleResponse {
-        messages: vec![send_msg],
-        log: vec![],
-        data: Some(to_binary(&HandleAnswer::SendFromPlatform {
-            status: ResponseStatus::Success,
-        })?),
-    })
-}
+    let penalty = instant_redeem.penalty(amount);
+    let mut sink = instant_redeem.sink;
+    let mut messages = vec![];
 
-fn add_receiving_contracts<S: Storage, A: Api, Q: Querier>(
-    deps: &mut Extern<S, A, Q>,
-    env: &Env,
-    addresses: Vec<HumanAddr>,
-) -> HandleResult {
+    if penalty > 0 && sink == PenaltySink::Stakers {
+        let total_staked = TotalBalances::load(&deps.storage, &vault)?
+            .unwrap_or_default()
+            .staked;
+        let mut penalties = StakerPenalties::load(&deps.storage)?;
+        if penalties.distribute(penalty, total_staked) {
+            penalties.save(&mut deps.storage)?;
+            TotalBalances::handle_balance_change(
+                &mut deps.storage,
+                &vault,
+                BalanceChange::Deposit,
+                penalty,
+            )?;
+        } else {
+            // No one left to redistribute to
+            sink = PenaltySink::DistributeAddress;
+        }
+    }
+
+    if penalty > 0 {
+        match sink {
+            PenaltySink::DistributeAddress => messages.push(snip20::transfer_msg(
+                config.distribute_address.clone(),
+                Uint128(penalty),
+                None,
+                None,
+                RESPONSE_BLOCK_SIZE,
+                config.token.hash.clone(),
+                config.token.address.clone(),
+            )?),
+            PenaltySink::Burn => messages.push(snip20::burn_msg(
+                Uint128(penalty),
+                None,
+                None,
+                RESPONSE_BLOCK_SIZE,
+                config.token.hash.clone(),
+                config.token.address.clone(),
+            )?),
+            PenaltySink::Stakers => {}
+        }
+    }
+
+    let received = amount - penalty;
+    if received > 0 {
+        messages.extend(payout_msgs(&config, &vault, env, &user_address, received)?);
+    }
+
+    let event = Event::RedeemInstant {
+        account: user_address,
+        token: vault.id(),
+        amount: Uint128(amount),
+        penalty: Uint128(penalty),
+        penalty_sink: sink,
+    };
+
+    Ok(HandleResponse {
+        messages,
+        log: vec![event.log()?],
+        data: Some(to_binary(&HandleAnswer::RedeemInstant {
+            status: ResponseStatus::Success,
+            received: Uint128(received),
+            penalty: Uint128(penalty),
+        })?),
+    })
+}
+
+fn cancel_unbonding<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: &Env,
+    token: Option<HumanAddr>,
+    end_ts: u64,
+    amount: Option<u128>,
+) -> HandleResult {
+    FeatureToggle::require_not_paused(&deps.storage, vec![Features::Redeem])?;
+
+    let vault = Vault::load(&deps.storage, token.as_ref())?;
+    let user_address = env.message.sender.clone();
+    let mut user_balance = Balances::load_settled(&mut deps.storage, &vault, &user_address)?;
+
+    // The record stays in the auto-claim queue, which is fine since claiming skips accounts with
+    // nothing to claim
+    let amount = user_balance
+        .pending_redeem
+        .cancel_unbonding(env, end_ts, amount)?;
+
+    user_balance.staked += amount;
+    user_balance.save(&mut deps.storage, &vault, &user_address)?;
+
+    TotalBalances::handle_balance_change(
+        &mut deps.storage,
+        &vault,
+        BalanceChange::CancelUnbonding,
+        amount,
+    )?;
+
+    let event = Event::CancelUnbonding {
+        account: user_address,
+        token: vault.id(),
+        end_ts,
+        amount: Uint128(amount),
+    };
+
+    Ok(HandleResponse {
+        messages: vec![],
+        log: vec![event.log()?],
+        data: Some(to_binary(&HandleAnswer::CancelUnbonding {
+            status: ResponseStatus::Success,
+            amount: Uint128(amount),
+        })?),
+    })
+}
+
+fn claim<S: Storage, A: Api, Q: Querier>(deps: &mut Extern<S, A, Q>, env: &Env) -> HandleResult {
+    FeatureToggle::require_not_paused(&deps.storage, vec![Features::Claim])?;
+
+    let mut messages = vec![];
+    let mut logs = vec![];
+    for vault in Vault::load_all(&deps.storage)? {
+        let account = &env.message.sender;
+        if let Some((claim_messages, amount)) = do_claim(&mut deps.storage, env, &vault, account)? {
+            messages.extend(claim_messages);
+            let event = Event::Claim {
+                account: account.clone(),
+                token: vault.id(),
+                amount: Uint128(amount),
+            };
+            logs.push(event.log()?);
+        }
+    }
+
+    if messages.is_empty() {
+        return Err(StdError::generic_err("nothing to claim"));
+    }
+
+    Ok(HandleResponse {
+        messages,
+        log: logs,
+        data: Some(to_binary(&HandleAnswer::ClaimRedeemed {
+            status: ResponseStatus::Success,
+        })?),
+    })
+}
+
+fn process_claims<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: &Env,
+    limit: Option<u32>,
+) -> HandleResult {
+    FeatureToggle::require_not_paused(&deps.storage, vec![Features::Claim])?;
+
+    let limit = limit.unwrap_or(DEFAULT_CLAIMS_PER_CRANK).min(MAX_CLAIMS_PER_CRANK);
+    let (messages, processed) = AutoClaims::claim_pending_msgs(&mut deps.storage, env, limit)?;
+
+    Ok(HandleResponse {
+        messages,
+        log: vec![],
+        data: Some(to_binary(&HandleAnswer::ProcessClaims {
+            status: ResponseStatus::Success,
+            processed,
+        })?),
+    })
+}
+
+#[allow(clippy::too_many_arguments)]
+fn send_from_platform<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: &Env,
+    contract_addr: HumanAddr,
+    owner: Option<HumanAddr>,
+    token: Option<HumanAddr>,
+    amount: Option<u128>,
+    memo: Option<String>,
+    msg: Binary,
+) -> HandleResult {
+    FeatureToggle::require_not_paused(&deps.storage, vec![Features::SendFromPlatform])?;
+    let receiving_contract = ReceivingContracts::require_receiving(&deps.storage, &contract_addr)?;
+
+    let vault = Vault::load(&deps.storage, token.as_ref())?;
+    let owner = owner.unwrap_or_else(|| env.message.sender.clone());
+    let mut user_balance = Balances::load_settled(&mut deps.storage, &vault, &owner)?;
+
+    let amount = if owner == env.message.sender {
+        amount.unwrap_or(user_balance.staked)
+    } else {
+        let remaining = PlatformAllowances::remaining(
+            &deps.storage,
+            &owner,
+            &env.message.sender,
+            &vault.id(),
+            env.block.time,
+        )?;
+        let amount = amount.unwrap_or_else(|| remaining.min(user_balance.staked));
+        PlatformAllowances::spend(
+            &mut deps.storage,
+            &owner,
+            &env.message.sender,
+            &vault.id(),
+            amount,
+            env.block.time,
+        )?;
+        amount
+    };
+
+    if amount > user_balance.staked {
+        return Err(StdError::generic_err(format!(
+            "insufficient staked funds to send: balance={}, required={}",
+            user_balance.staked, amount,
+        )));
+    }
+
+    ReceivingContracts::record_send(
+        &mut deps.storage,
+        &receiving_contract,
+        &vault.id(),
+        amount,
+        env.block.time,
+    )?;
+
+    user_balance.staked -= amount;
+    user_balance.save(&mut deps.storage, &vault, &owner)?;
+
+    TotalBalances::handle_balance_change(&mut deps.storage, &vault, BalanceChange::Send, amount)?;
+
+    let event = Event::SendFromPlatform {
+        owner: owner.clone(),
+        sender: env.message.sender.clone(),
+        contract: contract_addr.clone(),
+        token: vault.id(),
+        amount: Uint128(amount),
+    };
+
+    let inner_msg = to_binary(&PlatformApi::ReceiveFromPlatform { from: owner, msg })?;
+    let send_msg = snip20::send_msg_with_code_hash(
+        contract_addr,
+        receiving_contract.code_hash,
+        Uint128(amount),
+        Some(inner_msg),
+        memo,
+        None,
+        RESPONSE_BLOCK_SIZE,
+        vault.token.hash,
+        vault.token.address,
+    )?;
+
+    Ok(HandleResponse {
+        messages: vec![send_msg],
+        log: vec![event.log()?],
+        data: Some(to_binary(&HandleAnswer::SendFromPlatform {
+            status: ResponseStatus::Success,
+        })?),
+    })
+}
+
+fn increase_platform_allowance<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: &Env,
+    spender: HumanAddr,
+    token: Option<HumanAddr>,
+    amount: u128,
+    expiration: Option<u64>,
+) -> HandleResult {
+    let vault = Vault::load(&deps.storage, token.as_ref())?;
+    let allowance = PlatformAllowances::increase(
+        &mut deps.storage,
+        &env.message.sender,
+        spender,
+        vault.id(),
+        amount,
+        expiration,
+        env.block.time,
+    )?;
+
+    let event = Event::PlatformAllowance {
+        owner: env.message.sender.clone(),
+        spender: allowance.spender.clone(),
+        token: allowance.token.clone(),
+        amount: allowance.amount,
+        expiration: allowance.expiration,
+    };
+
+    Ok(HandleResponse {
+        messages: vec![],
+        log: vec![event.log()?],
+        data: Some(to_binary(&HandleAnswer::IncreasePlatformAllowance {
+            status: ResponseStatus::Success,
+            allowance,
+        })?),
+    })
+}
+
+fn revoke_platform_allowance<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: &Env,
+    spender: HumanAddr,
+    token: Option<HumanAddr>,
+) -> HandleResult {
+    let vault = Vault::load(&deps.storage, token.as_ref())?;
+    PlatformAllowances::revoke(
+        &mut deps.storage,
+        &env.message.sender,
+        &spender,
+        &vault.id(),
+    )?;
+
+    let event = Event::PlatformAllowance {
+        owner: env.message.sender.clone(),
+        spender,
+        token: vault.id(),
+        amount: Uint128::zero(),
+        expiration: None,
+    };
+
+    Ok(HandleResponse {
+        messages: vec![],
+        log: vec![event.log()?],
+        data: Some(to_binary(&HandleAnswer::RevokePlatformAllowance {
+            status: ResponseStatus::Success,
+        })?),
+    })
+}
+
+fn add_receiving_contracts<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: &Env,
+    addresses: Vec<HumanAddr>,
+) -> HandleResult {
     Config::get_unchecked(&deps.storage)?.require_admin(env)?;
 
-    ReceivingContracts::set_multiple(&mut deps.storage, addresses)?;
+    ReceivingContracts::add_multiple(&mut deps.storage, addresses)?;
 
     Ok(HandleResponse {
         messages: vec![],
@@ -621,6 +1267,24 @@ fn add_receiving_contracts<S: Storage, A: Api, Q: Querier>(
     })
 }
 
+fn set_receiving_contracts<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: &Env,
+    contracts: Vec<ReceivingContract>,
+) -> HandleResult {
+    Config::get_unchecked(&deps.storage)?.require_admin(env)?;
+
+    ReceivingContracts::set_multiple(&mut deps.storage, contracts)?;
+
+    Ok(HandleResponse {
+        messages: vec![],
+        log: vec![],
+        data: Some(to_binary(&HandleAnswer::SetReceivingContracts {
+            status: ResponseStatus::Success,
+        })?),
+    })
+}
+
 fn remove_receiving_contracts<S: Storage, A: Api, Q: Querier>(
     deps: &mut Extern<S, A, Q>,
     env: &Env,
@@ -628,7 +1292,7 @@ fn remove_receiving_contracts<S: Storage, A: Api, Q: Querier>(
 ) -> HandleResult {
     Config::get_unchecked(&deps.storage)?.require_admin(env)?;
 
-    ReceivingContracts::remove_multiple(&mut deps.storage, addresses);
+    ReceivingContracts::remove_multiple(&mut deps.storage, addresses)?;
 
     Ok(HandleResponse {
         messages: vec![],
@@ -639,6 +1303,35 @@ fn remove_receiving_contracts<S: Storage, A: Api, Q: Querier>(
     })
 }
 
+fn add_supported_tokens<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: &Env,
+    tokens: Vec<SupportedToken>,
+) -> HandleResult {
+    Config::get_unchecked(&deps.storage)?.require_admin(env)?;
+
+    let mut messages = vec![];
+    for token in &tokens {
+        messages.push(snip20::register_receive_msg(
+            env.contract_code_hash.clone(),
+            None,
+            RESPONSE_BLOCK_SIZE,
+            token.contract.hash.clone(),
+            token.contract.address.clone(),
+        )?);
+    }
+
+    SupportedToken::set_multiple(&mut deps.storage, tokens)?;
+
+    Ok(HandleResponse {
+        messages,
+        log: vec![],
+        data: Some(to_binary(&HandleAnswer::AddSupportedTokens {
+            status: ResponseStatus::Success,
+        })?),
+    })
+}
+
 fn create_viewing_key<S: Storage, A: Api, Q: Querier>(
     deps: &mut Extern<S, A, Q>,
     env: &Env,
@@ -716,10 +1409,38 @@ fn change_config<S: Storage, A: Api, Q: Querier>(
     env: &Env,
     admin: Option<HumanAddr>,
     unbonding_period: Option<u64>,
+    instant_redeem: Option<InstantRedeem>,
+    native_token: Option<NativeToken>,
 ) -> StdResult<HandleResponse> {
     let mut config = Config::get_unchecked(&deps.storage)?;
     config.require_admin(env)?;
 
+    if let Some(instant_redeem) = instant_redeem {
+        // Redeeming instantly for free would make the unbonding period pointless
+        if instant_redeem.penalty_bps == 0 {
+            InstantRedeem::remove(&mut deps.storage);
+        } else {
+            instant_redeem.save(&mut deps.storage)?;
+        }
+    }
+
+    if let Some(native_token) = native_token {
+        // Native balances are kept under the denom and held as the wrapper, changing either would
+        // strand the deposits made so far
+        let current = NativeToken::load(&deps.storage)?;
+        if native_token.denom != current.denom || native_token.wrapper != current.wrapper {
+            let total_balances =
+                TotalBalances::load(&deps.storage, &Vault::native(current))?.unwrap_or_default();
+            if total_balances.staked != 0 || total_balances.unbonding != 0 {
+                return Err(StdError::generic_err(
+                    "can't change the native denom or wrapper while native deposits are held",
+                ));
+            }
+        }
+
+        native_token.save(&mut deps.storage)?;
+    }
+
     if let Some(admin) = admin {
         config.admin = admin;
     }
@@ -739,32 +1460,245 @@ fn change_config<S: Storage, A: Api, Q: Querier>(
     })
 }
 
+fn add_signers<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: &Env,
+    new_signers: Vec<Signer>,
+) -> HandleResult {
+    Config::get_unchecked(&deps.storage)?.require_admin(env)?;
+
+    let mut signers = Signers::load(&deps.storage)?;
+    signers.set_multiple(new_signers)?;
+    signers.save(&mut deps.storage)?;
+
+    Ok(HandleResponse {
+        messages: vec![],
+        log: vec![],
+        data: Some(to_binary(&HandleAnswer::AddSigners {
+            status: ResponseStatus::Success,
+        })?),
+    })
+}
+
+fn remove_signers<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: &Env,
+    public_keys: Vec<Binary>,
+) -> HandleResult {
+    Config::get_unchecked(&deps.storage)?.require_admin(env)?;
+
+    let mut signers = Signers::load(&deps.storage)?;
+    signers.remove_multiple(&public_keys);
+    signers.save(&mut deps.storage)?;
+
+    Ok(HandleResponse {
+        messages: vec![],
+        log: vec![],
+        data: Some(to_binary(&HandleAnswer::RemoveSigners {
+            status: ResponseStatus::Success,
+        })?),
+    })
+}
+
+fn set_signer_threshold<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: &Env,
+    threshold: u32,
+) -> HandleResult {
+    Config::get_unchecked(&deps.storage)?.require_admin(env)?;
+
+    let mut signers = Signers::load(&deps.storage)?;
+    signers.set_threshold(threshold)?;
+    signers.save(&mut deps.storage)?;
+
+    Ok(HandleResponse {
+        messages: vec![],
+        log: vec![],
+        data: Some(to_binary(&HandleAnswer::SetSignerThreshold {
+            status: ResponseStatus::Success,
+        })?),
+    })
+}
+
+fn migrate_storage<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: &Env,
+    random_seed: Binary,
+    legacy_receiving_contracts: Vec<HumanAddr>,
+) -> HandleResult {
+    // `Config` may not be readable before the migration
+    if load_admin(&deps.storage)? != env.message.sender {
+        return Err(StdError::unauthorized());
+    }
+
+    let from_version = migrate(
+        &mut deps.storage,
+        random_seed.as_slice(),
+        &legacy_receiving_contracts,
+    )?;
+
+    Ok(HandleResponse {
+        messages: vec![],
+        log: vec![],
+        data: Some(to_binary(&HandleAnswer::Migrate {
+            status: Success,
+            from_version,
+            version: STORAGE_VERSION,
+        })?),
+    })
+}
+
 fn query_config<S: Storage, A: Api, Q: Querier>(deps: &Extern<S, A, Q>) -> QueryResult {
     let config = Config::get_unchecked(&deps.storage)?;
     let result = to_binary(&QueryAnswer::Config(config))?;
     Ok(result)
 }
 
+fn query_signers<S: Storage, A: Api, Q: Querier>(deps: &Extern<S, A, Q>) -> QueryResult {
+    let signers = Signers::load(&deps.storage)?;
+    let result = to_binary(&QueryAnswer::Signers(signers))?;
+    Ok(result)
+}
+
+fn query_instant_redeem<S: Storage, A: Api, Q: Querier>(deps: &Extern<S, A, Q>) -> QueryResult {
+    let instant_redeem = InstantRedeem::load(&deps.storage)?;
+    let result = to_binary(&QueryAnswer::InstantRedeem(instant_redeem))?;
+    Ok(result)
+}
+
+fn query_supported_tokens<S: Storage, A: Api, Q: Querier>(deps: &Extern<S, A, Q>) -> QueryResult {
+    let tokens = SupportedToken::load_all(&deps.storage)?;
+    let result = to_binary(&QueryAnswer::SupportedTokens(tokens))?;
+    Ok(result)
+}
+
+fn query_receiving_contracts<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    page: u32,
+    page_size: u32,
+) -> QueryResult {
+    let page_size = page_size.min(MAX_PAGE_SIZE);
+    let (contracts, total) = ReceivingContracts::page(&deps.storage, page, page_size)?;
+    let result = to_binary(&QueryAnswer::ReceivingContracts(ResponseReceivingContracts {
+        contracts,
+        total: total as u64,
+    }))?;
+    Ok(result)
+}
+
+fn query_native_token<S: Storage, A: Api, Q: Querier>(deps: &Extern<S, A, Q>) -> QueryResult {
+    let native_token = NativeToken::load(&deps.storage)?;
+    let result = to_binary(&QueryAnswer::NativeToken(native_token))?;
+    Ok(result)
+}
+
+fn query_storage_version<S: Storage, A: Api, Q: Querier>(deps: &Extern<S, A, Q>) -> QueryResult {
+    to_binary(&QueryAnswer::StorageVersion {
+        version: load_version(&deps.storage)?,
+        latest: STORAGE_VERSION,
+    })
+}
+
+fn query_loot_table<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    box_type: String,
+) -> QueryResult {
+    let loot_table = LootTable::load(&deps.storage, &box_type)?.ok_or_else(|| {
+        StdError::generic_err(format!("no loot table for box type {}", box_type))
+    })?;
+
+    let result = to_binary(&QueryAnswer::LootTable(ResponseLootTable {
+        box_type,
+        total_weight: loot_table.total_weight(),
+        outcomes: loot_table.outcomes,
+    }))?;
+    Ok(result)
+}
+
+fn query_loot_box_type<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    loot_box_id: String,
+) -> QueryResult {
+    let box_type = loot_box_type(&deps.storage, &loot_box_id)?;
+    let result = to_binary(&QueryAnswer::LootBoxType(box_type))?;
+    Ok(result)
+}
+
 fn query_num_of_pending_claims<S: Storage, A: Api, Q: Querier>(
     deps: &Extern<S, A, Q>,
 ) -> QueryResult {
-    let len = AutoClaims::len(&deps.storage)?;
+    let mut len = 0;
+    for vault in Vault::load_all(&deps.storage)? {
+        len += AutoClaims::len(&deps.storage, &vault)?;
+    }
     let result = to_binary(&QueryAnswer::NumOfPendingClaims(Uint128::from(len as u64)))?;
     Ok(result)
 }
 
+fn query_claim_queue<S: Storage, A: Api, Q: Querier>(deps: &Extern<S, A, Q>) -> QueryResult {
+    let mut pending = 0;
+    let mut next_end_ts: Option<u64> = None;
+    for vault in Vault::load_all(&deps.storage)? {
+        pending += AutoClaims::len(&deps.storage, &vault)?;
+        if let Some(end_ts) = AutoClaims::next_end_ts(&deps.storage, &vault)? {
+            next_end_ts = Some(next_end_ts.map_or(end_ts, |ts| ts.min(end_ts)));
+        }
+    }
+
+    let result = to_binary(&QueryAnswer::ClaimQueue(ResponseClaimQueue {
+        pending,
+        next_end_ts,
+    }))?;
+    Ok(result)
+}
+
 fn query_balance<S: Storage, A: Api, Q: Querier>(
     deps: &Extern<S, A, Q>,
     account: HumanAddr,
 ) -> QueryResult {
     // Assuming authentication occurs in the calling function
-    let balance = Balances::load(&deps.storage, &account)?.unwrap_or_default();
-    let result = to_binary(&QueryAnswer::Balance(balance.into()))?;
+    let mut tokens = vec![];
+    for vault in Vault::load_all(&deps.storage)? {
+        let balance = Balances::load_with_penalties(&deps.storage, &vault, &account)?;
+        tokens.push(ResponseTokenBalances::new(vault.id(), balance));
+    }
+
+    let result = to_binary(&QueryAnswer::Balance(ResponseBalances::new(tokens)))?;
+    Ok(result)
+}
+
+fn query_allowances<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    account: HumanAddr,
+) -> QueryResult {
+    // Assuming authentication occurs in the calling function
+    let allowances = PlatformAllowances::load(&deps.storage, &account)?;
+    let result = to_binary(&QueryAnswer::Allowances(allowances))?;
     Ok(result)
 }
 
-fn query_total_balances<S: Storage, A: Api, Q: Querier>(deps: &Extern<S, A, Q>) -> QueryResult {
-    let total_balances = TotalBalances::load(&deps.storage)?.unwrap_or_default();
+fn query_loot_box_history<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    account: HumanAddr,
+    page: u32,
+    page_size: u32,
+) -> QueryResult {
+    // Assuming authentication occurs in the calling function
+    let page_size = page_size.min(MAX_PAGE_SIZE);
+    let (records, total) = LootBoxHistory::page(&deps.storage, &account, page, page_size)?;
+    let result = to_binary(&QueryAnswer::LootBoxHistory(ResponseLootBoxHistory {
+        records,
+        total: total as u64,
+    }))?;
+    Ok(result)
+}
+
+fn query_total_balances<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    token: Option<HumanAddr>,
+) -> QueryResult {
+    let vault = Vault::load(&deps.storage, token.as_ref())?;
+    let total_balances = TotalBalances::load(&deps.storage, &vault)?.unwrap_or_default();
     let result = to_binary(&QueryAnswer::TotalBalances(
         total_balances.to_query_result().into(),
     ))?;
@@ -775,10 +1709,11 @@ fn query_total_balances<S: Storage, A: Api, Q: Querier>(deps: &Extern<S, A, Q>)
 pub fn do_claim<S: Storage>(
     storage: &mut S,
     env: &Env,
+    vault: &Vault,
     account: &HumanAddr,
-) -> StdResult<Option<Vec<CosmosMsg>>> {
+) -> StdResult<Option<(Vec<CosmosMsg>, u128)>> {
     let config = Config::get_unchecked(storage)?;
-    let mut user_balance = Balances::load(storage, account)?.unwrap_or_default();
+    let mut user_balance = Balances::load(storage, vault, account)?.unwrap_or_default();
     user_balance.pending_redeem.refresh(env);
 
     let amount = user_balance.pending_redeem.claimable;
@@ -787,29 +1722,252 @@ pub fn do_claim<S: Storage>(
     }
 
     user_balance.pending_redeem.claimable -= amount;
-    user_balance.save(storage, account)?;
+    user_balance.save(storage, vault, account)?;
 
-    TotalBalances::handle_balance_change(storage, BalanceChange::Claim, amount)?;
+    TotalBalances::handle_balance_change(storage, vault, BalanceChange::Claim, amount)?;
+
+    let messages = payout_msgs(&config, vault, env, account, amount)?;
+
+    Ok(Some((messages, amount)))
+}
 
+/// Pays `amount` of the vault's token to `account`. LGND and native deposits are redeemed for
+/// native coins, the other tokens are transferred as they are
+fn payout_msgs(
+    config: &Config,
+    vault: &Vault,
+    env: &Env,
+    account: &HumanAddr,
+    amount: u128,
+) -> StdResult<Vec<CosmosMsg>> {
     let amount = Uint128::from(amount);
-    let messages = vec![
+    let denom = match vault.payout_denom(config) {
+        Some(denom) => denom,
+        None => {
+            return Ok(vec![snip20::transfer_msg(
+                account.clone(),
+                amount,
+                None,
+                None,
+                RESPONSE_BLOCK_SIZE,
+                vault.token.hash.clone(),
+                vault.token.address.clone(),
+            )?])
+        }
+    };
+
+    Ok(vec![
         snip20::redeem_msg(
             amount,
-            Some(config.native_token_denom.clone()),
+            Some(denom.clone()),
             None,
             RESPONSE_BLOCK_SIZE,
-            config.token.hash,
-            config.token.address,
+            vault.token.hash.clone(),
+            vault.token.address.clone(),
         )?,
         CosmosMsg::Bank(BankMsg::Send {
             from_address: env.contract.address.clone(),
             to_address: account.into(),
-            amount: vec![Coin {
-                denom: config.native_token_denom,
-                amount,
-            }],
+            amount: vec![Coin { denom, amount }],
         }),
-    ];
+    ])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::msg::ReceiveMsg;
+    use crate::state::PenaltySink;
+    use cosmwasm_std::testing::{mock_dependencies, mock_env, MockApi, MockQuerier, MockStorage};
+
+    fn contract(address: &str) -> Contract {
+        Contract {
+            address: HumanAddr::from(address),
+            hash: format!("{}_hash", address),
+        }
+    }
+
+    fn init_platform() -> Extern<MockStorage, MockApi, MockQuerier> {
+        let mut deps = mock_dependencies(20, &[]);
+        let msg = InitMsg {
+            token: contract("lgnd"),
+            token_native_denom: "uscrt".to_string(),
+            legen_dao_nft: contract("nft"),
+            unbonding_period: None,
+            receiving_contracts: None,
+            viewing_key: "key".to_string(),
+            distribute_address: HumanAddr::from("distribute"),
+            signer_address: Binary(vec![2; 33]),
+            random_seed: Binary(b"seed".to_vec()),
+        };
+        init(&mut deps, mock_env("admin", &[]), msg).unwrap();
+
+        deps
+    }
+
+    fn deposit_lgnd(deps: &mut Extern<MockStorage, MockApi, MockQuerier>, to: &str, amount: u128) {
+        let msg = HandleMsg::Receive {
+            sender: HumanAddr::from(to),
+            from: HumanAddr::from(to),
+            amount: Uint128(amount),
+            msg: ReceiveMsg::Deposit {
+                to: HumanAddr::from(to),
+            }
+            .into(),
+        };
+        handle(deps, mock_env("lgnd", &[]), msg).unwrap();
+    }
+
+    fn set_instant_redeem(
+        deps: &mut Extern<MockStorage, MockApi, MockQuerier>,
+        penalty_bps: u16,
+    ) -> HandleResult {
+        let msg = HandleMsg::ChangeConfig {
+            admin: None,
+            unbonding_period: None,
+            instant_redeem: Some(InstantRedeem {
+                penalty_bps,
+                sink: PenaltySink::Stakers,
+            }),
+            native_token: None,
+        };
+        handle(deps, mock_env("admin", &[]), msg)
+    }
+
+    fn set_native_token(
+        deps: &mut Extern<MockStorage, MockApi, MockQuerier>,
+        denom: &str,
+        wrapper: &str,
+        unbonding_period: u64,
+    ) -> HandleResult {
+        let msg = HandleMsg::ChangeConfig {
+            admin: None,
+            unbonding_period: None,
+            instant_redeem: None,
+            native_token: Some(NativeToken {
+                denom: denom.to_string(),
+                wrapper: contract(wrapper),
+                unbonding_period,
+            }),
+        };
+        handle(deps, mock_env("admin", &[]), msg)
+    }
+
+    fn staked(deps: &Extern<MockStorage, MockApi, MockQuerier>, account: &str) -> u128 {
+        let vault = Vault::primary(&Config::get_unchecked(&deps.storage).unwrap());
+        Balances::load_with_penalties(&deps.storage, &vault, &HumanAddr::from(account))
+            .unwrap()
+            .staked
+    }
+
+    #[test]
+    fn test_redeem_instant_penalty_to_stakers() {
+        let mut deps = init_platform();
+        deposit_lgnd(&mut deps, "alice", 300);
+        deposit_lgnd(&mut deps, "bob", 100);
+        deposit_lgnd(&mut deps, "carol", 200);
+        set_instant_redeem(&mut deps, 1_000).unwrap();
+
+        let msg = HandleMsg::RedeemInstant { amount: None };
+        handle(&mut deps, mock_env("carol", &[]), msg).unwrap();
+
+        // The 20 LGND penalty is shared 3:1
+        assert_eq!(staked(&deps, "alice"), 315);
+        assert_eq!(staked(&deps, "bob"), 105);
+        assert_eq!(staked(&deps, "carol"), 0);
+
+        let vault = Vault::primary(&Config::get_unchecked(&deps.storage).unwrap());
+        let total_balances = TotalBalances::load(&deps.storage, &vault).unwrap().unwrap();
+        assert_eq!(total_balances.staked, 420);
+
+        // Settling doesn't credit the penalty twice
+        deposit_lgnd(&mut deps, "alice", 100);
+        assert_eq!(staked(&deps, "alice"), 415);
+        assert_eq!(staked(&deps, "bob"), 105);
+    }
+
+    #[test]
+    fn test_disable_instant_redeem() {
+        let mut deps = init_platform();
+        deposit_lgnd(&mut deps, "alice", 100);
+        set_instant_redeem(&mut deps, 1_000).unwrap();
+        set_instant_redeem(&mut deps, 0).unwrap();
+
+        assert!(InstantRedeem::load(&deps.storage).unwrap().is_none());
+        let msg = HandleMsg::RedeemInstant { amount: None };
+        assert!(handle(&mut deps, mock_env("alice", &[]), msg).is_err());
+        assert_eq!(staked(&deps, "alice"), 100);
+    }
+
+    #[test]
+    fn test_deposit_native() {
+        let mut deps = init_platform();
+        set_native_token(&mut deps, "uatom", "satom", 100).unwrap();
+
+        let coins = [Coin::new(300, "uatom")];
+        let env = mock_env("alice", &coins);
+        let res = handle(&mut deps, env, HandleMsg::DepositNative {}).unwrap();
+        assert_eq!(
+            res.messages,
+            vec![deposit_native_msg(contract("satom"), Coin::new(300, "uatom")).unwrap()]
+        );
+
+        let vault = Vault::load(&deps.storage, Some(&HumanAddr::from("uatom"))).unwrap();
+        let balances = Balances::load(&deps.storage, &vault, &HumanAddr::from("alice"))
+            .unwrap()
+            .unwrap();
+        assert_eq!(balances.staked, 300);
+        assert_eq!(TotalBalances::load(&deps.storage, &vault).unwrap().unwrap().staked, 300);
+        assert_eq!(staked(&deps, "alice"), 0);
+
+        for coins in [
+            vec![],
+            vec![Coin::new(300, "uscrt")],
+            vec![Coin::new(0, "uatom")],
+            vec![Coin::new(300, "uatom"), Coin::new(300, "uscrt")],
+        ] {
+            let env = mock_env("alice", &coins);
+            assert!(handle(&mut deps, env, HandleMsg::DepositNative {}).is_err());
+        }
+    }
 
-    Ok(Some(messages))
+    #[test]
+    fn test_change_native_token_with_deposits() {
+        let mut deps = init_platform();
+        set_native_token(&mut deps, "uatom", "satom", 100).unwrap();
+
+        let coins = [Coin::new(300, "uatom")];
+        handle(&mut deps, mock_env("alice", &coins), HandleMsg::DepositNative {}).unwrap();
+
+        assert!(set_native_token(&mut deps, "uosmo", "satom", 100).is_err());
+        assert!(set_native_token(&mut deps, "uatom", "other_satom", 100).is_err());
+        set_native_token(&mut deps, "uatom", "satom", 200).unwrap();
+        assert_eq!(NativeToken::load(&deps.storage).unwrap().unbonding_period, 200);
+    }
+
+    #[test]
+    fn test_auto_claims_of_other_vaults_wait_for_crank() {
+        let mut deps = init_platform();
+        set_native_token(&mut deps, "uatom", "satom", 100).unwrap();
+
+        let coins = [Coin::new(300, "uatom")];
+        handle(&mut deps, mock_env("alice", &coins), HandleMsg::DepositNative {}).unwrap();
+        let msg = HandleMsg::Redeem {
+            token: Some(HumanAddr::from("uatom")),
+            amount: None,
+        };
+        handle(&mut deps, mock_env("alice", &[]), msg).unwrap();
+
+        let mut env = mock_env("bob", &[]);
+        env.block.time += 100;
+        let msg = HandleMsg::SetViewingKey {
+            key: "key".to_string(),
+            padding: None,
+        };
+        let res = handle(&mut deps, env.clone(), msg).unwrap();
+        assert!(res.messages.is_empty());
+
+        let res = handle(&mut deps, env, HandleMsg::ProcessClaims { limit: None }).unwrap();
+        assert!(!res.messages.is_empty());
+    }
 }
@@ -0,0 +1,191 @@
+use cosmwasm_std::{
+    log, plaintext_log, to_vec, HumanAddr, LogAttribute, ReadonlyStorage, StdError, StdResult,
+    Storage, Uint128,
+};
+use schemars::JsonSchema;
+use secret_toolkit::storage::{TypedStore, TypedStoreMut};
+use serde::Serialize;
+
+use crate::loot_table::LootDraw;
+use crate::msg::ResponseTotalBalances;
+use crate::state::{PenaltySink, TotalBalances, Vault};
+
+/// Version of the `Event` format. Bump it whenever an event changes in a non additive way
+pub const EVENTS_VERSION: u32 = 1;
+
+const KEY_EVENT_COUNTER: &[u8] = b"event_counter";
+
+/// Log keys of the events. Every transaction logs:
+/// * `events_version` (plaintext) - `EVENTS_VERSION`
+/// * `event_counter` (plaintext) - number of transactions handled so far. It counts transactions
+///   rather than events, so it doesn't tell what a transaction did
+/// * `total_balances` (plaintext) - obfuscated totals of all the tokens, like the `total_balances`
+///   query
+/// * `event` (encrypted) - one per `Event`, as JSON
+pub const LOG_EVENTS_VERSION: &str = "events_version";
+pub const LOG_EVENT_COUNTER: &str = "event_counter";
+pub const LOG_TOTAL_BALANCES: &str = "total_balances";
+pub const LOG_EVENT: &str = "event";
+
+/// State changes of the platform, for indexers
+#[derive(Serialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case", tag = "type")]
+pub enum Event {
+    Deposit {
+        account: HumanAddr,
+        token: HumanAddr,
+        amount: Uint128,
+    },
+    Redeem {
+        account: HumanAddr,
+        token: HumanAddr,
+        amount: Uint128,
+    },
+    RedeemInstant {
+        account: HumanAddr,
+        token: HumanAddr,
+        amount: Uint128,
+        penalty: Uint128,
+        penalty_sink: PenaltySink,
+    },
+    CancelUnbonding {
+        account: HumanAddr,
+        token: HumanAddr,
+        end_ts: u64,
+        amount: Uint128,
+    },
+    Claim {
+        account: HumanAddr,
+        token: HumanAddr,
+        amount: Uint128,
+    },
+    SendFromPlatform {
+        owner: HumanAddr,
+        /// Differs from `owner` when sent using an allowance
+        sender: HumanAddr,
+        contract: HumanAddr,
+        token: HumanAddr,
+        amount: Uint128,
+    },
+    PlatformAllowance {
+        owner: HumanAddr,
+        spender: HumanAddr,
+        token: HumanAddr,
+        /// Zero when revoked
+        amount: Uint128,
+        expiration: Option<u64>,
+    },
+    OpenLootBox {
+        account: HumanAddr,
+        loot_box_id: String,
+        lgnd_amount: Uint128,
+        nft_contract: Option<HumanAddr>,
+        nft_uri: Option<String>,
+    },
+    /// Handles of the admin and the pausers, named like the handle
+    Admin { action: String },
+}
+
+impl Event {
+    /// Events carry account data, so they are always encrypted
+    pub fn log(&self) -> StdResult<LogAttribute> {
+        Ok(log(LOG_EVENT, to_json(self)?))
+    }
+
+    pub fn open_loot_box(account: &HumanAddr, loot_box_id: &str, draw: &LootDraw) -> Self {
+        Event::OpenLootBox {
+            account: account.clone(),
+            loot_box_id: loot_box_id.to_string(),
+            lgnd_amount: draw.lgnd_amount,
+            nft_contract: draw.nft_contract.as_ref().map(|c| c.address.clone()),
+            nft_uri: draw.nft_uri.clone(),
+        }
+    }
+}
+
+/// The plaintext logs of a transaction, see `LOG_EVENT_COUNTER`
+pub fn transaction_logs<S: Storage>(storage: &mut S) -> StdResult<Vec<LogAttribute>> {
+    let counter = next_event_counter(storage)?;
+
+    let mut totals = vec![];
+    for vault in Vault::load_all(storage)? {
+        let total_balances = TotalBalances::load(storage, &vault)?.unwrap_or_default();
+        totals.push(TokenTotalBalances {
+            token: vault.id(),
+            totals: total_balances.to_query_result().into(),
+        });
+    }
+
+    Ok(vec![
+        plaintext_log(LOG_EVENTS_VERSION, EVENTS_VERSION),
+        plaintext_log(LOG_EVENT_COUNTER, counter),
+        plaintext_log(LOG_TOTAL_BALANCES, to_json(&totals)?),
+    ])
+}
+
+fn to_json<T: Serialize>(value: &T) -> StdResult<String> {
+    String::from_utf8(to_vec(value)?)
+        .map_err(|err| StdError::generic_err(format!("invalid event: {}", err)))
+}
+
+#[derive(Serialize)]
+struct TokenTotalBalances {
+    token: HumanAddr,
+    totals: ResponseTotalBalances,
+}
+
+pub fn event_counter<S: ReadonlyStorage>(storage: &S) -> StdResult<u64> {
+    Ok(TypedStore::attach(storage)
+        .may_load(KEY_EVENT_COUNTER)?
+        .unwrap_or_default())
+}
+
+fn next_event_counter<S: Storage>(storage: &mut S) -> StdResult<u64> {
+    let counter = event_counter(storage)? + 1;
+    TypedStoreMut::attach(storage).store(KEY_EVENT_COUNTER, &counter)?;
+    Ok(counter)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::tests::storage_with_config;
+
+    #[test]
+    fn test_event_format() {
+        let event = Event::Deposit {
+            account: HumanAddr::from("alice"),
+            token: HumanAddr::from("lgnd"),
+            amount: Uint128(100),
+        };
+        let attribute = event.log().unwrap();
+
+        assert!(attribute.encrypted);
+        assert_eq!(attribute.key, LOG_EVENT);
+        assert_eq!(
+            attribute.value,
+            r#"{"type":"deposit","account":"alice","token":"lgnd","amount":"100"}"#
+        );
+    }
+
+    #[test]
+    fn test_transaction_logs() {
+        let mut storage = storage_with_config();
+
+        assert_eq!(event_counter(&storage).unwrap(), 0);
+        transaction_logs(&mut storage).unwrap();
+        let logs = transaction_logs(&mut storage).unwrap();
+        assert_eq!(event_counter(&storage).unwrap(), 2);
+
+        assert!(logs.iter().all(|l| !l.encrypted));
+        assert_eq!(logs[0].value, EVENTS_VERSION.to_string());
+        assert_eq!(logs[1].value, "2");
+        assert_eq!(
+            logs[2].value,
+            concat!(
+                r#"[{"token":"lgnd","totals":{"staked":"0","unbonding":"0"}},"#,
+                r#"{"token":"uscrt","totals":{"staked":"0","unbonding":"0"}}]"#
+            )
+        );
+    }
+}
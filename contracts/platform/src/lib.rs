@@ -1,5 +1,7 @@
 mod auto_claim;
 mod constants;
+pub mod events;
+pub mod migration;
 pub mod contract;
 pub mod msg;
 pub mod state;
@@ -8,6 +10,8 @@ mod unittests_utils;
 mod msgs;
 mod snip721;
 mod ethereum;
+mod loot_box;
+pub mod loot_table;
 
 #[cfg(target_arch = "wasm32")]
 mod wasm {
@@ -0,0 +1,338 @@
+use cosmwasm_std::{to_vec, Api, Binary, Env, HumanAddr, StdError, StdResult, Uint128};
+use secret_toolkit::utils::types::Contract;
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+
+use crate::state::Signers;
+
+/// Domain tag of the open loot box signatures. Bump the version whenever the payload changes,
+/// so that signatures issued for the old format can't be replayed against the new one.
+pub const OPEN_LOOT_BOX_DOMAIN: &str = "legendao_platform_open_loot_box_v1";
+
+/// The payload the loot box signer has to sign in order to authorize an `OpenLootBox`.
+///
+/// The contract builds it itself from the handle parameters and the execution context, so a
+/// signature is only valid for the exact box, outcome, user, nonce, chain and platform instance
+/// it was issued for. The signed bytes are the compact JSON serialization of this struct
+/// (fields in declaration order), hashed with SHA-256.
+#[derive(Serialize, Clone, Debug, PartialEq)]
+pub struct OpenLootBoxPayload {
+    pub domain: String,
+    pub chain_id: String,
+    pub contract: HumanAddr,
+    pub sender: HumanAddr,
+    pub loot_box_id: String,
+    pub open_lgnd_amount: Uint128,
+    pub open_nft_contract: Option<Contract>,
+    pub open_nft_uri: Option<String>,
+    pub nonce: u64,
+    pub ts_execute: u64,
+}
+
+impl OpenLootBoxPayload {
+    pub fn new(
+        env: &Env,
+        loot_box_id: String,
+        open_lgnd_amount: Uint128,
+        open_nft_contract: Option<Contract>,
+        open_nft_uri: Option<String>,
+        nonce: u64,
+        ts_execute: u64,
+    ) -> Self {
+        Self {
+            domain: OPEN_LOOT_BOX_DOMAIN.to_string(),
+            chain_id: env.block.chain_id.clone(),
+            contract: env.contract.address.clone(),
+            sender: env.message.sender.clone(),
+            loot_box_id,
+            open_lgnd_amount,
+            open_nft_contract,
+            open_nft_uri,
+            nonce,
+            ts_execute,
+        }
+    }
+
+    pub fn sign_bytes(&self) -> StdResult<Vec<u8>> {
+        to_vec(self)
+    }
+
+    pub fn hash(&self) -> StdResult<Vec<u8>> {
+        Ok(Sha256::digest(&self.sign_bytes()?).to_vec())
+    }
+
+    /// Verifies that the active signers that signed this payload meet the signer threshold
+    pub fn verify<A: Api>(
+        &self,
+        api: &A,
+        signatures: &[Binary],
+        signers: &Signers,
+        now: u64,
+    ) -> StdResult<()> {
+        // A malformed signature doesn't match any signer, rather than failing the whole check
+        self.verify_with(signatures, signers, now, |hash, signature, public_key| {
+            api.secp256k1_verify(hash, signature, public_key)
+                .unwrap_or(false)
+        })
+    }
+
+    fn verify_with<F: Fn(&[u8], &[u8], &[u8]) -> bool>(
+        &self,
+        signatures: &[Binary],
+        signers: &Signers,
+        now: u64,
+        verify_signature: F,
+    ) -> StdResult<()> {
+        let hash = self.hash()?;
+
+        // Each signer is counted once, no matter how many of the signatures are its own
+        let mut approvals = 0;
+        for signer in signers.active(now) {
+            for signature in signatures {
+                if verify_signature(&hash, signature.as_slice(), signer.public_key.as_slice()) {
+                    approvals += 1;
+                    break;
+                }
+            }
+        }
+
+        if approvals == 0 {
+            return Err(StdError::generic_err("Wrong Signature"));
+        }
+
+        if approvals < signers.threshold {
+            return Err(StdError::generic_err(format!(
+                "Not enough signatures: got {}, required {}",
+                approvals, signers.threshold
+            )));
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::Signer;
+    use cosmwasm_std::testing::mock_env;
+
+    const NOW: u64 = 1_571_797_419;
+
+    /// The mock api accepts any signature, so the tests "sign" a payload by appending the
+    /// signer's key to its hash. That's enough to check what the contract binds signatures to.
+    fn verify(
+        payload: &OpenLootBoxPayload,
+        signatures: &[Binary],
+        signers: &Signers,
+    ) -> StdResult<()> {
+        payload.verify_with(signatures, signers, NOW, |hash, signature, public_key| {
+            signature == [hash, public_key].concat().as_slice()
+        })
+    }
+
+    fn sign(payload: &OpenLootBoxPayload, key: &str) -> Binary {
+        Binary([payload.hash().unwrap().as_slice(), key.as_bytes()].concat())
+    }
+
+    fn signer(key: &str) -> Signer {
+        Signer::new(Binary::from(key.as_bytes()))
+    }
+
+    fn signers(keys: &[&str], threshold: u32) -> Signers {
+        Signers {
+            signers: keys.iter().map(|key| signer(key)).collect(),
+            threshold,
+        }
+    }
+
+    fn payload(env: &Env) -> OpenLootBoxPayload {
+        OpenLootBoxPayload::new(
+            env,
+            "7".to_string(),
+            Uint128(1000),
+            Some(Contract {
+                address: HumanAddr::from("collection"),
+                hash: "collection_hash".to_string(),
+            }),
+            Some("https://legendao.io/7".to_string()),
+            3,
+            NOW,
+        )
+    }
+
+    fn assert_rejected(result: StdResult<()>, expected: &str) {
+        match result {
+            Err(StdError::GenericErr { msg, .. }) => assert_eq!(msg, expected),
+            other => panic!("expected a signature error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_sign_bytes_are_canonical() {
+        let env = mock_env("alice", &[]);
+        let bytes = payload(&env).sign_bytes().unwrap();
+
+        assert_eq!(
+            String::from_utf8(bytes).unwrap(),
+            format!(
+                "{{\"domain\":\"{}\",\"chain_id\":\"{}\",\"contract\":\"{}\",\"sender\":\"alice\",\
+                \"loot_box_id\":\"7\",\"open_lgnd_amount\":\"1000\",\
+                \"open_nft_contract\":{{\"address\":\"collection\",\"hash\":\"collection_hash\"}},\
+                \"open_nft_uri\":\"https://legendao.io/7\",\"nonce\":3,\"ts_execute\":1571797419}}",
+                OPEN_LOOT_BOX_DOMAIN, env.block.chain_id, env.contract.address
+            )
+        );
+    }
+
+    #[test]
+    fn test_valid_signature() {
+        let payload = payload(&mock_env("alice", &[]));
+        let signers = signers(&["backend", "backup"], 1);
+
+        assert!(verify(&payload, &[sign(&payload, "backend")], &signers).is_ok());
+        assert!(verify(&payload, &[sign(&payload, "backup")], &signers).is_ok());
+    }
+
+    #[test]
+    fn test_unknown_signer() {
+        let payload = payload(&mock_env("alice", &[]));
+
+        assert_rejected(
+            verify(
+                &payload,
+                &[sign(&payload, "attacker")],
+                &signers(&["backend"], 1),
+            ),
+            "Wrong Signature",
+        );
+        assert_rejected(
+            verify(&payload, &[], &signers(&["backend"], 1)),
+            "Wrong Signature",
+        );
+    }
+
+    #[test]
+    fn test_tampered_fields_are_rejected() {
+        let env = mock_env("alice", &[]);
+        let signed = payload(&env);
+        let signatures = [sign(&signed, "backend")];
+        let signers = signers(&["backend"], 1);
+
+        let tampered: Vec<fn(&mut OpenLootBoxPayload)> = vec![
+            |p| p.domain = "some_other_contract_v1".to_string(),
+            |p| p.chain_id = "secret-4".to_string(),
+            |p| p.contract = HumanAddr::from("other_platform"),
+            |p| p.sender = HumanAddr::from("bob"),
+            |p| p.loot_box_id = "8".to_string(),
+            |p| p.open_lgnd_amount = Uint128(1_000_000),
+            |p| p.open_nft_contract = None,
+            |p| {
+                if let Some(contract) = p.open_nft_contract.as_mut() {
+                    contract.address = HumanAddr::from("other_collection")
+                }
+            },
+            |p| p.open_nft_uri = Some("https://legendao.io/legendary".to_string()),
+            |p| p.open_nft_uri = None,
+            |p| p.nonce = 4,
+            |p| p.ts_execute += 1,
+        ];
+
+        for tamper in tampered {
+            let mut payload = signed.clone();
+            tamper(&mut payload);
+            assert_ne!(payload, signed);
+            assert_rejected(verify(&payload, &signatures, &signers), "Wrong Signature");
+        }
+    }
+
+    #[test]
+    fn test_signature_bound_to_sender() {
+        let signed = payload(&mock_env("alice", &[]));
+        let replayed = payload(&mock_env("bob", &[]));
+
+        assert_rejected(
+            verify(
+                &replayed,
+                &[sign(&signed, "backend")],
+                &signers(&["backend"], 1),
+            ),
+            "Wrong Signature",
+        );
+    }
+
+    #[test]
+    fn test_signer_validity_window() {
+        let payload = payload(&mock_env("alice", &[]));
+        let signatures = [sign(&payload, "backend")];
+
+        let mut signers = signers(&["backend"], 1);
+        signers.signers[0].valid_from = Some(NOW + 1);
+        assert_rejected(verify(&payload, &signatures, &signers), "Wrong Signature");
+
+        signers.signers[0].valid_from = Some(NOW);
+        signers.signers[0].valid_until = Some(NOW + 1);
+        assert!(verify(&payload, &signatures, &signers).is_ok());
+
+        signers.signers[0].valid_until = Some(NOW);
+        assert_rejected(verify(&payload, &signatures, &signers), "Wrong Signature");
+    }
+
+    #[test]
+    fn test_removed_signer() {
+        let payload = payload(&mock_env("alice", &[]));
+        let signatures = [sign(&payload, "compromised")];
+
+        let mut signers = signers(&["compromised", "rotated"], 1);
+        assert!(verify(&payload, &signatures, &signers).is_ok());
+
+        signers.remove_multiple(&[Binary::from(b"compromised".as_ref())]);
+        assert_rejected(verify(&payload, &signatures, &signers), "Wrong Signature");
+    }
+
+    #[test]
+    fn test_signer_threshold() {
+        let payload = payload(&mock_env("alice", &[]));
+        let signers = signers(&["a", "b", "c"], 2);
+
+        assert_rejected(
+            verify(&payload, &[sign(&payload, "a")], &signers),
+            "Not enough signatures: got 1, required 2",
+        );
+        // The same signer signing twice doesn't count twice
+        assert_rejected(
+            verify(
+                &payload,
+                &[sign(&payload, "a"), sign(&payload, "a")],
+                &signers,
+            ),
+            "Not enough signatures: got 1, required 2",
+        );
+        assert!(verify(
+            &payload,
+            &[sign(&payload, "c"), sign(&payload, "a")],
+            &signers
+        )
+        .is_ok());
+    }
+
+    #[test]
+    fn test_set_threshold() {
+        let mut signers = signers(&["a", "b"], 1);
+
+        assert!(signers.set_threshold(0).is_err());
+        assert!(signers.set_threshold(3).is_err());
+        assert!(signers.set_threshold(2).is_ok());
+        assert_eq!(signers.threshold, 2);
+
+        let key = |byte: u8, len: usize| Binary(vec![byte; len]);
+        let result = signers.set_multiple(vec![Signer::new(key(2, 33)), Signer::new(key(3, 32))]);
+        assert!(result.is_err());
+        assert_eq!(signers.signers.len(), 2);
+
+        signers
+            .set_multiple(vec![Signer::new(key(2, 33)), Signer::new(key(4, 65))])
+            .unwrap();
+        assert_eq!(signers.signers.len(), 4);
+    }
+}
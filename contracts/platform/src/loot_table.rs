@@ -0,0 +1,340 @@
+use cosmwasm_std::{Env, HumanAddr, ReadonlyStorage, StdError, StdResult, Storage, Uint128};
+use cosmwasm_storage::{PrefixedStorage, ReadonlyPrefixedStorage};
+use schemars::JsonSchema;
+use secret_toolkit::crypto::{sha_256, Prng};
+use secret_toolkit::storage::{TypedStore, TypedStoreMut};
+use secret_toolkit::utils::types::Contract;
+use serde::{Deserialize, Serialize};
+
+use crate::state::LootBoxRecord;
+
+/// Loot table used by boxes that weren't assigned a box type
+pub const DEFAULT_LOOT_BOX_TYPE: &str = "default";
+
+const PREFIX_LOOT_TABLES: &[u8] = b"loot_tables";
+const PREFIX_LOOT_BOX_TYPES: &[u8] = b"loot_box_types";
+const PREFIX_LOOT_RNG: &[u8] = b"loot_rng";
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct LgndRange {
+    pub min: Uint128,
+    pub max: Uint128,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct LootOutcome {
+    /// Relative weight of the outcome, the odds are `weight / total_weight` of the table
+    pub weight: u32,
+    /// LGND paid from the distribute address, drawn uniformly from the (inclusive) range
+    pub lgnd: Option<LgndRange>,
+    /// Collection of the item. The LegenDAO collection turns the box itself into the item,
+    /// any other collection mints a new token and burns the box
+    pub nft_contract: Option<Contract>,
+    /// Uris of the item, one of them is drawn uniformly
+    pub nft_uris: Vec<String>,
+}
+
+/// What a single box produced
+#[derive(Clone, Debug, PartialEq)]
+pub struct LootDraw {
+    pub lgnd_amount: Uint128,
+    pub nft_contract: Option<Contract>,
+    pub nft_uri: Option<String>,
+}
+
+impl LootDraw {
+    pub fn to_record(&self, env: &Env, loot_box_id: String, nonce: Option<u64>) -> LootBoxRecord {
+        LootBoxRecord {
+            loot_box_id,
+            lgnd_amount: self.lgnd_amount,
+            nft_contract: self.nft_contract.as_ref().map(|c| c.address.clone()),
+            nft_uri: self.nft_uri.clone(),
+            timestamp: env.block.time,
+            nonce,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct LootTable {
+    pub outcomes: Vec<LootOutcome>,
+}
+
+impl LootTable {
+    pub fn load<S: ReadonlyStorage>(storage: &S, box_type: &str) -> StdResult<Option<Self>> {
+        let store = ReadonlyPrefixedStorage::new(PREFIX_LOOT_TABLES, storage);
+        TypedStore::attach(&store).may_load(box_type.as_bytes())
+    }
+
+    pub fn save<S: Storage>(&self, storage: &mut S, box_type: &str) -> StdResult<()> {
+        let mut store = PrefixedStorage::new(PREFIX_LOOT_TABLES, storage);
+        TypedStoreMut::attach(&mut store).store(box_type.as_bytes(), self)
+    }
+
+    pub fn remove<S: Storage>(storage: &mut S, box_type: &str) {
+        let mut store = PrefixedStorage::new(PREFIX_LOOT_TABLES, storage);
+        TypedStoreMut::<Self, _>::attach(&mut store).remove(box_type.as_bytes())
+    }
+
+    /// Loads the loot table of a box, according to the box type it was assigned
+    pub fn for_loot_box<S: ReadonlyStorage>(storage: &S, loot_box_id: &str) -> StdResult<Self> {
+        let box_type = loot_box_type(storage, loot_box_id)?;
+        Self::load(storage, &box_type)?.ok_or_else(|| {
+            StdError::generic_err(format!("no loot table for box type {}", box_type))
+        })
+    }
+}
+
+impl LootTable {
+    pub fn total_weight(&self) -> u64 {
+        self.outcomes.iter().map(|o| o.weight as u64).sum()
+    }
+
+    pub fn validate(&self) -> StdResult<()> {
+        if self.total_weight() == 0 {
+            return Err(StdError::generic_err(
+                "loot table must have at least one outcome with a positive weight",
+            ));
+        }
+
+        for outcome in &self.outcomes {
+            if let Some(range) = &outcome.lgnd {
+                if range.min > range.max {
+                    return Err(StdError::generic_err(format!(
+                        "invalid LGND range: min {} is larger than max {}",
+                        range.min, range.max
+                    )));
+                }
+            }
+
+            if outcome.nft_contract.is_some() == outcome.nft_uris.is_empty() {
+                return Err(StdError::generic_err(
+                    "an outcome must have both an NFT contract and item uris, or neither",
+                ));
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn draw(&self, rng: &mut Prng) -> LootDraw {
+        let bytes = rng.rand_bytes();
+        let mut roll = u64_from(&bytes[0..8]) % self.total_weight();
+
+        let outcome = self
+            .outcomes
+            .iter()
+            .find(|o| {
+                if roll < o.weight as u64 {
+                    return true;
+                }
+                roll -= o.weight as u64;
+                false
+            })
+            .expect("roll is always lower than the total weight");
+
+        let lgnd_amount = match &outcome.lgnd {
+            Some(LgndRange { min, max }) => {
+                let span = max.u128() - min.u128();
+                let offset = match span.checked_add(1) {
+                    Some(modulo) => u128_from(&bytes[8..24]) % modulo,
+                    None => u128_from(&bytes[8..24]),
+                };
+                Uint128(min.u128() + offset)
+            }
+            None => Uint128::zero(),
+        };
+
+        let nft_uri = match outcome.nft_uris.len() {
+            0 => None,
+            len => Some(outcome.nft_uris[(u64_from(&bytes[24..32]) % len as u64) as usize].clone()),
+        };
+
+        LootDraw {
+            lgnd_amount,
+            nft_contract: outcome.nft_contract.clone(),
+            nft_uri,
+        }
+    }
+}
+
+pub fn loot_box_type<S: ReadonlyStorage>(storage: &S, loot_box_id: &str) -> StdResult<String> {
+    let store = ReadonlyPrefixedStorage::new(PREFIX_LOOT_BOX_TYPES, storage);
+    Ok(TypedStore::attach(&store)
+        .may_load(loot_box_id.as_bytes())?
+        .unwrap_or_else(|| DEFAULT_LOOT_BOX_TYPE.to_string()))
+}
+
+pub fn set_loot_box_types<S: Storage>(
+    storage: &mut S,
+    box_type: &str,
+    loot_box_ids: Vec<String>,
+) -> StdResult<()> {
+    let mut store = PrefixedStorage::new(PREFIX_LOOT_BOX_TYPES, storage);
+    let mut typed_store = TypedStoreMut::attach(&mut store);
+
+    for loot_box_id in loot_box_ids {
+        typed_store.store(loot_box_id.as_bytes(), &box_type.to_string())?
+    }
+
+    Ok(())
+}
+
+/// Secret seed of the loot box draws. Every draw ratchets it forward, so knowing the outcome of
+/// one box says nothing about the next one.
+#[derive(Serialize, Deserialize)]
+pub struct LootRng {
+    seed: Vec<u8>,
+}
+
+impl LootRng {
+    pub fn new(seed: &[u8]) -> StdResult<Self> {
+        if seed.is_empty() {
+            return Err(StdError::generic_err("the loot box seed can't be empty"));
+        }
+
+        Ok(Self {
+            seed: seed.to_vec(),
+        })
+    }
+
+    /// Fails until a seed is set, drawing from a public seed would make the boxes predictable
+    pub fn load<S: ReadonlyStorage>(storage: &S) -> StdResult<Self> {
+        TypedStore::attach(storage)
+            .may_load(PREFIX_LOOT_RNG)?
+            .ok_or_else(|| {
+                StdError::generic_err("loot boxes can't be opened before they're seeded")
+            })
+    }
+
+    pub fn save<S: Storage>(&self, storage: &mut S) -> StdResult<()> {
+        TypedStoreMut::attach(storage).store(PREFIX_LOOT_RNG, self)
+    }
+
+    /// Rng for a single box, seeded like `NftRng::random_number`: the secret seed, plus the
+    /// user's entropy mixed with the execution context
+    pub fn prng(
+        &mut self,
+        env: &Env,
+        owner: &HumanAddr,
+        loot_box_id: &str,
+        entropy: &[u8],
+    ) -> Prng {
+        let mut rng_entropy = entropy.to_vec();
+        rng_entropy.extend_from_slice(owner.0.as_bytes());
+        rng_entropy.extend_from_slice(loot_box_id.as_bytes());
+        rng_entropy.extend_from_slice(&env.block.height.to_be_bytes());
+        rng_entropy.extend_from_slice(&env.block.time.to_be_bytes());
+
+        let mut rng = Prng::new(&self.seed, &sha_256(&rng_entropy));
+        self.seed = rng.rand_bytes().to_vec();
+
+        rng
+    }
+}
+
+fn u64_from(bytes: &[u8]) -> u64 {
+    let mut be_bytes = [0u8; 8];
+    be_bytes.copy_from_slice(bytes);
+    u64::from_be_bytes(be_bytes)
+}
+
+fn u128_from(bytes: &[u8]) -> u128 {
+    let mut be_bytes = [0u8; 16];
+    be_bytes.copy_from_slice(bytes);
+    u128::from_be_bytes(be_bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn outcome(weight: u32, lgnd: Option<(u128, u128)>, uris: &[&str]) -> LootOutcome {
+        LootOutcome {
+            weight,
+            lgnd: lgnd.map(|(min, max)| LgndRange {
+                min: Uint128(min),
+                max: Uint128(max),
+            }),
+            nft_contract: match uris.is_empty() {
+                true => None,
+                false => Some(Contract {
+                    address: "items".into(),
+                    hash: "items_hash".to_string(),
+                }),
+            },
+            nft_uris: uris.iter().map(|uri| uri.to_string()).collect(),
+        }
+    }
+
+    #[test]
+    fn test_validate() {
+        let valid = LootTable {
+            outcomes: vec![outcome(1, Some((1, 10)), &[]), outcome(0, None, &["a"])],
+        };
+        assert!(valid.validate().is_ok());
+
+        let no_weight = LootTable {
+            outcomes: vec![outcome(0, Some((1, 10)), &[])],
+        };
+        assert!(no_weight.validate().is_err());
+        assert!(LootTable { outcomes: vec![] }.validate().is_err());
+
+        let bad_range = LootTable {
+            outcomes: vec![outcome(1, Some((10, 1)), &[])],
+        };
+        assert!(bad_range.validate().is_err());
+
+        let mut missing_uris = outcome(1, None, &["a"]);
+        missing_uris.nft_uris.clear();
+        assert!(LootTable {
+            outcomes: vec![missing_uris]
+        }
+        .validate()
+        .is_err());
+    }
+
+    #[test]
+    fn test_draw_respects_table() {
+        let table = LootTable {
+            outcomes: vec![
+                outcome(3, Some((100, 200)), &[]),
+                outcome(0, Some((1_000_000, 1_000_000)), &[]),
+                outcome(1, None, &["common", "rare"]),
+            ],
+        };
+        let mut rng = Prng::new(b"seed", b"entropy");
+
+        let mut lgnd_draws = 0;
+        let mut uris = vec![];
+        for _ in 0..200 {
+            let draw = table.draw(&mut rng);
+            match draw.nft_uri {
+                Some(uri) => {
+                    assert_eq!(draw.lgnd_amount, Uint128::zero());
+                    assert!(draw.nft_contract.is_some());
+                    uris.push(uri);
+                }
+                None => {
+                    assert!((100..=200).contains(&draw.lgnd_amount.u128()));
+                    lgnd_draws += 1;
+                }
+            }
+        }
+
+        assert!(lgnd_draws > uris.len());
+        assert!(uris.iter().any(|uri| uri == "common"));
+        assert!(uris.iter().any(|uri| uri == "rare"));
+    }
+
+    #[test]
+    fn test_rng_ratchets() {
+        let env = cosmwasm_std::testing::mock_env("alice", &[]);
+        let mut loot_rng = LootRng::new(b"init").unwrap();
+
+        let owner = HumanAddr::from("alice");
+        let first = loot_rng.prng(&env, &owner, "1", b"entropy").rand_bytes();
+        let second = loot_rng.prng(&env, &owner, "1", b"entropy").rand_bytes();
+        assert_ne!(first, second);
+    }
+}
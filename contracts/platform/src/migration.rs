@@ -0,0 +1,203 @@
+use cosmwasm_std::{HumanAddr, ReadonlyStorage, StdError, StdResult, Storage};
+use secret_toolkit::storage::{TypedStore, TypedStoreMut};
+
+use crate::loot_table::LootRng;
+use crate::state::{Config, ReceivingContracts, TotalBalances, Vault};
+
+const KEY_STORAGE_VERSION: &[u8] = b"storage_version";
+
+/// Version of the storage layout this code works with. Contracts instantiated before the storage
+/// was versioned have no version stored, which is version 0.
+///
+/// Changing a stored struct requires bumping this version, keeping the old struct in this module
+/// and adding a step from the previous version to `migrate`
+pub const STORAGE_VERSION: u32 = 1;
+
+pub fn load_version<S: ReadonlyStorage>(storage: &S) -> StdResult<u32> {
+    Ok(TypedStore::attach(storage)
+        .may_load(KEY_STORAGE_VERSION)?
+        .unwrap_or_default())
+}
+
+pub fn save_version<S: Storage>(storage: &mut S, version: u32) -> StdResult<()> {
+    TypedStoreMut::attach(storage).store(KEY_STORAGE_VERSION, &version)
+}
+
+/// Handles (but `Migrate`) and queries require the storage to be up to date, as reading an older
+/// layout fails, or `Config::get_unchecked` panics
+pub fn require_current_version<S: ReadonlyStorage>(storage: &S) -> StdResult<()> {
+    let version = load_version(storage)?;
+    if version != STORAGE_VERSION {
+        return Err(StdError::generic_err(format!(
+            "storage is at version {}, expected {}. The admin has to migrate it first",
+            version, STORAGE_VERSION
+        )));
+    }
+
+    Ok(())
+}
+
+/// The admin, read with the layout of the stored version
+pub fn load_admin<S: ReadonlyStorage>(storage: &S) -> StdResult<HumanAddr> {
+    match load_version(storage)? {
+        // Version 1 kept the config of version 0
+        0 | 1 => Ok(load_config(storage)?.admin),
+        version => Err(unknown_version(version)),
+    }
+}
+
+/// Upgrades the storage one version at a time up to `STORAGE_VERSION`. Returns the version it
+/// started from
+pub fn migrate<S: Storage>(
+    storage: &mut S,
+    random_seed: &[u8],
+    legacy_receiving_contracts: &[HumanAddr],
+) -> StdResult<u32> {
+    let from = load_version(storage)?;
+    if from > STORAGE_VERSION {
+        return Err(unknown_version(from));
+    }
+
+    for version in from..STORAGE_VERSION {
+        match version {
+            0 => migrate_v0_to_v1(storage, random_seed, legacy_receiving_contracts)?,
+            _ => return Err(unknown_version(version)),
+        }
+        save_version(storage, version + 1)?;
+    }
+
+    Ok(from)
+}
+
+fn unknown_version(version: u32) -> StdError {
+    StdError::generic_err(format!("can't migrate from storage version {}", version))
+}
+
+fn load_config<S: ReadonlyStorage>(storage: &S) -> StdResult<Config> {
+    Config::load(storage)?.ok_or_else(|| StdError::generic_err("config not found"))
+}
+
+/// The loot boxes came with version 1, their draws need a seed. Receiving contracts registered
+/// before they had metadata are moved into the list, which can't be done without their addresses
+fn migrate_v0_to_v1<S: Storage>(
+    storage: &mut S,
+    random_seed: &[u8],
+    legacy_receiving_contracts: &[HumanAddr],
+) -> StdResult<()> {
+    let loot_rng = LootRng::new(random_seed)?;
+
+    let config = load_config(storage)?;
+    config.save(storage)?;
+
+    let vault = Vault::primary(&config);
+    if let Some(total_balances) = TotalBalances::load(storage, &vault)? {
+        total_balances.save(storage, &vault)?;
+    }
+
+    for address in legacy_receiving_contracts {
+        if !ReceivingContracts::move_legacy(storage, address)? {
+            return Err(StdError::generic_err(format!(
+                "{} is not a legacy receiving contract",
+                address
+            )));
+        }
+    }
+
+    loot_rng.save(storage)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::Balances;
+    use cosmwasm_std::testing::MockStorage;
+    use cosmwasm_std::Binary;
+    use cosmwasm_storage::PrefixedStorage;
+
+    // Storage of a platform instantiated before the storage was versioned, as `TypedStore` wrote it
+    const LEGACY_CONFIG: &[u8] = b"\x05\0\0\0\0\0\0\0admin\
+        \x0a\0\0\0\0\0\0\0distribute\
+        \x04\0\0\0\0\0\0\0lgnd\x04\0\0\0\0\0\0\0hash\
+        \x03\0\0\0\0\0\0\0nft\x04\0\0\0\0\0\0\0hash\
+        \x05\0\0\0\0\0\0\0uscrt\
+        \x80\xaf\x1b\0\0\0\0\0\
+        \x08\0\0\0\0\0\0\0platform\
+        \x04\0\0\0\0\0\0\0AQID\
+        \0\0\0\0\0\0\0\0";
+    const LEGACY_BALANCES: &[u8] = b"\xe8\x03\0\0\0\0\0\0\0\0\0\0\0\0\0\0\
+        \x01\0\0\0\0\0\0\0\
+        \x64\0\0\0\0\0\0\0\x32\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\
+        \x19\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0";
+    const LEGACY_TOTAL_BALANCE: &[u8] = b"\xe8\x03\0\0\0\0\0\0\0\0\0\0\0\0\0\0\
+        \x32\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0";
+
+    fn legacy_storage() -> MockStorage {
+        let mut storage = MockStorage::new();
+        storage.set(b"config", LEGACY_CONFIG);
+        storage.set(b"total_balance", LEGACY_TOTAL_BALANCE);
+        PrefixedStorage::new(b"balances", &mut storage).set(b"alice", LEGACY_BALANCES);
+        PrefixedStorage::new(b"receiving_contracts", &mut storage).set(b"market", b"\x01");
+
+        storage
+    }
+
+    #[test]
+    fn test_migrate_legacy_storage() {
+        let mut storage = legacy_storage();
+        assert_eq!(load_version(&storage).unwrap(), 0);
+        assert!(require_current_version(&storage).is_err());
+        assert_eq!(load_admin(&storage).unwrap(), HumanAddr::from("admin"));
+
+        let market = HumanAddr::from("market");
+        assert!(LootRng::load(&storage).is_err());
+        assert!(migrate(&mut storage, b"", std::slice::from_ref(&market)).is_err());
+        assert!(migrate(&mut storage, b"seed", &["minting".into()]).is_err());
+        assert_eq!(
+            migrate(&mut storage, b"seed", std::slice::from_ref(&market)).unwrap(),
+            0
+        );
+        assert_eq!(load_version(&storage).unwrap(), STORAGE_VERSION);
+        assert!(require_current_version(&storage).is_ok());
+        assert_eq!(
+            migrate(&mut storage, b"seed", &[]).unwrap(),
+            STORAGE_VERSION
+        );
+        assert!(LootRng::load(&storage).is_ok());
+
+        let (contracts, total) = ReceivingContracts::page(&storage, 0, 10).unwrap();
+        assert_eq!(total, 1);
+        assert_eq!(contracts[0].address, market);
+
+        let config = Config::get_unchecked(&storage).unwrap();
+        assert_eq!(config.distribute_address, HumanAddr::from("distribute"));
+        assert_eq!(config.token.address, HumanAddr::from("lgnd"));
+        assert_eq!(config.legen_dao_nft.address, HumanAddr::from("nft"));
+        assert_eq!(config.native_token_denom, "uscrt");
+        assert_eq!(config.unbonding_period, 21 * 24 * 60 * 60);
+        assert_eq!(config.signer_address, Binary::from(vec![1, 2, 3]));
+
+        let vault = Vault::primary(&config);
+        let balances = Balances::load(&storage, &vault, &HumanAddr::from("alice"))
+            .unwrap()
+            .unwrap();
+        assert_eq!(balances.staked, 1000);
+        assert_eq!(balances.pending_redeem.unbondings.len(), 1);
+        assert_eq!(balances.pending_redeem.unbondings[0].end_ts, 100);
+        assert_eq!(balances.pending_redeem.unbondings[0].amount, 50);
+        assert_eq!(balances.pending_redeem.claimable, 25);
+
+        let total_balances = TotalBalances::load(&storage, &vault).unwrap().unwrap();
+        assert_eq!(total_balances.staked, 1000);
+        assert_eq!(total_balances.unbonding, 50);
+    }
+
+    #[test]
+    fn test_migrate_newer_storage() {
+        let mut storage = legacy_storage();
+        save_version(&mut storage, STORAGE_VERSION + 1).unwrap();
+
+        assert!(migrate(&mut storage, b"seed", &[]).is_err());
+        assert!(load_admin(&storage).is_err());
+        assert!(require_current_version(&storage).is_err());
+    }
+}
@@ -1,4 +1,8 @@
-use crate::state::{Balances, Config, Features, RedeemInfo, TotalBalances, UnbondingRecord};
+use crate::loot_table::LootOutcome;
+use crate::state::{
+    Balances, Config, Features, InstantRedeem, LootBoxRecord, NativeToken, PlatformAllowance,
+    ReceivingContract, RedeemInfo, Signer, Signers, SupportedToken, TotalBalances, UnbondingRecord,
+};
 use cosmwasm_std::{Binary, HumanAddr, Uint128};
 use schemars::JsonSchema;
 use secret_toolkit::permit::Permit;
@@ -19,24 +23,58 @@ pub struct InitMsg {
     pub receiving_contracts: Option<Vec<HumanAddr>>,
     pub viewing_key: String,
     pub distribute_address: HumanAddr,
-    pub signer_address: Binary
+    pub signer_address: Binary,
+    /// Secret seed of the loot box draws. Anyone who knows it can predict them
+    pub random_seed: Binary,
 }
 
 #[derive(Deserialize, JsonSchema)]
 #[serde(rename_all = "snake_case")]
 pub enum HandleMsg {
+    /// Deposit native coins (sent along with the message) to the sender's account. They are
+    /// wrapped into `NativeToken::wrapper`, and paid back as native coins when redeemed
+    DepositNative {},
     /// Withdraw funds from the platform, which will initiate an unbonding period
     Redeem {
+        /// Token to redeem (or the native denom, for native deposits), `Config::token` if not
+        /// specified
+        token: Option<HumanAddr>,
         /// If not specified, use all funds
         amount: Option<Uint128>,
     },
-    /// Manually claim funds that finished the unbonding time
+    /// Withdraw funds from the platform right away, for a penalty (see `InstantRedeem`)
+    RedeemInstant {
+        /// If not specified, use all funds
+        amount: Option<Uint128>,
+    },
+    /// Move funds that are still unbonding back to the staked balance
+    CancelUnbonding {
+        /// Token of the record (or the native denom, for native deposits), `Config::token` if
+        /// not specified
+        token: Option<HumanAddr>,
+        /// End of the unbonding period of the record, as returned by the `balance` query
+        end_ts: u64,
+        /// If not specified, cancel the whole record
+        amount: Option<Uint128>,
+    },
+    /// Manually claim funds that finished the unbonding time, of all tokens
     /// (i.e. don't wait for it to be claimed automatically)
     ClaimRedeemed {},
+    /// Pay matured claims of any account. Can be called by anyone
+    ProcessClaims {
+        /// Max number of claims to process, defaults to 10 (capped at 50)
+        limit: Option<u32>,
+    },
     /// Send tokens from platform to other contract in the LegenDAO ecosystem (e.g. NFT mint)
     SendFromPlatform {
         /// Destination contract
         contract_addr: HumanAddr,
+        /// Account to send from, using the allowance it granted to the sender.
+        /// The sender's own account if not specified
+        owner: Option<HumanAddr>,
+        /// Token to send (or the native denom, for native deposits), `Config::token` if not
+        /// specified
+        token: Option<HumanAddr>,
         /// If not specified, use all funds
         amount: Option<Uint128>,
         /// Probably not necessary
@@ -44,6 +82,23 @@ pub enum HandleMsg {
         /// Wanted message to initiate at the destination contract (defined in the destination contract)
         msg: Binary,
     },
+    /// Allow `spender` (e.g. a receiving contract or a session key) to send up to `amount` of
+    /// the sender's funds from the platform. The allowance still only reaches receiving contracts
+    IncreasePlatformAllowance {
+        spender: HumanAddr,
+        /// Token of the allowance, `Config::token` if not specified
+        token: Option<HumanAddr>,
+        amount: Uint128,
+        /// Time (in seconds) the allowance expires at, replaces the current expiration
+        expiration: Option<u64>,
+        padding: Option<String>,
+    },
+    RevokePlatformAllowance {
+        spender: HumanAddr,
+        /// Token of the allowance, `Config::token` if not specified
+        token: Option<HumanAddr>,
+        padding: Option<String>,
+    },
     OpenLootBox {
         loot_box_id: String,
         open_lgnd_amount: Uint128,
@@ -51,22 +106,74 @@ pub enum HandleMsg {
         open_nft_uri: Option<String>,
         nonce: u64,
         ts_execute: u64,
-        message: Binary,
-        signature: Binary,
+        /// Signatures of the loot box signers over the canonical `OpenLootBoxPayload`
+        /// built from the fields above, the chain id, the platform address and the sender.
+        /// At least `Signers::threshold` distinct active signers have to sign
+        signatures: Vec<Binary>,
         memo: Option<String>,
     },
 
 
     // Admin
+    /// Register receiving contracts without metadata, see `SetReceivingContracts`
     AddReceivingContracts {
         addresses: Vec<HumanAddr>,
     },
+    /// Register receiving contracts along with their metadata and caps, or update them
+    SetReceivingContracts {
+        contracts: Vec<ReceivingContract>,
+    },
     RemoveReceivingContracts {
         addresses: Vec<HumanAddr>,
     },
     ChangeConfig {
         admin: Option<HumanAddr>,
         unbonding_period: Option<u64>,
+        /// Penalty and penalty sink of `RedeemInstant`, which is disabled until set. A penalty of
+        /// 0 disables it again
+        instant_redeem: Option<InstantRedeem>,
+        /// Coins accepted by `DepositNative` and the SNIP-20 wrapping them. The denom and wrapper
+        /// can't change while native deposits are held
+        native_token: Option<NativeToken>,
+    },
+    /// Accept deposits of more SNIP-20s, or update the unbonding period of supported ones.
+    /// Supported tokens can't be removed, since accounts may still hold them
+    AddSupportedTokens {
+        tokens: Vec<SupportedToken>,
+    },
+    /// Add loot box signers, or update the validity window of existing ones
+    AddSigners {
+        signers: Vec<Signer>,
+    },
+    /// Revoke loot box signers. Takes effect even if fewer signers than the threshold remain
+    RemoveSigners {
+        public_keys: Vec<Binary>,
+    },
+    /// Number of distinct active signers required to open a loot box
+    SetSignerThreshold {
+        threshold: u32,
+    },
+    /// Set the loot table boxes of `box_type` are drawn from when sent to the platform
+    SetLootTable {
+        box_type: String,
+        outcomes: Vec<LootOutcome>,
+    },
+    RemoveLootTable {
+        box_type: String,
+    },
+    /// Assign loot boxes to a box type. Boxes without one use the `default` loot table
+    SetLootBoxTypes {
+        box_type: String,
+        loot_box_ids: Vec<String>,
+    },
+    /// Upgrade the storage to the version of the contract's code. Every other handle and query
+    /// fails until the storage is up to date
+    Migrate {
+        /// Secret seed of the loot box draws, for storage from before the loot boxes
+        random_seed: Binary,
+        /// Receiving contracts registered before they had metadata, which only the migration from
+        /// version 0 moves into the list
+        legacy_receiving_contracts: Option<Vec<HumanAddr>>,
     },
 
     // Viewing keys
@@ -85,6 +192,16 @@ pub enum HandleMsg {
         padding: Option<String>,
     },
 
+    // Snip721 commands
+    /// Loot boxes sent to the platform with `LootBoxReceiveMsg::OpenLootBox` are opened
+    /// with an outcome drawn on-chain from their loot table
+    BatchReceiveNft {
+        sender: HumanAddr,
+        from: HumanAddr,
+        token_ids: Vec<String>,
+        msg: Option<Binary>,
+    },
+
     // Snip20 commands
     Receive {
         sender: HumanAddr,
@@ -108,6 +225,16 @@ pub enum ReceiveMsg {
     BatchDeposit(Vec<Deposit>),
 }
 
+#[derive(Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum LootBoxReceiveMsg {
+    OpenLootBox {
+        /// Mixed into the seed of the draw
+        entropy: String,
+        memo: Option<String>,
+    },
+}
+
 #[derive(Serialize, Deserialize, JsonSchema)]
 #[serde(rename_all = "snake_case")]
 pub struct Deposit {
@@ -126,11 +253,30 @@ impl Deposit {
 #[cfg_attr(test, derive(Deserialize))]
 pub enum HandleAnswer {
     Deposit { status: ResponseStatus },
+    DepositNative { status: ResponseStatus },
     Redeem { status: ResponseStatus },
+    RedeemInstant {
+        status: ResponseStatus,
+        /// Amount sent to the user, after the penalty
+        received: Uint128,
+        penalty: Uint128,
+    },
+    CancelUnbonding {
+        status: ResponseStatus,
+        /// Amount moved back to the staked balance
+        amount: Uint128,
+    },
     OpenLootBox { status: ResponseStatus },
     ClaimRedeemed { status: ResponseStatus },
+    ProcessClaims { status: ResponseStatus, processed: u32 },
     SendFromPlatform { status: ResponseStatus },
+    IncreasePlatformAllowance {
+        status: ResponseStatus,
+        allowance: PlatformAllowance,
+    },
+    RevokePlatformAllowance { status: ResponseStatus },
     AddReceivingContracts { status: ResponseStatus },
+    SetReceivingContracts { status: ResponseStatus },
     RemoveReceivingContracts { status: ResponseStatus },
     CreateViewingKey { key: String },
     SetViewingKey { status: ResponseStatus },
@@ -138,6 +284,20 @@ pub enum HandleAnswer {
     SetPauser { status: ResponseStatus },
     RemovePauser { status: ResponseStatus },
     ChangeConfig { status: ResponseStatus },
+    AddSupportedTokens { status: ResponseStatus },
+    AddSigners { status: ResponseStatus },
+    RemoveSigners { status: ResponseStatus },
+    SetSignerThreshold { status: ResponseStatus },
+    SetLootTable { status: ResponseStatus },
+    RemoveLootTable { status: ResponseStatus },
+    SetLootBoxTypes { status: ResponseStatus },
+    BatchReceiveNft { status: ResponseStatus },
+    Migrate {
+        status: ResponseStatus,
+        /// Storage version before the migration
+        from_version: u32,
+        version: u32,
+    },
 }
 
 #[derive(Deserialize, Serialize, JsonSchema)]
@@ -147,9 +307,38 @@ pub enum QueryMsg {
         token_id: String,
     },
     Config {},
+    /// Loot box signers and the signer threshold
+    Signers {},
+    /// Penalty settings of `RedeemInstant`, `null` while it's disabled
+    InstantRedeem {},
+    /// Coins accepted by `DepositNative`
+    NativeToken {},
+    /// Outcomes and odds of the boxes of `box_type`
+    LootTable {
+        box_type: String,
+    },
+    /// Box type, and therefore loot table, of a loot box
+    LootBoxType {
+        loot_box_id: String,
+    },
     /// Number of withdraws pending to be claimed (both unbonding and claimable)
     NumOfPendingClaims {},
-    TotalBalances {},
+    /// Depth of the auto-claim queue and when its next claim matures
+    ClaimQueue {},
+    /// Total balances of a token, `Config::token` if not specified
+    TotalBalances {
+        token: Option<HumanAddr>,
+    },
+    /// Tokens accepted besides `Config::token`
+    SupportedTokens {},
+    /// Contracts funds can be sent to from the platform, in registration order
+    ReceivingContracts {
+        page: Option<u32>,
+        /// At most 50
+        page_size: u32,
+    },
+    /// Version of the storage, and the version the contract's code expects
+    StorageVersion {},
 
     // Authenticated
     /// Balance of an account
@@ -159,6 +348,16 @@ pub enum QueryMsg {
         /// Viewing key of the account
         key: String,
     },
+    /// Loot boxes opened by an account, newest first
+    LootBoxHistory {
+        /// Address of the account
+        address: HumanAddr,
+        /// Viewing key of the account
+        key: String,
+        page: Option<u32>,
+        /// At most 50
+        page_size: u32,
+    },
 
     // Permits  
     /// Permit queries. See more: [Permits API](https://github.com/SecretFoundation/SNIPs/blob/master/SNIP-24.md)
@@ -176,22 +375,86 @@ pub enum QueryMsg {
 #[cfg_attr(test, derive(Deserialize))]
 pub enum QueryAnswer {
     Config(Config),
+    Signers(Signers),
+    InstantRedeem(Option<InstantRedeem>),
+    NativeToken(NativeToken),
+    LootTable(ResponseLootTable),
+    LootBoxType(String),
     Balance(ResponseBalances),
     NumOfPendingClaims(Uint128),
+    ClaimQueue(ResponseClaimQueue),
     TotalBalances(ResponseTotalBalances),
+    SupportedTokens(Vec<SupportedToken>),
+    ReceivingContracts(ResponseReceivingContracts),
+    LootBoxHistory(ResponseLootBoxHistory),
+    Allowances(Vec<PlatformAllowance>),
+    StorageVersion { version: u32, latest: u32 },
+}
+
+#[derive(Serialize, Deserialize, Clone, JsonSchema)]
+pub struct ResponseLootTable {
+    pub box_type: String,
+    /// Sum of the outcome weights, the odds of an outcome are `weight / total_weight`
+    pub total_weight: u64,
+    pub outcomes: Vec<LootOutcome>,
+}
+
+#[derive(Serialize, Deserialize, Clone, JsonSchema)]
+pub struct ResponseClaimQueue {
+    /// Number of claims waiting in the queue (both unbonding and matured)
+    pub pending: u32,
+    /// Unbonding period ending timestamp (in seconds) of the next claim in the queue
+    pub next_end_ts: Option<u64>,
+}
+
+#[derive(Serialize, Deserialize, Clone, JsonSchema)]
+pub struct ResponseReceivingContracts {
+    pub contracts: Vec<ReceivingContract>,
+    /// Total number of receiving contracts
+    pub total: u64,
+}
+
+#[derive(Serialize, Deserialize, Clone, JsonSchema)]
+pub struct ResponseLootBoxHistory {
+    pub records: Vec<LootBoxRecord>,
+    /// Total number of loot boxes opened by the account
+    pub total: u64,
 }
 
 #[derive(Serialize, Deserialize, Default, Clone, JsonSchema)]
 pub struct ResponseBalances {
+    /// Staked amount of `Config::token`, not including unbonding (or claimable) funds
+    pub staked: Uint128,
+    /// Withdraw requests of `Config::token`
+    pub pending_redeem: ResponseRedeemInfo,
+    /// Balances of all the supported tokens, starting with `Config::token`
+    pub tokens: Vec<ResponseTokenBalances>,
+}
+
+impl ResponseBalances {
+    pub fn new(tokens: Vec<ResponseTokenBalances>) -> Self {
+        let primary = tokens.first().cloned().unwrap_or_default();
+        Self {
+            staked: primary.staked,
+            pending_redeem: primary.pending_redeem,
+            tokens,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Default, Clone, JsonSchema)]
+pub struct ResponseTokenBalances {
+    pub token: HumanAddr,
     /// Staked amount, not including unbonding (or claimable) funds
     pub staked: Uint128,
     /// Withdraw requests
     pub pending_redeem: ResponseRedeemInfo,
 }
 
-impl From<Balances> for ResponseBalances {
-    fn from(b: Balances) -> Self {
+impl ResponseTokenBalances {
+    pub fn new(token: HumanAddr, b: Balances) -> Self {
         Self {
+            token,
             staked: b.staked.into(),
             pending_redeem: b.pending_redeem.into(),
         }
@@ -252,6 +515,7 @@ impl QueryMsg {
     pub fn get_validation_params(&self) -> (&HumanAddr, String) {
         match self {
             QueryMsg::Balance { address, key } => (address, key.clone()),
+            QueryMsg::LootBoxHistory { address, key, .. } => (address, key.clone()),
             _ => panic!("This should never happen"),
         }
     }
@@ -262,6 +526,10 @@ impl QueryMsg {
 pub enum QueryWithPermit {
     /// Balance of an account (the account that signed the permit). Same as QueryMsg::Balance
     Balance {},
+    /// Loot boxes opened by the account that signed the permit. Same as QueryMsg::LootBoxHistory
+    LootBoxHistory { page: Option<u32>, page_size: u32 },
+    /// Platform allowances granted by the account that signed the permit
+    Allowances {},
 }
 
 #[derive(Serialize, JsonSchema)]
@@ -277,15 +545,3 @@ pub enum ResponseStatus {
     Success,
     Failure,
 }
-
-#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
-pub struct OpenLootBoxMessage {
-    pub loot_box_id: String,
-    pub open_lgnd_amount: Uint128,
-    pub open_nft_contract: Option<Contract>,
-    pub open_nft_uri: Option<String>,
-    pub message: Binary,
-    pub signature: Binary,
-    pub memo: Option<String>,
-}
-
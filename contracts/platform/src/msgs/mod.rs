@@ -1,4 +1,6 @@
 pub(crate) mod mint_nft;
 pub(crate) mod msg_enable_reveal;
 pub(crate) mod update_nft;
-pub(crate) mod nft_info;
\ No newline at end of file
+pub(crate) mod nft_info;
+pub(crate) mod transfer_nft;
+pub(crate) mod wrap_native;
@@ -1,36 +1,36 @@
 use cosmwasm_std::{to_binary, CosmosMsg, HumanAddr, StdResult, WasmMsg};
 use secret_toolkit::utils::types::Contract;
 
-use crate::snip721::metadata::Metadata;
-use crate::snip721::snip721_handle_msg::{HandleMsg, Mint};
+use crate::snip721::snip721_handle_msg::HandleMsg;
 
-#[allow(clippy::too_many_arguments)]
-#[allow(dead_code)]
-pub fn mint_nft_msg(
-    token_id: Option<String>,
-    owner: Option<HumanAddr>,
-    public_metadata: Option<Metadata>,
-    private_metadata: Option<Metadata>,
-    memo: Option<String>,
-    padding: Option<String>,
-    _block_size: usize,
+pub fn transfer_nft_msg(
     contract: Contract,
+    recipient: HumanAddr,
+    token_id: String,
+    memo: Option<String>,
 ) -> StdResult<CosmosMsg> {
     Ok(CosmosMsg::Wasm(WasmMsg::Execute {
         contract_addr: contract.address,
         callback_code_hash: contract.hash,
-        msg: to_binary(&HandleMsg::MintNft {
+        msg: to_binary(&HandleMsg::TransferNft {
+            recipient,
             token_id,
-            owner,
-            public_metadata,
-            private_metadata,
-            serial_number: None,
-            royalty_info: None,
-            token_type: None,
             memo,
-            padding,
-        })
-        .unwrap(),
+            padding: None,
+        })?,
+        send: vec![],
+    }))
+}
+
+pub fn register_receive_nft_msg(contract: Contract, code_hash: String) -> StdResult<CosmosMsg> {
+    Ok(CosmosMsg::Wasm(WasmMsg::Execute {
+        contract_addr: contract.address,
+        callback_code_hash: contract.hash,
+        msg: to_binary(&HandleMsg::RegisterReceiveNft {
+            code_hash,
+            also_implements_batch_receive_nft: Some(true),
+            padding: None,
+        })?,
         send: vec![],
     }))
 }
@@ -0,0 +1,20 @@
+use cosmwasm_std::{to_binary, Coin, CosmosMsg, StdResult, WasmMsg};
+use secret_toolkit::snip20::HandleMsg;
+use secret_toolkit::utils::space_pad;
+use secret_toolkit::utils::types::Contract;
+
+use crate::constants::RESPONSE_BLOCK_SIZE;
+
+/// SNIP-20 `Deposit` of native coins into their wrapper token. Unlike `snip20::deposit_msg`,
+/// the coins aren't assumed to be SCRT
+pub fn deposit_native_msg(wrapper: Contract, coin: Coin) -> StdResult<CosmosMsg> {
+    let mut msg = to_binary(&HandleMsg::Deposit { padding: None })?;
+    space_pad(&mut msg.0, RESPONSE_BLOCK_SIZE);
+
+    Ok(CosmosMsg::Wasm(WasmMsg::Execute {
+        contract_addr: wrapper.address,
+        callback_code_hash: wrapper.hash,
+        msg,
+        send: vec![coin],
+    }))
+}
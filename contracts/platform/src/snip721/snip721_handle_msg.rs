@@ -62,6 +62,27 @@ pub enum HandleMsg {
         /// optional message length padding
         padding: Option<String>,
     },
+    /// transfer a token
+    TransferNft {
+        /// recipient of the transfer
+        recipient: HumanAddr,
+        /// id of the token to transfer
+        token_id: String,
+        /// optional memo for the tx
+        memo: Option<String>,
+        /// optional message length padding
+        padding: Option<String>,
+    },
+    /// register that the message sending contract implements ReceiveNft and possibly
+    /// BatchReceiveNft
+    RegisterReceiveNft {
+        /// receving contract's code hash
+        code_hash: String,
+        /// optionally true if the contract also implements BatchReceiveNft
+        also_implements_batch_receive_nft: Option<bool>,
+        /// optional message length padding
+        padding: Option<String>,
+    },
 }
 
 
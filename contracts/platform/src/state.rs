@@ -1,7 +1,7 @@
-use cosmwasm_std::{Env, HumanAddr, ReadonlyStorage, StdError, StdResult, Storage, WasmQuery, QueryRequest, Binary};
+use cosmwasm_std::{Env, HumanAddr, ReadonlyStorage, StdError, StdResult, Storage, WasmQuery, QueryRequest, Binary, Uint128};
 use cosmwasm_storage::{PrefixedStorage, ReadonlyPrefixedStorage};
 use schemars::JsonSchema;
-use secret_toolkit::storage::{TypedStore, TypedStoreMut};
+use secret_toolkit::storage::{AppendStore, AppendStoreMut, TypedStore, TypedStoreMut};
 use secret_toolkit::utils::types::Contract;
 use serde::{Deserialize, Serialize};
 use std::cmp::Ordering;
@@ -17,7 +17,23 @@ const PREFIX_CONFIG: &[u8] = b"config";
 const PREFIX_BALANCES: &[u8] = b"balances";
 const PREFIX_NONCE: &[u8] = b"user_nonce";
 const PREFIX_RECEIVING_CONTRACTS: &[u8] = b"receiving_contracts";
+const PREFIX_RECEIVING_CONTRACT_INFO: &[u8] = b"receiving_contract_info";
+const PREFIX_RECEIVING_CONTRACT_LIST: &[u8] = b"receiving_contract_list";
+const PREFIX_RECEIVING_CONTRACT_USAGE: &[u8] = b"receiving_contract_usage";
+const PREFIX_SIGNERS: &[u8] = b"signers";
+const PREFIX_LOOT_BOX_HISTORY: &[u8] = b"loot_box_history";
+const PREFIX_INSTANT_REDEEM: &[u8] = b"instant_redeem";
+const PREFIX_STAKER_PENALTIES: &[u8] = b"staker_penalties";
+const PREFIX_PENALTY_INDEX: &[u8] = b"penalty_index";
 const PREFIX_TOTAL_BALANCE: &[u8] = b"total_balance";
+const PREFIX_SUPPORTED_TOKENS: &[u8] = b"supported_tokens";
+const PREFIX_TOKEN_BALANCES: &[u8] = b"token_balances";
+const PREFIX_TOKEN_TOTAL_BALANCES: &[u8] = b"token_total_balances";
+const PREFIX_PLATFORM_ALLOWANCES: &[u8] = b"platform_allowances";
+const PREFIX_NATIVE_TOKEN: &[u8] = b"native_token";
+
+/// Precision of `StakerPenalties::per_share`
+const PENALTY_SCALE: u128 = 1_000_000_000_000;
 
 // Stored Types
 
@@ -30,6 +46,7 @@ pub struct Config {
     pub native_token_denom: String,
     pub unbonding_period: u64,
     pub self_contract_addr: HumanAddr,
+    /// Loot box signer set at init. The signers accepted by `OpenLootBox` are managed in `Signers`
     pub signer_address: Binary,
     pub ts_now: u64,
 }
@@ -68,17 +85,246 @@ pub struct Balances {
 }
 
 impl Balances {
-    pub fn load<S: ReadonlyStorage>(storage: &S, key: &HumanAddr) -> StdResult<Option<Self>> {
-        let balances_store = ReadonlyPrefixedStorage::new(PREFIX_BALANCES, storage);
+    pub fn load<S: ReadonlyStorage>(
+        storage: &S,
+        vault: &Vault,
+        key: &HumanAddr,
+    ) -> StdResult<Option<Self>> {
+        let namespace = vault.namespace(PREFIX_BALANCES, PREFIX_TOKEN_BALANCES);
+        let balances_store = ReadonlyPrefixedStorage::multilevel(&namespace, storage);
         TypedStore::attach(&balances_store).may_load(key.0.as_bytes())
     }
 
-    pub fn save<S: Storage>(&self, storage: &mut S, key: &HumanAddr) -> StdResult<()> {
-        let mut balances_store = PrefixedStorage::new(PREFIX_BALANCES, storage);
+    pub fn save<S: Storage>(&self, storage: &mut S, vault: &Vault, key: &HumanAddr) -> StdResult<()> {
+        let namespace = vault.namespace(PREFIX_BALANCES, PREFIX_TOKEN_BALANCES);
+        let mut balances_store = PrefixedStorage::multilevel(&namespace, storage);
         TypedStoreMut::attach(&mut balances_store).store(key.0.as_bytes(), self)
     }
 }
 
+impl Balances {
+    /// Loads the balances and credits the staker penalties distributed since the last time they
+    /// were loaded this way. Use before changing `staked`, and save the balances afterwards.
+    /// Penalties are only redistributed in `Config::token`
+    pub fn load_settled<S: Storage>(
+        storage: &mut S,
+        vault: &Vault,
+        key: &HumanAddr,
+    ) -> StdResult<Self> {
+        let mut balances = Self::load(storage, vault, key)?.unwrap_or_default();
+        if !vault.is_primary() {
+            return Ok(balances);
+        }
+
+        let penalties = StakerPenalties::load(storage)?;
+        balances.staked += penalties.owed(storage, key, balances.staked)?;
+        StakerPenalties::set_index(storage, key, penalties.per_share)?;
+
+        Ok(balances)
+    }
+
+    /// Same as `load_settled`, without recording the settlement
+    pub fn load_with_penalties<S: ReadonlyStorage>(
+        storage: &S,
+        vault: &Vault,
+        key: &HumanAddr,
+    ) -> StdResult<Self> {
+        let mut balances = Self::load(storage, vault, key)?.unwrap_or_default();
+        if vault.is_primary() {
+            balances.staked +=
+                StakerPenalties::load(storage)?.owed(storage, key, balances.staked)?;
+        }
+
+        Ok(balances)
+    }
+}
+
+/// A SNIP-20 the platform accepts deposits of, besides `Config::token`
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct SupportedToken {
+    pub contract: Contract,
+    /// Unbonding period of the redeems of this token (in seconds)
+    pub unbonding_period: u64,
+}
+
+impl SupportedToken {
+    pub fn load_all<S: ReadonlyStorage>(storage: &S) -> StdResult<Vec<Self>> {
+        Ok(TypedStore::attach(storage)
+            .may_load(PREFIX_SUPPORTED_TOKENS)?
+            .unwrap_or_default())
+    }
+
+    pub fn save_all<S: Storage>(storage: &mut S, tokens: &[Self]) -> StdResult<()> {
+        TypedStoreMut::attach(storage).store(PREFIX_SUPPORTED_TOKENS, &tokens.to_vec())
+    }
+
+    /// Adds tokens, or updates the unbonding period of tokens that are already supported
+    pub fn set_multiple<S: Storage>(storage: &mut S, tokens: Vec<Self>) -> StdResult<()> {
+        let config = Config::get_unchecked(storage)?;
+        let mut supported = Self::load_all(storage)?;
+
+        for token in tokens {
+            if token.contract.address == config.token.address {
+                return Err(StdError::generic_err(format!(
+                    "{} is the platform token, its unbonding period is set with ChangeConfig",
+                    token.contract.address
+                )));
+            }
+
+            match supported
+                .iter_mut()
+                .find(|t| t.contract.address == token.contract.address)
+            {
+                Some(existing) => *existing = token,
+                None => supported.push(token),
+            }
+        }
+
+        Self::save_all(storage, &supported)
+    }
+}
+
+/// Native coins deposited with `DepositNative`. They are held as `wrapper` tokens, but the
+/// balances are kept apart from the wrapper's and paid back as native coins
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct NativeToken {
+    pub denom: String,
+    /// SNIP-20 the coins are wrapped into
+    pub wrapper: Contract,
+    /// Unbonding period of the redeems of native deposits (in seconds)
+    pub unbonding_period: u64,
+}
+
+impl NativeToken {
+    /// Defaults to wrapping `Config::native_token_denom` into `Config::token`
+    pub fn load<S: ReadonlyStorage>(storage: &S) -> StdResult<Self> {
+        if let Some(native_token) = TypedStore::attach(storage).may_load(PREFIX_NATIVE_TOKEN)? {
+            return Ok(native_token);
+        }
+
+        let config = Config::get_unchecked(storage)?;
+        Ok(Self {
+            denom: config.native_token_denom,
+            wrapper: config.token,
+            unbonding_period: config.unbonding_period,
+        })
+    }
+
+    pub fn save<S: Storage>(&self, storage: &mut S) -> StdResult<()> {
+        TypedStoreMut::attach(storage).store(PREFIX_NATIVE_TOKEN, self)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum VaultKind {
+    /// `Config::token`
+    Primary,
+    Supported,
+    Native { denom: String },
+}
+
+/// The balances of a single token. `Config::token` keeps using the storage it had before the
+/// platform supported other tokens, the rest are namespaced by their id: the token's address, or
+/// the denom of native deposits
+#[derive(Clone, Debug, PartialEq)]
+pub struct Vault {
+    pub token: Contract,
+    pub unbonding_period: u64,
+    kind: VaultKind,
+}
+
+impl Vault {
+    pub fn primary(config: &Config) -> Self {
+        Self {
+            token: config.token.clone(),
+            unbonding_period: config.unbonding_period,
+            kind: VaultKind::Primary,
+        }
+    }
+
+    pub fn native(native_token: NativeToken) -> Self {
+        Self {
+            token: native_token.wrapper,
+            unbonding_period: native_token.unbonding_period,
+            kind: VaultKind::Native {
+                denom: native_token.denom,
+            },
+        }
+    }
+
+    /// The vault of `token` (or native denom), or of `Config::token` if not specified
+    pub fn load<S: ReadonlyStorage>(storage: &S, token: Option<&HumanAddr>) -> StdResult<Self> {
+        let config = Config::get_unchecked(storage)?;
+        let token = match token {
+            Some(token) if *token != config.token.address => token,
+            _ => return Ok(Self::primary(&config)),
+        };
+
+        let native_token = NativeToken::load(storage)?;
+        if token.0 == native_token.denom {
+            return Ok(Self::native(native_token));
+        }
+
+        SupportedToken::load_all(storage)?
+            .into_iter()
+            .find(|t| t.contract.address == *token)
+            .map(Self::from)
+            .ok_or_else(|| {
+                StdError::generic_err(format!("this token is not supported, got: {}", token))
+            })
+    }
+
+    /// `Config::token` first, then the other supported tokens and the native deposits
+    pub fn load_all<S: ReadonlyStorage>(storage: &S) -> StdResult<Vec<Self>> {
+        let config = Config::get_unchecked(storage)?;
+        let mut vaults = vec![Self::primary(&config)];
+        vaults.extend(SupportedToken::load_all(storage)?.into_iter().map(Self::from));
+        vaults.push(Self::native(NativeToken::load(storage)?));
+
+        Ok(vaults)
+    }
+
+    pub fn is_primary(&self) -> bool {
+        self.kind == VaultKind::Primary
+    }
+
+    /// Denom of the native coins the vault is paid out in, if it isn't paid out as a SNIP-20
+    pub fn payout_denom(&self, config: &Config) -> Option<String> {
+        match &self.kind {
+            VaultKind::Primary => Some(config.native_token_denom.clone()),
+            VaultKind::Supported => None,
+            VaultKind::Native { denom } => Some(denom.clone()),
+        }
+    }
+
+    /// Identifies the vault in messages and queries
+    pub fn id(&self) -> HumanAddr {
+        match &self.kind {
+            VaultKind::Native { denom } => HumanAddr(denom.clone()),
+            _ => self.token.address.clone(),
+        }
+    }
+
+    /// Storage namespace of the vault, `legacy_prefix` being the one `Config::token` used before
+    /// the platform supported other tokens
+    pub fn namespace<'a>(&'a self, legacy_prefix: &'a [u8], prefix: &'a [u8]) -> Vec<&'a [u8]> {
+        match &self.kind {
+            VaultKind::Primary => vec![legacy_prefix],
+            VaultKind::Supported => vec![prefix, self.token.address.0.as_bytes()],
+            VaultKind::Native { denom } => vec![prefix, denom.as_bytes()],
+        }
+    }
+}
+
+impl From<SupportedToken> for Vault {
+    fn from(token: SupportedToken) -> Self {
+        Self {
+            token: token.contract,
+            unbonding_period: token.unbonding_period,
+            kind: VaultKind::Supported,
+        }
+    }
+}
 
 #[derive(Serialize, Deserialize)]
 pub struct Nonces {
@@ -105,6 +351,258 @@ impl Default for Nonces {
     }
 }
 
+#[derive(Serialize, Deserialize, Clone, JsonSchema)]
+pub struct Signer {
+    /// secp256k1 public key of the loot box signer
+    pub public_key: Binary,
+    /// Timestamp (in seconds) from which the signer is accepted
+    pub valid_from: Option<u64>,
+    /// Timestamp (in seconds) from which the signer is no longer accepted
+    pub valid_until: Option<u64>,
+}
+
+impl Signer {
+    pub fn new(public_key: Binary) -> Self {
+        Self {
+            public_key,
+            valid_from: None,
+            valid_until: None,
+        }
+    }
+
+    pub fn is_active(&self, now: u64) -> bool {
+        self.valid_from.is_none_or(|from| from <= now)
+            && self.valid_until.is_none_or(|until| now < until)
+    }
+}
+
+/// Signers allowed to authorize `OpenLootBox`, and how many of them have to sign each payload
+#[derive(Serialize, Deserialize, JsonSchema)]
+pub struct Signers {
+    pub signers: Vec<Signer>,
+    pub threshold: u32,
+}
+
+impl Signers {
+    /// Contracts instantiated before the signer set existed only have `Config::signer_address`
+    pub fn load<S: ReadonlyStorage>(storage: &S) -> StdResult<Self> {
+        match TypedStore::attach(storage).may_load(PREFIX_SIGNERS)? {
+            Some(signers) => Ok(signers),
+            None => Ok(Self {
+                signers: vec![Signer::new(Config::get_unchecked(storage)?.signer_address)],
+                threshold: 1,
+            }),
+        }
+    }
+
+    pub fn save<S: Storage>(&self, storage: &mut S) -> StdResult<()> {
+        TypedStoreMut::attach(storage).store(PREFIX_SIGNERS, self)
+    }
+}
+
+impl Signers {
+    /// Adds new signers, or updates the validity window of existing ones
+    pub fn set_multiple(&mut self, signers: Vec<Signer>) -> StdResult<()> {
+        // secp256k1 public keys, compressed or not
+        if let Some(signer) = signers
+            .iter()
+            .find(|s| s.public_key.len() != 33 && s.public_key.len() != 65)
+        {
+            return Err(StdError::generic_err(format!(
+                "signer public key must be 33 or 65 bytes long, got {}",
+                signer.public_key.len()
+            )));
+        }
+
+        for signer in signers {
+            match self
+                .signers
+                .iter_mut()
+                .find(|s| s.public_key == signer.public_key)
+            {
+                Some(existing) => *existing = signer,
+                None => self.signers.push(signer),
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn remove_multiple(&mut self, public_keys: &[Binary]) {
+        self.signers
+            .retain(|s| !public_keys.contains(&s.public_key));
+    }
+
+    pub fn set_threshold(&mut self, threshold: u32) -> StdResult<()> {
+        if threshold == 0 || threshold as usize > self.signers.len() {
+            return Err(StdError::generic_err(format!(
+                "signer threshold must be between 1 and the number of signers ({}), got {}",
+                self.signers.len(),
+                threshold
+            )));
+        }
+
+        self.threshold = threshold;
+        Ok(())
+    }
+
+    pub fn active(&self, now: u64) -> impl Iterator<Item = &Signer> {
+        self.signers.iter().filter(move |s| s.is_active(now))
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, JsonSchema)]
+pub struct LootBoxRecord {
+    pub loot_box_id: String,
+    /// LGND sent to the user from the distribute address
+    pub lgnd_amount: Uint128,
+    /// Collection of the item the box produced, if any
+    pub nft_contract: Option<HumanAddr>,
+    pub nft_uri: Option<String>,
+    /// Block time of the opening (in seconds)
+    pub timestamp: u64,
+    /// Nonce of signed openings. Boxes drawn on-chain have none
+    pub nonce: Option<u64>,
+}
+
+/// Append-only log of the loot boxes each user opened
+pub struct LootBoxHistory {}
+
+impl LootBoxHistory {
+    pub fn push<S: Storage>(
+        storage: &mut S,
+        user: &HumanAddr,
+        record: &LootBoxRecord,
+    ) -> StdResult<()> {
+        let mut history_store =
+            PrefixedStorage::multilevel(&[PREFIX_LOOT_BOX_HISTORY, user.0.as_bytes()], storage);
+        let mut history = AppendStoreMut::attach_or_create(&mut history_store)?;
+
+        history.push(record)
+    }
+
+    /// Returns a page of the user's history, newest first, and the total number of records
+    pub fn page<S: ReadonlyStorage>(
+        storage: &S,
+        user: &HumanAddr,
+        page: u32,
+        page_size: u32,
+    ) -> StdResult<(Vec<LootBoxRecord>, u32)> {
+        let history_store = ReadonlyPrefixedStorage::multilevel(
+            &[PREFIX_LOOT_BOX_HISTORY, user.0.as_bytes()],
+            storage,
+        );
+        let history = match AppendStore::<LootBoxRecord, _>::attach(&history_store) {
+            None => return Ok((vec![], 0)),
+            Some(h) => h?,
+        };
+
+        let records = history
+            .iter()
+            .rev()
+            .skip(page.saturating_mul(page_size) as usize)
+            .take(page_size as usize)
+            .collect::<StdResult<Vec<_>>>()?;
+
+        Ok((records, history.len()))
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum PenaltySink {
+    /// Send the penalty to the distribute address
+    DistributeAddress,
+    /// Burn the penalty
+    Burn,
+    /// Redistribute the penalty to everyone staked in the platform, pro rata
+    Stakers,
+}
+
+/// Redeeming without waiting for the unbonding period, for a penalty
+#[derive(Serialize, Deserialize, Clone, JsonSchema)]
+pub struct InstantRedeem {
+    /// Penalty in basis points of the redeemed amount
+    pub penalty_bps: u16,
+    pub sink: PenaltySink,
+}
+
+impl InstantRedeem {
+    pub fn load<S: ReadonlyStorage>(storage: &S) -> StdResult<Option<Self>> {
+        TypedStore::attach(storage).may_load(PREFIX_INSTANT_REDEEM)
+    }
+
+    pub fn save<S: Storage>(&self, storage: &mut S) -> StdResult<()> {
+        if self.penalty_bps > 10_000 {
+            return Err(StdError::generic_err(format!(
+                "penalty can't be more than 10000 basis points, got {}",
+                self.penalty_bps
+            )));
+        }
+
+        TypedStoreMut::attach(storage).store(PREFIX_INSTANT_REDEEM, self)
+    }
+
+    pub fn remove<S: Storage>(storage: &mut S) {
+        storage.remove(PREFIX_INSTANT_REDEEM)
+    }
+
+    pub fn penalty(&self, amount: u128) -> u128 {
+        amount * self.penalty_bps as u128 / 10_000
+    }
+}
+
+/// Instant redeem penalties redistributed to the stakers, as penalty per staked token
+#[derive(Serialize, Deserialize, Default)]
+pub struct StakerPenalties {
+    pub per_share: u128,
+}
+
+impl StakerPenalties {
+    pub fn load<S: ReadonlyStorage>(storage: &S) -> StdResult<Self> {
+        Ok(TypedStore::attach(storage)
+            .may_load(PREFIX_STAKER_PENALTIES)?
+            .unwrap_or_default())
+    }
+
+    pub fn save<S: Storage>(&self, storage: &mut S) -> StdResult<()> {
+        TypedStoreMut::attach(storage).store(PREFIX_STAKER_PENALTIES, self)
+    }
+
+    fn index<S: ReadonlyStorage>(storage: &S, user: &HumanAddr) -> StdResult<u128> {
+        let index_store = ReadonlyPrefixedStorage::new(PREFIX_PENALTY_INDEX, storage);
+        Ok(TypedStore::attach(&index_store)
+            .may_load(user.0.as_bytes())?
+            .unwrap_or_default())
+    }
+
+    fn set_index<S: Storage>(storage: &mut S, user: &HumanAddr, index: u128) -> StdResult<()> {
+        let mut index_store = PrefixedStorage::new(PREFIX_PENALTY_INDEX, storage);
+        TypedStoreMut::attach(&mut index_store).store(user.0.as_bytes(), &index)
+    }
+
+    /// Penalties `staked` earned since the user's last settlement
+    pub fn owed<S: ReadonlyStorage>(
+        &self,
+        storage: &S,
+        user: &HumanAddr,
+        staked: u128,
+    ) -> StdResult<u128> {
+        let index = Self::index(storage, user)?;
+        Ok(staked * (self.per_share - index) / PENALTY_SCALE)
+    }
+
+    /// Returns `false` if there is no one to distribute to
+    pub fn distribute(&mut self, amount: u128, total_staked: u128) -> bool {
+        if total_staked == 0 {
+            return false;
+        }
+
+        self.per_share += amount * PENALTY_SCALE / total_staked;
+        true
+    }
+}
+
 // Types
 
 #[derive(Serialize, Deserialize, Default, Clone)]
@@ -159,15 +657,50 @@ impl RedeemInfo {
         self.unbondings.len() - 1
     }
 
+    /// Takes `amount` (or everything, if `None`) out of the record that ends at `end_ts`, and returns
+    /// the amount taken. Records that finished unbonding can't be cancelled, they are claimable
+    pub fn cancel_unbonding(
+        &mut self,
+        env: &Env,
+        end_ts: u64,
+        amount: Option<u128>,
+    ) -> StdResult<u128> {
+        self.refresh(env);
+
+        let index = self
+            .unbondings
+            .iter()
+            .position(|u| u.end_ts == end_ts)
+            .ok_or_else(|| {
+                StdError::generic_err(format!("no pending unbonding ends at {}", end_ts))
+            })?;
+
+        let unbonding = &mut self.unbondings[index];
+        let amount = amount.unwrap_or(unbonding.amount);
+        if amount > unbonding.amount {
+            return Err(StdError::generic_err(format!(
+                "insufficient unbonding funds to cancel: unbonding={}, required={}",
+                unbonding.amount, amount,
+            )));
+        }
+
+        unbonding.amount -= amount;
+        if unbonding.amount == 0 {
+            // `remove` keeps the order of the rest of the records
+            self.unbondings.remove(index);
+        }
+
+        Ok(amount)
+    }
+
     /// Returns `true` if created a new record or `false` if didn't
     pub fn create_or_add_to_existing_unbonding_record(
         &mut self,
         env: &Env,
-        config: &Config,
+        unbonding_period: u64,
         amount: u128,
     ) -> StdResult<(UnbondingRecord, bool)> {
-        let bulk_end_ts =
-            env.block.time - (env.block.time % SECONDS_IN_DAY) + config.unbonding_period;
+        let bulk_end_ts = env.block.time - (env.block.time % SECONDS_IN_DAY) + unbonding_period;
         let new_record = UnbondingRecord {
             end_ts: bulk_end_ts,
             amount,
@@ -206,48 +739,346 @@ pub enum Features {
     Deposit,
 }
 
+/// A contract users can send their platform funds to, with `SendFromPlatform`
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct ReceivingContract {
+    pub address: HumanAddr,
+    /// Code hash of the contract, passed along with the tokens sent to it
+    pub code_hash: Option<String>,
+    pub label: String,
+    /// Max amount a single `SendFromPlatform` can send to the contract
+    pub max_per_tx: Option<Uint128>,
+    /// Max amount all accounts together can send to the contract per (UTC) day
+    pub daily_cap: Option<Uint128>,
+}
+
+impl ReceivingContract {
+    /// A contract registered without any metadata
+    pub fn new(address: HumanAddr) -> Self {
+        Self {
+            address,
+            code_hash: None,
+            label: String::new(),
+            max_per_tx: None,
+            daily_cap: None,
+        }
+    }
+}
+
+/// Amount sent to a receiving contract during `day` (days since epoch). Caps are counted in the
+/// base units of the token sent, so every token is tracked separately
+#[derive(Serialize, Deserialize, Default)]
+struct DailyUsage {
+    day: u64,
+    sent: u128,
+}
+
 pub struct ReceivingContracts {}
 
 impl ReceivingContracts {
-    pub fn get<S: ReadonlyStorage>(storage: &S, address: &HumanAddr) -> StdResult<bool> {
+    /// Receiving contracts registered before they had metadata are only kept as a flag, they are
+    /// treated as contracts without metadata
+    pub fn get<S: ReadonlyStorage>(
+        storage: &S,
+        address: &HumanAddr,
+    ) -> StdResult<Option<ReceivingContract>> {
+        let info_store = ReadonlyPrefixedStorage::new(PREFIX_RECEIVING_CONTRACT_INFO, storage);
+        if let Some(contract) = TypedStore::attach(&info_store).may_load(address.0.as_bytes())? {
+            return Ok(Some(contract));
+        }
+
         let store = ReadonlyPrefixedStorage::new(PREFIX_RECEIVING_CONTRACTS, storage);
-        match TypedStore::attach(&store).may_load(address.0.as_bytes())? {
-            None => Ok(false),
-            Some(s) => Ok(s),
+        match TypedStore::<bool, _>::attach(&store).may_load(address.0.as_bytes())? {
+            Some(true) => Ok(Some(ReceivingContract::new(address.clone()))),
+            _ => Ok(None),
         }
     }
 
-    pub fn set_multiple<S: Storage>(storage: &mut S, addresses: Vec<HumanAddr>) -> StdResult<()> {
-        let mut store = PrefixedStorage::new(PREFIX_RECEIVING_CONTRACTS, storage);
-        let mut typed_store = TypedStoreMut::attach(&mut store);
+    fn list<S: ReadonlyStorage>(storage: &S) -> StdResult<Vec<HumanAddr>> {
+        Ok(TypedStore::attach(storage)
+            .may_load(PREFIX_RECEIVING_CONTRACT_LIST)?
+            .unwrap_or_default())
+    }
 
-        for addr in addresses {
-            typed_store.store(addr.0.as_bytes(), &true)?
+    fn save_list<S: Storage>(storage: &mut S, list: &[HumanAddr]) -> StdResult<()> {
+        TypedStoreMut::attach(storage).store(PREFIX_RECEIVING_CONTRACT_LIST, &list.to_vec())
+    }
+
+    /// Registers contracts, or updates the metadata of contracts that are already registered
+    pub fn set_multiple<S: Storage>(
+        storage: &mut S,
+        contracts: Vec<ReceivingContract>,
+    ) -> StdResult<()> {
+        let mut list = Self::list(storage)?;
+
+        for contract in contracts {
+            if !list.contains(&contract.address) {
+                list.push(contract.address.clone());
+            }
+
+            let mut info_store = PrefixedStorage::new(PREFIX_RECEIVING_CONTRACT_INFO, storage);
+            TypedStoreMut::attach(&mut info_store).store(contract.address.0.as_bytes(), &contract)?;
         }
 
-        Ok(())
+        Self::save_list(storage, &list)
+    }
+
+    /// Registers contracts without metadata. Contracts that are already registered are kept as
+    /// they are, but legacy ones are moved into the list
+    pub fn add_multiple<S: Storage>(storage: &mut S, addresses: Vec<HumanAddr>) -> StdResult<()> {
+        let mut contracts = vec![];
+        for address in addresses {
+            if !Self::move_legacy(storage, &address)? && Self::get(storage, &address)?.is_none() {
+                contracts.push(ReceivingContract::new(address));
+            }
+        }
+
+        Self::set_multiple(storage, contracts)
     }
 
-    pub fn remove_multiple<S: Storage>(storage: &mut S, addresses: Vec<HumanAddr>) {
+    /// Moves a contract registered before receiving contracts had metadata into the list, which
+    /// can't find it otherwise. Returns `false` if the contract wasn't registered that way
+    pub fn move_legacy<S: Storage>(storage: &mut S, address: &HumanAddr) -> StdResult<bool> {
         let mut store = PrefixedStorage::new(PREFIX_RECEIVING_CONTRACTS, storage);
-        let mut typed_store = TypedStoreMut::<bool, PrefixedStorage<S>>::attach(&mut store);
+        let mut typed_store = TypedStoreMut::<bool, _>::attach(&mut store);
+        if typed_store.may_load(address.0.as_bytes())? != Some(true) {
+            return Ok(false);
+        }
+        typed_store.remove(address.0.as_bytes());
+
+        // Contracts given metadata since then are in the list already
+        if Self::get(storage, address)?.is_none() {
+            Self::set_multiple(storage, vec![ReceivingContract::new(address.clone())])?;
+        }
+
+        Ok(true)
+    }
+
+    pub fn remove_multiple<S: Storage>(
+        storage: &mut S,
+        addresses: Vec<HumanAddr>,
+    ) -> StdResult<()> {
+        let mut list = Self::list(storage)?;
+        list.retain(|address| !addresses.contains(address));
+        Self::save_list(storage, &list)?;
 
         for addr in addresses {
-            typed_store.remove(addr.0.as_bytes())
+            let mut store = PrefixedStorage::new(PREFIX_RECEIVING_CONTRACTS, storage);
+            TypedStoreMut::<bool, PrefixedStorage<S>>::attach(&mut store).remove(addr.0.as_bytes());
+
+            let mut info_store = PrefixedStorage::new(PREFIX_RECEIVING_CONTRACT_INFO, storage);
+            TypedStoreMut::<ReceivingContract, PrefixedStorage<S>>::attach(&mut info_store)
+                .remove(addr.0.as_bytes());
         }
+
+        Ok(())
+    }
+
+    /// Registered contracts, in registration order. Returns the page and the total number of
+    /// contracts
+    pub fn page<S: ReadonlyStorage>(
+        storage: &S,
+        page: u32,
+        page_size: u32,
+    ) -> StdResult<(Vec<ReceivingContract>, u32)> {
+        let list = Self::list(storage)?;
+        let contracts = list
+            .iter()
+            .skip((page as usize).saturating_mul(page_size as usize))
+            .take(page_size as usize)
+            .filter_map(|address| Self::get(storage, address).transpose())
+            .collect::<StdResult<Vec<_>>>()?;
+
+        Ok((contracts, list.len() as u32))
     }
 
     pub fn require_receiving<S: ReadonlyStorage>(
         storage: &S,
         address: &HumanAddr,
-    ) -> StdResult<()> {
-        match Self::get(storage, address)? {
-            true => Ok(()),
-            false => Err(StdError::generic_err(format!(
+    ) -> StdResult<ReceivingContract> {
+        Self::get(storage, address)?.ok_or_else(|| {
+            StdError::generic_err(format!(
                 "address {} is not a receiving contract, sending tokens from platform is not allowed",
                 address
-            ))),
+            ))
+        })
+    }
+
+    /// Checks the caps of the contract, and counts `amount` of `token` towards its daily cap
+    pub fn record_send<S: Storage>(
+        storage: &mut S,
+        contract: &ReceivingContract,
+        token: &HumanAddr,
+        amount: u128,
+        now: u64,
+    ) -> StdResult<()> {
+        if let Some(max_per_tx) = contract.max_per_tx {
+            if amount > max_per_tx.u128() {
+                return Err(StdError::generic_err(format!(
+                    "amount exceeds the per transaction cap of {}: cap={}, required={}",
+                    contract.address, max_per_tx, amount,
+                )));
+            }
+        }
+
+        let daily_cap = match contract.daily_cap {
+            None => return Ok(()),
+            Some(daily_cap) => daily_cap.u128(),
+        };
+
+        let mut usage_store = PrefixedStorage::multilevel(
+            &[PREFIX_RECEIVING_CONTRACT_USAGE, contract.address.0.as_bytes()],
+            storage,
+        );
+        let mut typed_store = TypedStoreMut::attach(&mut usage_store);
+
+        let today = now / SECONDS_IN_DAY;
+        let mut usage: DailyUsage = typed_store.may_load(token.0.as_bytes())?.unwrap_or_default();
+        if usage.day != today {
+            usage = DailyUsage { day: today, sent: 0 };
         }
+
+        usage.sent += amount;
+        if usage.sent > daily_cap {
+            return Err(StdError::generic_err(format!(
+                "amount exceeds the daily cap of {}: remaining today={}, required={}",
+                contract.address,
+                daily_cap.saturating_sub(usage.sent - amount),
+                amount,
+            )));
+        }
+
+        typed_store.store(token.0.as_bytes(), &usage)
+    }
+}
+
+/// Permission of `spender` to send up to `amount` of the owner's `token` from the platform
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct PlatformAllowance {
+    pub spender: HumanAddr,
+    pub token: HumanAddr,
+    pub amount: Uint128,
+    /// Time (in seconds) the allowance expires at, never if not specified
+    pub expiration: Option<u64>,
+}
+
+impl PlatformAllowance {
+    pub fn is_expired(&self, now: u64) -> bool {
+        self.expiration.is_some_and(|expiration| expiration <= now)
+    }
+
+    fn is_for(&self, spender: &HumanAddr, token: &HumanAddr) -> bool {
+        self.spender == *spender && self.token == *token
+    }
+}
+
+/// The allowances granted by each account, kept as a list since an account grants only a few
+pub struct PlatformAllowances {}
+
+impl PlatformAllowances {
+    pub fn load<S: ReadonlyStorage>(
+        storage: &S,
+        owner: &HumanAddr,
+    ) -> StdResult<Vec<PlatformAllowance>> {
+        let store = ReadonlyPrefixedStorage::new(PREFIX_PLATFORM_ALLOWANCES, storage);
+        Ok(TypedStore::attach(&store)
+            .may_load(owner.0.as_bytes())?
+            .unwrap_or_default())
+    }
+
+    fn save<S: Storage>(
+        storage: &mut S,
+        owner: &HumanAddr,
+        allowances: &[PlatformAllowance],
+    ) -> StdResult<()> {
+        let mut store = PrefixedStorage::new(PREFIX_PLATFORM_ALLOWANCES, storage);
+        TypedStoreMut::attach(&mut store).store(owner.0.as_bytes(), &allowances.to_vec())
+    }
+
+    /// Adds `amount` to the allowance, which starts over if it expired. Also drops the other
+    /// expired allowances of the owner
+    pub fn increase<S: Storage>(
+        storage: &mut S,
+        owner: &HumanAddr,
+        spender: HumanAddr,
+        token: HumanAddr,
+        amount: u128,
+        expiration: Option<u64>,
+        now: u64,
+    ) -> StdResult<PlatformAllowance> {
+        let mut allowances = Self::load(storage, owner)?;
+        allowances.retain(|a| !a.is_expired(now));
+
+        let allowance = match allowances.iter_mut().find(|a| a.is_for(&spender, &token)) {
+            Some(allowance) => {
+                allowance.amount = Uint128(allowance.amount.u128().saturating_add(amount));
+                allowance.expiration = expiration;
+                allowance.clone()
+            }
+            None => {
+                let allowance = PlatformAllowance {
+                    spender,
+                    token,
+                    amount: Uint128(amount),
+                    expiration,
+                };
+                allowances.push(allowance.clone());
+                allowance
+            }
+        };
+
+        Self::save(storage, owner, &allowances)?;
+        Ok(allowance)
+    }
+
+    pub fn revoke<S: Storage>(
+        storage: &mut S,
+        owner: &HumanAddr,
+        spender: &HumanAddr,
+        token: &HumanAddr,
+    ) -> StdResult<()> {
+        let mut allowances = Self::load(storage, owner)?;
+        allowances.retain(|a| !a.is_for(spender, token));
+        Self::save(storage, owner, &allowances)
+    }
+
+    /// What's left of the allowance, zero if it doesn't exist or expired
+    pub fn remaining<S: ReadonlyStorage>(
+        storage: &S,
+        owner: &HumanAddr,
+        spender: &HumanAddr,
+        token: &HumanAddr,
+        now: u64,
+    ) -> StdResult<u128> {
+        Ok(Self::load(storage, owner)?
+            .iter()
+            .find(|a| a.is_for(spender, token) && !a.is_expired(now))
+            .map_or(0, |a| a.amount.u128()))
+    }
+
+    pub fn spend<S: Storage>(
+        storage: &mut S,
+        owner: &HumanAddr,
+        spender: &HumanAddr,
+        token: &HumanAddr,
+        amount: u128,
+        now: u64,
+    ) -> StdResult<()> {
+        let remaining = Self::remaining(storage, owner, spender, token, now)?;
+        if amount > remaining {
+            return Err(StdError::generic_err(format!(
+                "insufficient allowance: allowance={}, required={}",
+                remaining, amount,
+            )));
+        }
+
+        let mut allowances = Self::load(storage, owner)?;
+        allowances.retain(|a| !a.is_expired(now));
+        if let Some(allowance) = allowances.iter_mut().find(|a| a.is_for(spender, token)) {
+            allowance.amount = Uint128(remaining - amount);
+        }
+
+        Self::save(storage, owner, &allowances)
     }
 }
 
@@ -258,22 +1089,33 @@ pub struct TotalBalances {
 }
 
 impl TotalBalances {
-    pub fn load<S: ReadonlyStorage>(storage: &S) -> StdResult<Option<Self>> {
-        TypedStore::attach(storage).may_load(PREFIX_TOTAL_BALANCE)
+    pub fn load<S: ReadonlyStorage>(storage: &S, vault: &Vault) -> StdResult<Option<Self>> {
+        if vault.is_primary() {
+            return TypedStore::attach(storage).may_load(PREFIX_TOTAL_BALANCE);
+        }
+
+        let store = ReadonlyPrefixedStorage::new(PREFIX_TOKEN_TOTAL_BALANCES, storage);
+        TypedStore::attach(&store).may_load(vault.id().0.as_bytes())
     }
 
-    pub fn save<S: Storage>(&self, storage: &mut S) -> StdResult<()> {
-        TypedStoreMut::attach(storage).store(PREFIX_TOTAL_BALANCE, self)
+    pub fn save<S: Storage>(&self, storage: &mut S, vault: &Vault) -> StdResult<()> {
+        if vault.is_primary() {
+            return TypedStoreMut::attach(storage).store(PREFIX_TOTAL_BALANCE, self);
+        }
+
+        let mut store = PrefixedStorage::new(PREFIX_TOKEN_TOTAL_BALANCES, storage);
+        TypedStoreMut::attach(&mut store).store(vault.id().0.as_bytes(), self)
     }
 }
 
 impl TotalBalances {
     pub fn handle_balance_change<S: Storage>(
         storage: &mut S,
+        vault: &Vault,
         change: BalanceChange,
         amount: u128,
     ) -> StdResult<()> {
-        let mut balances = Self::load(storage)?.unwrap_or_default();
+        let mut balances = Self::load(storage, vault)?.unwrap_or_default();
         match change {
             BalanceChange::Redeem => {
                 balances.staked -= amount;
@@ -282,8 +1124,13 @@ impl TotalBalances {
             BalanceChange::Claim => balances.unbonding -= amount,
             BalanceChange::Deposit => balances.staked += amount,
             BalanceChange::Send => balances.staked -= amount,
+            BalanceChange::RedeemInstant => balances.staked -= amount,
+            BalanceChange::CancelUnbonding => {
+                balances.unbonding -= amount;
+                balances.staked += amount;
+            }
         }
-        balances.save(storage)
+        balances.save(storage, vault)
     }
 
     pub fn to_query_result(&self) -> Self {
@@ -330,7 +1177,289 @@ fn leave_n_most_significant_digits(num: u128, digits: u32) -> u128 {
 
 pub enum BalanceChange {
     Redeem,
+    RedeemInstant,
+    CancelUnbonding,
     Claim,
     Deposit,
     Send,
 }
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use cosmwasm_std::testing::{mock_env, MockStorage};
+
+    fn contract(address: &str) -> Contract {
+        Contract {
+            address: HumanAddr::from(address),
+            hash: format!("{}_hash", address),
+        }
+    }
+
+    pub(crate) fn storage_with_config() -> MockStorage {
+        let mut storage = MockStorage::new();
+        Config {
+            admin: HumanAddr::from("admin"),
+            distribute_address: HumanAddr::from("distribute"),
+            token: contract("lgnd"),
+            legen_dao_nft: contract("nft"),
+            native_token_denom: "uscrt".to_string(),
+            unbonding_period: SECONDS_IN_DAY * 21,
+            self_contract_addr: HumanAddr::from("platform"),
+            signer_address: Binary::default(),
+            ts_now: 0,
+        }
+        .save(&mut storage)
+        .unwrap();
+
+        storage
+    }
+
+    fn redeem_info(records: &[(u64, u128)]) -> RedeemInfo {
+        RedeemInfo {
+            unbondings: records
+                .iter()
+                .map(|&(end_ts, amount)| UnbondingRecord { end_ts, amount })
+                .collect(),
+            claimable: 0,
+        }
+    }
+
+    #[test]
+    fn test_supported_tokens() {
+        let mut storage = storage_with_config();
+        let usdc = SupportedToken {
+            contract: contract("usdc"),
+            unbonding_period: SECONDS_IN_DAY,
+        };
+
+        let primary = Vault::load(&storage, None).unwrap();
+        assert!(primary.is_primary());
+        assert_eq!(Vault::load(&storage, Some(&"lgnd".into())).unwrap(), primary);
+        assert!(Vault::load(&storage, Some(&"usdc".into())).is_err());
+
+        let native = Vault::load(&storage, Some(&"uscrt".into())).unwrap();
+        assert_eq!(native.id(), HumanAddr::from("uscrt"));
+        assert_eq!(native.token, contract("lgnd"));
+        assert_ne!(native, primary);
+
+        SupportedToken::set_multiple(&mut storage, vec![usdc.clone()]).unwrap();
+        let vault = Vault::load(&storage, Some(&"usdc".into())).unwrap();
+        assert!(!vault.is_primary());
+        assert_eq!(vault.unbonding_period, SECONDS_IN_DAY);
+
+        // Updating a token doesn't add it twice
+        let usdc = SupportedToken {
+            unbonding_period: 2 * SECONDS_IN_DAY,
+            ..usdc
+        };
+        SupportedToken::set_multiple(&mut storage, vec![usdc.clone()]).unwrap();
+        assert_eq!(SupportedToken::load_all(&storage).unwrap(), vec![usdc]);
+
+        let lgnd = SupportedToken {
+            contract: contract("lgnd"),
+            unbonding_period: 0,
+        };
+        assert!(SupportedToken::set_multiple(&mut storage, vec![lgnd]).is_err());
+    }
+
+    #[test]
+    fn test_token_balances_are_separate() {
+        let mut storage = storage_with_config();
+        SupportedToken::set_multiple(
+            &mut storage,
+            vec![SupportedToken {
+                contract: contract("usdc"),
+                unbonding_period: SECONDS_IN_DAY,
+            }],
+        )
+        .unwrap();
+        let vaults = Vault::load_all(&storage).unwrap();
+        assert_eq!(vaults.len(), 3);
+        let user = HumanAddr::from("alice");
+
+        for (i, vault) in vaults.iter().enumerate() {
+            let balances = Balances {
+                staked: 100 * (i as u128 + 1),
+                ..Default::default()
+            };
+            balances.save(&mut storage, vault, &user).unwrap();
+            TotalBalances::handle_balance_change(&mut storage, vault, BalanceChange::Deposit, 7)
+                .unwrap();
+        }
+
+        for (i, vault) in vaults.iter().enumerate() {
+            let balances = Balances::load(&storage, vault, &user).unwrap().unwrap();
+            assert_eq!(balances.staked, 100 * (i as u128 + 1));
+            assert_eq!(TotalBalances::load(&storage, vault).unwrap().unwrap().staked, 7);
+        }
+
+        // `Config::token` stays where it was before multiple tokens were supported
+        let legacy_store = ReadonlyPrefixedStorage::new(PREFIX_BALANCES, &storage);
+        let legacy: Balances = TypedStore::attach(&legacy_store).load(b"alice").unwrap();
+        assert_eq!(legacy.staked, 100);
+    }
+
+    #[test]
+    fn test_platform_allowances() {
+        let mut storage = MockStorage::new();
+        let owner = HumanAddr::from("alice");
+        let (game, token) = (HumanAddr::from("game"), HumanAddr::from("lgnd"));
+        let remaining = |storage: &MockStorage, now| {
+            PlatformAllowances::remaining(storage, &owner, &game, &token, now).unwrap()
+        };
+
+        PlatformAllowances::increase(
+            &mut storage,
+            &owner,
+            game.clone(),
+            token.clone(),
+            100,
+            Some(50),
+            0,
+        )
+        .unwrap();
+        PlatformAllowances::increase(
+            &mut storage,
+            &owner,
+            game.clone(),
+            token.clone(),
+            20,
+            Some(60),
+            10,
+        )
+        .unwrap();
+        assert_eq!(remaining(&storage, 59), 120);
+        assert_eq!(remaining(&storage, 60), 0);
+        assert_eq!(
+            PlatformAllowances::remaining(&storage, &owner, &game, &"usdc".into(), 0).unwrap(),
+            0
+        );
+
+        PlatformAllowances::spend(&mut storage, &owner, &game, &token, 30, 20).unwrap();
+        assert_eq!(remaining(&storage, 20), 90);
+        assert!(PlatformAllowances::spend(&mut storage, &owner, &game, &token, 91, 20).is_err());
+        assert!(PlatformAllowances::spend(&mut storage, &owner, &game, &token, 1, 60).is_err());
+
+        // An expired allowance starts over
+        PlatformAllowances::increase(
+            &mut storage,
+            &owner,
+            game.clone(),
+            token.clone(),
+            5,
+            None,
+            70,
+        )
+        .unwrap();
+        assert_eq!(remaining(&storage, u64::MAX), 5);
+
+        PlatformAllowances::revoke(&mut storage, &owner, &game, &token).unwrap();
+        assert_eq!(remaining(&storage, 70), 0);
+        assert!(PlatformAllowances::load(&storage, &owner).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_receiving_contracts() {
+        let mut storage = MockStorage::new();
+
+        // Registered before receiving contracts had metadata
+        let mut legacy_store = PrefixedStorage::new(PREFIX_RECEIVING_CONTRACTS, &mut storage);
+        TypedStoreMut::attach(&mut legacy_store).store(b"legacy", &true).unwrap();
+        assert_eq!(
+            ReceivingContracts::require_receiving(&storage, &"legacy".into()).unwrap(),
+            ReceivingContract::new("legacy".into())
+        );
+
+        let minting = ReceivingContract {
+            code_hash: Some("minting_hash".to_string()),
+            label: "Minting".to_string(),
+            ..ReceivingContract::new("minting".into())
+        };
+        ReceivingContracts::set_multiple(&mut storage, vec![minting.clone()]).unwrap();
+        ReceivingContracts::add_multiple(&mut storage, vec!["minting".into(), "market".into()])
+            .unwrap();
+
+        let (contracts, total) = ReceivingContracts::page(&storage, 0, 10).unwrap();
+        assert_eq!(total, 2);
+        assert_eq!(contracts, vec![minting, ReceivingContract::new("market".into())]);
+        let (contracts, _) = ReceivingContracts::page(&storage, 1, 1).unwrap();
+        assert_eq!(contracts[0].address, HumanAddr::from("market"));
+
+        ReceivingContracts::remove_multiple(&mut storage, vec!["legacy".into(), "minting".into()])
+            .unwrap();
+        assert!(ReceivingContracts::require_receiving(&storage, &"legacy".into()).is_err());
+        assert!(ReceivingContracts::require_receiving(&storage, &"minting".into()).is_err());
+        assert_eq!(ReceivingContracts::page(&storage, 0, 10).unwrap().1, 1);
+    }
+
+    #[test]
+    fn test_add_legacy_receiving_contracts() {
+        let mut storage = MockStorage::new();
+        let mut legacy_store = PrefixedStorage::new(PREFIX_RECEIVING_CONTRACTS, &mut storage);
+        TypedStoreMut::attach(&mut legacy_store).store(b"legacy", &true).unwrap();
+        TypedStoreMut::attach(&mut legacy_store).store(b"minting", &true).unwrap();
+        assert_eq!(ReceivingContracts::page(&storage, 0, 10).unwrap().1, 0);
+
+        let minting = ReceivingContract {
+            label: "Minting".to_string(),
+            ..ReceivingContract::new("minting".into())
+        };
+        ReceivingContracts::set_multiple(&mut storage, vec![minting.clone()]).unwrap();
+        ReceivingContracts::add_multiple(&mut storage, vec!["legacy".into(), "minting".into()])
+            .unwrap();
+
+        let (contracts, total) = ReceivingContracts::page(&storage, 0, 10).unwrap();
+        assert_eq!(total, 2);
+        assert_eq!(contracts, vec![minting, ReceivingContract::new("legacy".into())]);
+        assert!(!ReceivingContracts::move_legacy(&mut storage, &"legacy".into()).unwrap());
+    }
+
+    #[test]
+    fn test_receiving_contract_caps() {
+        let mut storage = MockStorage::new();
+        let contract = ReceivingContract {
+            max_per_tx: Some(Uint128(60)),
+            daily_cap: Some(Uint128(100)),
+            ..ReceivingContract::new("market".into())
+        };
+        let (lgnd, usdc) = (HumanAddr::from("lgnd"), HumanAddr::from("usdc"));
+        let day = SECONDS_IN_DAY;
+
+        assert!(ReceivingContracts::record_send(&mut storage, &contract, &lgnd, 61, day).is_err());
+        ReceivingContracts::record_send(&mut storage, &contract, &lgnd, 60, day).unwrap();
+        ReceivingContracts::record_send(&mut storage, &contract, &lgnd, 40, day + 1).unwrap();
+        assert!(
+            ReceivingContracts::record_send(&mut storage, &contract, &lgnd, 1, day + 2).is_err()
+        );
+
+        // Other tokens and the next day have their own quota
+        ReceivingContracts::record_send(&mut storage, &contract, &usdc, 60, day + 2).unwrap();
+        ReceivingContracts::record_send(&mut storage, &contract, &lgnd, 60, 2 * day).unwrap();
+    }
+
+    fn end_timestamps(redeem_info: &RedeemInfo) -> Vec<u64> {
+        redeem_info.unbondings.iter().map(|u| u.end_ts).collect()
+    }
+
+    #[test]
+    fn test_cancel_unbonding() {
+        let mut env = mock_env("alice", &[]);
+        env.block.time = 100;
+        let mut redeem_info = redeem_info(&[(400, 10), (300, 20), (200, 30)]);
+
+        assert_eq!(redeem_info.cancel_unbonding(&env, 300, Some(5)).unwrap(), 5);
+        assert_eq!(redeem_info.unbondings[1].amount, 15);
+        assert!(redeem_info.cancel_unbonding(&env, 300, Some(16)).is_err());
+        assert!(redeem_info.cancel_unbonding(&env, 250, None).is_err());
+
+        assert_eq!(redeem_info.cancel_unbonding(&env, 300, None).unwrap(), 15);
+        assert_eq!(end_timestamps(&redeem_info), vec![400, 200]);
+
+        // Matured records are claimable and can't be cancelled anymore
+        env.block.time = 201;
+        assert!(redeem_info.cancel_unbonding(&env, 200, None).is_err());
+        assert_eq!(redeem_info.claimable, 30);
+        assert_eq!(end_timestamps(&redeem_info), vec![400]);
+    }
+}
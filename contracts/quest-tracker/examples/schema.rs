@@ -3,7 +3,7 @@ use std::fs::create_dir_all;
 
 use cosmwasm_schema::{export_schema, remove_schemas, schema_for};
 
-use quest_tracker::{HandleMsg, InitMsg, QueryMsg};
+use quest_tracker::{HandleMsg, InitMsg, QueryMsg, ReceiveMsg};
 
 fn main() {
     let mut out_dir = current_dir().unwrap();
@@ -14,4 +14,5 @@ fn main() {
     export_schema(&schema_for!(InitMsg), &out_dir);
     export_schema(&schema_for!(HandleMsg), &out_dir);
     export_schema(&schema_for!(QueryMsg), &out_dir);
+    export_schema(&schema_for!(ReceiveMsg), &out_dir);
 }
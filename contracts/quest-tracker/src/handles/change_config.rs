@@ -1,9 +1,4 @@
-use cosmwasm_std::{
-    log, to_binary, Api, CosmosMsg, Env, Extern, HandleResponse, Querier, StdResult, Storage,
-    WasmMsg,
-};
-
-use airdrop::HandleMsg::SetQuestPassword;
+use cosmwasm_std::{log, Api, Env, Extern, HandleResponse, Querier, StdResult, Storage};
 
 use crate::state::Config;
 
@@ -18,29 +13,20 @@ pub fn change_config<S: Storage, A: Api, Q: Querier>(
     config.assert_admin(&env.message.sender)?;
 
     config.admin = new_config.admin.unwrap_or(config.admin);
-    config.token = new_config.token.unwrap_or(config.token);
-    config.platform = new_config.platform.unwrap_or(config.platform);
-
     let mut messages = vec![];
-
-    // reset airdrop contract
-    if let Some(airdrop_contract) = new_config.airdrop_contract {
-        config.airdrop_contract = Some(airdrop_contract.clone());
-
-        // todo: generate password
-        config.password = Some("hello".to_string());
-
-        let msg = to_binary(&SetQuestPassword {
-            password: config.password.clone().unwrap(),
-        })?;
-
-        messages.push(CosmosMsg::Wasm(WasmMsg::Execute {
-            contract_addr: airdrop_contract.address,
-            callback_code_hash: airdrop_contract.hash,
-            msg,
-            send: vec![],
-        }));
+    if let Some(token) = new_config.token {
+        // quest budgets are funded through `Receive`
+        messages.push(secret_toolkit::snip20::register_receive_msg(
+            env.contract_code_hash,
+            None,
+            256,
+            token.hash.clone(),
+            token.address.clone(),
+        )?);
+        config.token = token;
     }
+    config.platform = new_config.platform.unwrap_or(config.platform);
+    config.airdrop_round = new_config.airdrop_round.unwrap_or(config.airdrop_round);
 
     config.save(&mut deps.storage)?;
 
@@ -1,12 +1,17 @@
 use cosmwasm_std::{
-    to_binary, Api, Env, Extern, HandleResponse, HumanAddr, Querier, QueryRequest, StdError,
+    log, to_binary, Api, Env, Extern, HandleResponse, HumanAddr, Querier, QueryRequest, StdError,
     StdResult, Storage, Uint128, WasmQuery,
 };
 
-use crate::state::{get_quest_contract, get_quest_weight, set_quest_status, Config};
+use crate::handles::pay_rewards::reward_msg;
+use crate::state::{
+    get_quest_contract, Config, PendingReward, PendingRewards, Quest, QuestBudget, QuestReward,
+    QuestStatus,
+};
 
+use airdrop::AccountInfoResponse;
+use airdrop::Deposit;
 use airdrop::QueryMsg::GetAccountInfo;
-use airdrop::{AccountInfoResponse, Deposit, LgndReceiveMsg};
 
 pub fn complete_quest<S: Storage, A: Api, Q: Querier>(
     deps: &mut Extern<S, A, Q>,
@@ -14,65 +19,245 @@ pub fn complete_quest<S: Storage, A: Api, Q: Querier>(
     config: Config,
     address: HumanAddr,
 ) -> StdResult<HandleResponse> {
-    let quest = get_quest_contract(&deps.storage, &env.message.sender);
+    let quest = get_quest_contract(&deps.storage, &env.message.sender).ok_or_else(|| {
+        StdError::generic_err(format!(
+            "Address {} is not a registered quest contract",
+            env.message.sender
+        ))
+    })?;
+
+    // each quest only pays out once. Don't fail, so the quest contract's transaction still goes through
+    let mut status = QuestStatus::load(&deps.storage, &address)?;
+    if status.is_completed(quest) {
+        return Ok(HandleResponse {
+            log: vec![log("already_completed", quest)],
+            ..Default::default()
+        });
+    }
 
-    let weight = get_quest_weight(&deps.storage, quest);
+    // quests that can't be completed right now don't fail it either
+    let mut quest_info = match Quest::may_load(&deps.storage, quest)? {
+        Some(quest_info) => quest_info,
+        None => {
+            return Ok(not_completed(
+                quest,
+                format!("Quest {} does not exist", quest),
+            ))
+        }
+    };
+    if let Err(reason) = quest_info.check_can_complete(env.block.time, &status) {
+        return Ok(not_completed(quest, reason));
+    }
 
-    let airdrop_contract = if let Some(contract) = config.airdrop_contract {
-        Ok(contract)
-    } else {
-        Err(StdError::generic_err(
-            "Cannot complete quest before airdrop contract is set up",
-        ))
-    }?;
+    let reward = match quest_info.definition.reward {
+        QuestReward::Fixed(amount) => amount.u128(),
+        QuestReward::Multiplier(multiplier) => {
+            let base_amount = query_airdrop_amount(deps, &env, config.clone(), &address)?;
+            base_amount
+                .checked_mul(multiplier as u128)
+                .ok_or_else(|| StdError::generic_err("Quest reward exceeded u128::MAX"))?
+        }
+    };
+
+    quest_info.completions += 1;
+    quest_info.save(&mut deps.storage)?;
+
+    status.set_completed(quest);
+
+    // Rewards wait in line if the quest is out of funds, so the quest contract's transaction
+    // doesn't fail on an insufficient balance
+    let mut budget = QuestBudget::load(&deps.storage, quest)?;
+    let mut messages = vec![];
+    let mut logs = vec![log("completed", quest)];
+    if reward > 0 {
+        if PendingRewards::len(&deps.storage, quest)? == 0 && budget.try_pay(reward) {
+            status.rewarded += reward;
+            messages.push(reward_msg(
+                config,
+                vec![Deposit {
+                    to: address.clone(),
+                    amount: Uint128(reward),
+                }],
+            )?);
+        } else {
+            budget.owed = Uint128(budget.owed.u128() + reward);
+            PendingRewards::push(
+                &mut deps.storage,
+                quest,
+                &PendingReward {
+                    address: address.clone(),
+                    amount: reward,
+                },
+            )?;
+            logs.push(log("reward_queued", reward));
+        }
+    }
+
+    budget.save(&mut deps.storage, quest)?;
+    status.save(&mut deps.storage, &address)?;
+
+    Ok(HandleResponse {
+        messages,
+        log: logs,
+        data: None,
+    })
+}
+
+fn not_completed(quest: u8, reason: String) -> HandleResponse {
+    HandleResponse {
+        log: vec![log("not_completed", quest), log("reason", reason)],
+        ..Default::default()
+    }
+}
+
+/// Get the base amount of multiplier rewards from the airdrop contract
+fn query_airdrop_amount<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    env: &Env,
+    config: Config,
+    address: &HumanAddr,
+) -> StdResult<u128> {
+    let (airdrop_contract, airdrop_key) =
+        if let (Some(contract), Some(key)) = (config.airdrop_contract, config.airdrop_key) {
+            Ok((contract, key))
+        } else {
+            Err(StdError::generic_err(
+                "Cannot complete quest before airdrop contract is set up",
+            ))
+        }?;
 
-    // get base amount from airdrop contract
     let query_msg = QueryRequest::Wasm(WasmQuery::Smart {
         contract_addr: airdrop_contract.address,
         callback_code_hash: airdrop_contract.hash,
         msg: to_binary(&GetAccountInfo {
-            password: config.password.unwrap(),
+            round: config.airdrop_round,
+            querier: env.contract.address.clone(),
+            key: airdrop_key,
             address: address.clone(),
         })?,
     });
     let query_result: AccountInfoResponse = deps.querier.query(&query_msg)?;
-    let base_amount = query_result.account.amount.u128();
-
-    set_quest_status(&mut deps.storage, &address, quest)?;
-
-    let mut distribute_msg = vec![];
-    if weight > 0 {
-        let send_amount = Uint128(base_amount * weight as u128);
-
-        distribute_msg.push(secret_toolkit::snip20::send_msg_with_code_hash(
-            config.platform.address,
-            Some(config.platform.hash),
-            send_amount,
-            Some(to_binary(&LgndReceiveMsg::BatchDeposit(vec![Deposit {
-                to: address,
-                amount: send_amount,
-            }]))?),
-            Some("complete quest".to_string()),
-            None,
-            64,
-            config.token.hash,
-            config.token.address,
-        )?);
-
-        // distribute_msg.push(secret_toolkit::snip20::transfer_msg(
-        //     address,
-        //     Uint128(base_amount * weight as u128),
-        //     None,
-        //     None,
-        //     64,
-        //     config.token.hash,
-        //     config.token.address,
-        // )?)
+
+    Ok(query_result.account.amount.u128())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::handles::{handle, HandleMsg};
+    use crate::queries::{query, QueryMsg, QuestStatusResponse};
+    use crate::unittest_utils::{
+        complete_quest_helper, create_quest_helper, extract_error_msg, init_helper,
+        quest_definition, Deps,
+    };
+    use cosmwasm_std::from_binary;
+    use cosmwasm_std::testing::mock_env;
+
+    fn quest_status(deps: &Deps, address: &str) -> QuestStatusResponse {
+        let msg = QueryMsg::GetQuestStatus {
+            address: HumanAddr::from(address),
+        };
+        from_binary(&query(deps, msg).unwrap()).unwrap()
     }
 
-    Ok(HandleResponse {
-        messages: distribute_msg,
-        log: vec![],
-        data: None,
-    })
+    #[test]
+    fn test_complete_quests_once() {
+        let mut deps = init_helper();
+        for quest in 0..2 {
+            let reward = QuestReward::Fixed(Uint128(0));
+            create_quest_helper(&mut deps, quest_definition(quest, reward)).unwrap();
+        }
+
+        let msg = HandleMsg::CompleteQuest {
+            address: HumanAddr::from("alice"),
+        };
+        let result = handle(&mut deps, mock_env("alice", &[]), msg);
+        assert_eq!(
+            extract_error_msg(result),
+            "Address alice is not a registered quest contract"
+        );
+
+        let response = complete_quest_helper(&mut deps, 1, "alice", 0).unwrap();
+        assert_eq!(response.log, vec![log("completed", 1)]);
+        let response = complete_quest_helper(&mut deps, 1, "alice", 0).unwrap();
+        assert_eq!(response.log, vec![log("already_completed", 1)]);
+
+        // every quest is tracked separately
+        let response = complete_quest_helper(&mut deps, 0, "alice", 0).unwrap();
+        assert_eq!(response.log, vec![log("completed", 0)]);
+        assert_eq!(quest_status(&deps, "alice").completed, vec![0, 1]);
+        assert_eq!(quest_status(&deps, "bob").completed, Vec::<u8>::new());
+
+        let response = complete_quest_helper(&mut deps, 2, "alice", 0).unwrap();
+        assert_eq!(
+            response.log,
+            vec![
+                log("not_completed", 2),
+                log("reason", "Quest 2 does not exist")
+            ]
+        );
+        assert_eq!(Quest::load(&deps.storage, 1).unwrap().completions, 1);
+    }
+
+    #[test]
+    fn test_complete_quest_conditions() {
+        let mut deps = init_helper();
+        let reward = QuestReward::Fixed(Uint128(0));
+
+        let mut window = quest_definition(0, reward.clone());
+        window.start_time = Some(100);
+        window.end_time = Some(100);
+        let result = create_quest_helper(&mut deps, window.clone());
+        assert_eq!(extract_error_msg(result), "Quest must end after it starts");
+        window.end_time = Some(200);
+        create_quest_helper(&mut deps, window).unwrap();
+
+        let mut capped = quest_definition(1, reward.clone());
+        capped.max_completions = Some(1);
+        create_quest_helper(&mut deps, capped).unwrap();
+
+        let mut follow_up = quest_definition(2, reward);
+        follow_up.prerequisites = vec![0, 2];
+        let result = create_quest_helper(&mut deps, follow_up.clone());
+        assert_eq!(
+            extract_error_msg(result),
+            "Quest can not be a prerequisite of itself"
+        );
+        follow_up.prerequisites = vec![0];
+        create_quest_helper(&mut deps, follow_up).unwrap();
+
+        let not_completed =
+            |quest: u8, reason: &str| vec![log("not_completed", quest), log("reason", reason)];
+
+        // the window includes its start, but not its end
+        let response = complete_quest_helper(&mut deps, 0, "alice", 99).unwrap();
+        assert_eq!(
+            response.log,
+            not_completed(0, "Quest 0 has not started yet")
+        );
+        let response = complete_quest_helper(&mut deps, 0, "alice", 200).unwrap();
+        assert_eq!(response.log, not_completed(0, "Quest 0 has ended"));
+        let response = complete_quest_helper(&mut deps, 0, "alice", 100).unwrap();
+        assert_eq!(response.log, vec![log("completed", 0)]);
+
+        let response = complete_quest_helper(&mut deps, 1, "alice", 0).unwrap();
+        assert_eq!(response.log, vec![log("completed", 1)]);
+        let response = complete_quest_helper(&mut deps, 1, "bob", 0).unwrap();
+        assert_eq!(
+            response.log,
+            not_completed(1, "Quest 1 reached its maximum completions")
+        );
+
+        let response = complete_quest_helper(&mut deps, 2, "bob", 0).unwrap();
+        assert_eq!(
+            response.log,
+            not_completed(2, "Quest 0 must be completed first")
+        );
+        let response = complete_quest_helper(&mut deps, 2, "alice", 0).unwrap();
+        assert_eq!(response.log, vec![log("completed", 2)]);
+
+        // quests that weren't completed can still be completed later
+        assert_eq!(quest_status(&deps, "bob").completed, Vec::<u8>::new());
+        assert_eq!(Quest::load(&deps.storage, 0).unwrap().completions, 1);
+    }
 }
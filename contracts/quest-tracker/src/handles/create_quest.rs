@@ -0,0 +1,27 @@
+use cosmwasm_std::{log, Api, Env, Extern, HandleResponse, Querier, StdError, StdResult, Storage};
+
+use crate::state::{Config, Quest, QuestDefinition};
+
+/// Defines a new quest
+pub fn create_quest<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    config: Config,
+    definition: QuestDefinition,
+) -> StdResult<HandleResponse> {
+    config.assert_admin(&env.message.sender)?;
+
+    if Quest::may_load(&deps.storage, definition.id)?.is_some() {
+        return Err(StdError::generic_err(format!(
+            "Quest {} already exists",
+            definition.id
+        )));
+    }
+
+    Quest::new(definition)?.save(&mut deps.storage)?;
+
+    Ok(HandleResponse {
+        log: vec![log("create quest", "success")],
+        ..Default::default()
+    })
+}
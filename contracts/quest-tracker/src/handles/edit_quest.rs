@@ -0,0 +1,24 @@
+use cosmwasm_std::{log, Api, Env, Extern, HandleResponse, Querier, StdResult, Storage};
+
+use crate::state::{Config, Quest, QuestDefinition};
+
+/// Replaces the definition of an existing quest. Completions made so far are kept
+pub fn edit_quest<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    config: Config,
+    definition: QuestDefinition,
+) -> StdResult<HandleResponse> {
+    config.assert_admin(&env.message.sender)?;
+
+    let completions = Quest::load(&deps.storage, definition.id)?.completions;
+
+    let mut quest = Quest::new(definition)?;
+    quest.completions = completions;
+    quest.save(&mut deps.storage)?;
+
+    Ok(HandleResponse {
+        log: vec![log("edit quest", "success")],
+        ..Default::default()
+    })
+}
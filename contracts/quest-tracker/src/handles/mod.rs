@@ -1,23 +1,35 @@
 use schemars::JsonSchema;
 use serde::Deserialize;
 
-use cosmwasm_std::{Api, Env, Extern, HandleResponse, HumanAddr, Querier, StdResult, Storage};
+use cosmwasm_std::{
+    Api, Binary, Env, Extern, HandleResponse, HumanAddr, Querier, StdResult, Storage, Uint128,
+};
 
-use crate::state::Config;
+use crate::migration::require_current_version;
+use crate::state::{Config, QuestDefinition};
 use crate::types::secret_contract::SecretContract;
 
 mod complete_quest;
 use complete_quest::complete_quest;
 
 mod change_config;
-mod change_quest_weight;
+mod create_quest;
+mod edit_quest;
+mod migrate;
+mod pay_rewards;
+mod receive;
 mod register_airdrop_contract;
 mod register_quest_contract;
 mod remove_quest_contract;
 
 use crate::handles::register_airdrop_contract::register_airdrop_contract;
 use change_config::change_config;
-use change_quest_weight::change_quest_weight;
+use create_quest::create_quest;
+use edit_quest::edit_quest;
+use migrate::migrate_storage;
+use pay_rewards::pay_pending_rewards;
+use receive::receive;
+pub use receive::ReceiveMsg;
 use register_quest_contract::register_quest_contract;
 use remove_quest_contract::remove_quest_contract;
 
@@ -27,19 +39,54 @@ pub struct NewConfig {
     admin: Option<HumanAddr>,
     // platform: Option<SecretContract>,
     token: Option<SecretContract>,
-    airdrop_contract: Option<SecretContract>,
+    airdrop_round: Option<u32>,
     platform: Option<SecretContract>,
 }
 
 #[derive(Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 #[serde(rename_all = "snake_case")]
 pub enum HandleMsg {
-    CompleteQuest { address: HumanAddr },
+    CompleteQuest {
+        address: HumanAddr,
+    },
+    /// Fund quest rewards through the SNIP-20 `Send` interface, see `ReceiveMsg`
+    Receive {
+        sender: HumanAddr,
+        from: HumanAddr,
+        amount: Uint128,
+        msg: Option<Binary>,
+    },
+    /// Pay rewards that were queued while a quest was out of funds
+    PayPendingRewards {
+        quest: u8,
+        limit: Option<u32>,
+    },
     ChangeConfig(NewConfig),
-    RegisterQuestContract { address: HumanAddr, quest: u8 },
-    RegisterAirdropContract { contract: SecretContract },
-    RemoveQuestContract { address: HumanAddr },
-    ChangeQuestWeight { quest: u8, weight: u8 },
+    RegisterQuestContract {
+        address: HumanAddr,
+        quest: u8,
+    },
+    /// Replaces the old password handshake. Use it to change the airdrop contract as well
+    RegisterAirdropContract {
+        contract: SecretContract,
+        entropy: String,
+    },
+    RemoveQuestContract {
+        address: HumanAddr,
+    },
+    CreateQuest {
+        quest: QuestDefinition,
+    },
+    EditQuest {
+        quest: QuestDefinition,
+    },
+    /// Upgrade the storage of an older version of the contract. Every other message fails until
+    /// the admin did it
+    Migrate {
+        /// Contracts the older version signaled quests for. Those it registered for quest 0 had
+        /// been removed, and are deleted. Only read when migrating from storage version 0
+        quest_contracts: Vec<HumanAddr>,
+    },
 }
 
 pub fn handle<S: Storage, A: Api, Q: Querier>(
@@ -49,21 +96,30 @@ pub fn handle<S: Storage, A: Api, Q: Querier>(
 ) -> StdResult<HandleResponse> {
     use HandleMsg::*;
 
+    if let Migrate { quest_contracts } = msg {
+        return migrate_storage(deps, env, quest_contracts);
+    }
+
+    require_current_version(&deps.storage)?;
     let config = Config::load(&deps.storage)?;
 
     match msg {
         CompleteQuest { address } => complete_quest(deps, env, config, address),
+        Receive {
+            from, amount, msg, ..
+        } => receive(deps, env, config, from, amount, msg),
+        PayPendingRewards { quest, limit } => pay_pending_rewards(deps, env, config, quest, limit),
         // admin commands
         ChangeConfig(new_config) => change_config(deps, env, config, new_config),
         RegisterQuestContract { address, quest } => {
             register_quest_contract(deps, env, config, address, quest)
         }
-        RegisterAirdropContract { contract } => {
-            register_airdrop_contract(deps, env, config, contract)
-        }
-        ChangeQuestWeight { quest, weight } => {
-            change_quest_weight(deps, env, config, quest, weight)
+        RegisterAirdropContract { contract, entropy } => {
+            register_airdrop_contract(deps, env, config, contract, entropy)
         }
+        CreateQuest { quest } => create_quest(deps, env, config, quest),
+        EditQuest { quest } => edit_quest(deps, env, config, quest),
         RemoveQuestContract { address } => remove_quest_contract(deps, env, config, address),
+        Migrate { .. } => unreachable!("storage migrations are handled before loading the config"),
     }
 }
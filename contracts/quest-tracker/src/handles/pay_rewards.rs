@@ -0,0 +1,273 @@
+use cosmwasm_std::{
+    log, to_binary, Api, CosmosMsg, Env, Extern, HandleResponse, Querier, StdResult, Storage,
+    Uint128,
+};
+
+use airdrop::{Deposit, LgndReceiveMsg};
+
+use crate::state::{Config, PendingRewards, QuestBudget, QuestStatus};
+
+/// Maximum amount of pending rewards paid in a single transaction
+pub const MAX_PENDING_PAYOUTS: u32 = 30;
+
+/// Deposits rewards in the platform for their recipients
+pub fn reward_msg(config: Config, deposits: Vec<Deposit>) -> StdResult<CosmosMsg> {
+    let amount = deposits.iter().map(|deposit| deposit.amount.u128()).sum();
+
+    secret_toolkit::snip20::send_msg_with_code_hash(
+        config.platform.address,
+        Some(config.platform.hash),
+        Uint128(amount),
+        Some(to_binary(&LgndReceiveMsg::BatchDeposit(deposits))?),
+        Some("complete quest".to_string()),
+        None,
+        64,
+        config.token.hash,
+        config.token.address,
+    )
+}
+
+/// Pays queued rewards of a quest in order, for as long as its budget allows
+pub fn pay_pending<S: Storage>(
+    store: &mut S,
+    quest: u8,
+    budget: &mut QuestBudget,
+    limit: u32,
+) -> StdResult<Vec<Deposit>> {
+    let mut deposits = vec![];
+
+    while (deposits.len() as u32) < limit {
+        match PendingRewards::peek(store, quest)? {
+            Some(reward) if budget.try_pay(reward.amount) => {
+                PendingRewards::pop(store, quest)?;
+                budget.owed = Uint128(budget.owed.u128() - reward.amount);
+
+                let mut status = QuestStatus::load(store, &reward.address)?;
+                status.rewarded += reward.amount;
+                status.save(store, &reward.address)?;
+
+                deposits.push(Deposit {
+                    to: reward.address,
+                    amount: Uint128(reward.amount),
+                });
+            }
+            _ => break,
+        }
+    }
+
+    Ok(deposits)
+}
+
+/// Pays queued rewards of a quest that has funds available. Anyone can call this
+pub fn pay_pending_rewards<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    _env: Env,
+    config: Config,
+    quest: u8,
+    limit: Option<u32>,
+) -> StdResult<HandleResponse> {
+    let limit = limit
+        .unwrap_or(MAX_PENDING_PAYOUTS)
+        .min(MAX_PENDING_PAYOUTS);
+
+    let mut budget = QuestBudget::load(&deps.storage, quest)?;
+    let deposits = pay_pending(&mut deps.storage, quest, &mut budget, limit)?;
+    budget.save(&mut deps.storage, quest)?;
+
+    let paid = deposits.len();
+    let mut messages = vec![];
+    if !deposits.is_empty() {
+        messages.push(reward_msg(config, deposits)?);
+    }
+
+    Ok(HandleResponse {
+        messages,
+        log: vec![log("paid_pending", paid)],
+        data: None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::handles::{handle, HandleMsg, ReceiveMsg};
+    use crate::queries::{query, QueryMsg, QuestBudgetResponse};
+    use crate::state::QuestReward;
+    use crate::unittest_utils::{
+        complete_quest_helper, create_quest_helper, extract_error_msg, fund_quest_helper,
+        init_helper, quest_definition, Deps,
+    };
+    use cosmwasm_std::testing::mock_env;
+    use cosmwasm_std::{from_binary, log, HumanAddr};
+
+    fn quest_budget(deps: &Deps, quest: u8) -> QuestBudgetResponse {
+        from_binary(&query(deps, QueryMsg::QuestBudget { quest }).unwrap()).unwrap()
+    }
+
+    fn rewarded(deps: &Deps, address: &str) -> u128 {
+        QuestStatus::load(&deps.storage, &HumanAddr::from(address))
+            .unwrap()
+            .rewarded
+    }
+
+    fn deposits_msg(deps: &Deps, deposits: &[(&str, u128)]) -> CosmosMsg {
+        let deposits = deposits
+            .iter()
+            .map(|(to, amount)| Deposit {
+                to: HumanAddr::from(*to),
+                amount: Uint128(*amount),
+            })
+            .collect();
+        reward_msg(Config::load(&deps.storage).unwrap(), deposits).unwrap()
+    }
+
+    #[test]
+    fn test_fund_quest() {
+        let mut deps = init_helper();
+        create_quest_helper(
+            &mut deps,
+            quest_definition(0, QuestReward::Fixed(Uint128(100))),
+        )
+        .unwrap();
+
+        let msg = HandleMsg::Receive {
+            sender: HumanAddr::from("alice"),
+            from: HumanAddr::from("alice"),
+            amount: Uint128(100),
+            msg: Some(to_binary(&ReceiveMsg::FundQuest { quest: 0 }).unwrap()),
+        };
+        let result = handle(&mut deps, mock_env("other_token", &[]), msg);
+        assert_eq!(
+            extract_error_msg(result),
+            "Only token can be sent to this contract"
+        );
+
+        // funds of quests that don't exist would be stuck
+        assert!(fund_quest_helper(&mut deps, 1, 100).is_err());
+
+        let response = fund_quest_helper(&mut deps, 0, 100).unwrap();
+        assert_eq!(
+            response.log,
+            vec![log("funded_quest", 0), log("paid_pending", 0)]
+        );
+        assert!(response.messages.is_empty());
+        assert_eq!(quest_budget(&deps, 0).available, Uint128(100));
+    }
+
+    #[test]
+    fn test_pending_rewards() {
+        let mut deps = init_helper();
+        create_quest_helper(
+            &mut deps,
+            quest_definition(0, QuestReward::Fixed(Uint128(100))),
+        )
+        .unwrap();
+        fund_quest_helper(&mut deps, 0, 150).unwrap();
+
+        let response = complete_quest_helper(&mut deps, 0, "alice", 0).unwrap();
+        assert_eq!(response.log, vec![log("completed", 0)]);
+        assert_eq!(
+            response.messages,
+            vec![deposits_msg(&deps, &[("alice", 100)])]
+        );
+
+        // the quest still completes once its budget ran out, the reward waits in line
+        for address in &["bob", "carol", "dave"] {
+            let response = complete_quest_helper(&mut deps, 0, address, 0).unwrap();
+            assert_eq!(
+                response.log,
+                vec![log("completed", 0), log("reward_queued", 100)]
+            );
+            assert!(response.messages.is_empty());
+        }
+        assert_eq!(rewarded(&deps, "bob"), 0);
+        assert_eq!(
+            quest_budget(&deps, 0),
+            QuestBudgetResponse {
+                funded: Uint128(150),
+                paid_out: Uint128(100),
+                owed: Uint128(300),
+                pending: 3,
+                available: Uint128(50),
+            }
+        );
+
+        let msg = HandleMsg::PayPendingRewards {
+            quest: 0,
+            limit: None,
+        };
+        let response = handle(&mut deps, mock_env("anyone", &[]), msg).unwrap();
+        assert_eq!(response.log, vec![log("paid_pending", 0)]);
+        assert!(response.messages.is_empty());
+
+        // funding pays the queue in order, as far as the budget goes
+        let response = fund_quest_helper(&mut deps, 0, 100).unwrap();
+        assert_eq!(
+            response.log,
+            vec![log("funded_quest", 0), log("paid_pending", 1)]
+        );
+        assert_eq!(
+            response.messages,
+            vec![deposits_msg(&deps, &[("bob", 100)])]
+        );
+        assert_eq!(rewarded(&deps, "bob"), 100);
+        assert_eq!(rewarded(&deps, "carol"), 0);
+
+        // completions don't jump the queue, even when the budget covers them
+        let mut budget = QuestBudget::load(&deps.storage, 0).unwrap();
+        budget.fund(150).unwrap();
+        budget.save(&mut deps.storage, 0).unwrap();
+        let response = complete_quest_helper(&mut deps, 0, "erin", 0).unwrap();
+        assert_eq!(
+            response.log,
+            vec![log("completed", 0), log("reward_queued", 100)]
+        );
+
+        let msg = HandleMsg::PayPendingRewards {
+            quest: 0,
+            limit: Some(1),
+        };
+        let response = handle(&mut deps, mock_env("anyone", &[]), msg).unwrap();
+        assert_eq!(response.log, vec![log("paid_pending", 1)]);
+        assert_eq!(
+            response.messages,
+            vec![deposits_msg(&deps, &[("carol", 100)])]
+        );
+
+        let msg = HandleMsg::PayPendingRewards {
+            quest: 0,
+            limit: None,
+        };
+        let response = handle(&mut deps, mock_env("anyone", &[]), msg).unwrap();
+        assert_eq!(response.log, vec![log("paid_pending", 1)]);
+        assert_eq!(
+            response.messages,
+            vec![deposits_msg(&deps, &[("dave", 100)])]
+        );
+
+        assert_eq!(
+            quest_budget(&deps, 0),
+            QuestBudgetResponse {
+                funded: Uint128(400),
+                paid_out: Uint128(400),
+                owed: Uint128(100),
+                pending: 1,
+                available: Uint128(0),
+            }
+        );
+        assert_eq!(rewarded(&deps, "erin"), 0);
+        // other quests have their own budget and queue
+        create_quest_helper(
+            &mut deps,
+            quest_definition(1, QuestReward::Fixed(Uint128(10))),
+        )
+        .unwrap();
+        let response = complete_quest_helper(&mut deps, 1, "erin", 0).unwrap();
+        assert_eq!(
+            response.log,
+            vec![log("completed", 1), log("reward_queued", 10)]
+        );
+        assert_eq!(quest_budget(&deps, 1).owed, Uint128(10));
+        assert_eq!(quest_budget(&deps, 0).pending, 1);
+    }
+}
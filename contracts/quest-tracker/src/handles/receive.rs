@@ -0,0 +1,65 @@
+use cosmwasm_std::{
+    from_binary, log, Api, Binary, Env, Extern, HandleResponse, HumanAddr, Querier, StdError,
+    StdResult, Storage, Uint128,
+};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::handles::pay_rewards::{pay_pending, reward_msg, MAX_PENDING_PAYOUTS};
+use crate::state::{Config, Quest, QuestBudget};
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ReceiveMsg {
+    /// Add the sent tokens to the reward budget of a quest
+    FundQuest { quest: u8 },
+}
+
+pub fn receive<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    config: Config,
+    _from: HumanAddr,
+    amount: Uint128,
+    msg: Option<Binary>,
+) -> StdResult<HandleResponse> {
+    if env.message.sender != config.token.address {
+        return Err(StdError::generic_err(format!(
+            "Only {} can be sent to this contract",
+            config.token.address
+        )));
+    }
+
+    let msg = msg.ok_or_else(|| StdError::generic_err("Receive requires a message"))?;
+    match from_binary(&msg)? {
+        ReceiveMsg::FundQuest { quest } => fund_quest(deps, config, quest, amount),
+    }
+}
+
+fn fund_quest<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    config: Config,
+    quest: u8,
+    amount: Uint128,
+) -> StdResult<HandleResponse> {
+    // make sure the funds don't end up in a budget no quest can use
+    Quest::load(&deps.storage, quest)?;
+
+    let mut budget = QuestBudget::load(&deps.storage, quest)?;
+    budget.fund(amount.u128())?;
+
+    let deposits = pay_pending(&mut deps.storage, quest, &mut budget, MAX_PENDING_PAYOUTS)?;
+    budget.save(&mut deps.storage, quest)?;
+
+    let paid = deposits.len();
+    let mut messages = vec![];
+    if !deposits.is_empty() {
+        messages.push(reward_msg(config, deposits)?);
+    }
+
+    Ok(HandleResponse {
+        messages,
+        log: vec![log("funded_quest", quest), log("paid_pending", paid)],
+        data: None,
+    })
+}
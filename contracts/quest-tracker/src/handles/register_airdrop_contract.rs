@@ -1,44 +1,141 @@
 use cosmwasm_std::{
-    log, to_binary, Api, CosmosMsg, Env, Extern, HandleResponse, Querier, StdResult, Storage,
-    WasmMsg,
+    log, to_binary, Api, Binary, CosmosMsg, Env, Extern, HandleResponse, Querier, StdResult,
+    Storage, WasmMsg,
 };
+use secret_toolkit::crypto::{sha_256, Prng};
 
-use airdrop::HandleMsg::SetQuestPassword;
+use airdrop::HandleMsg::SetQuerierKey;
 
 use crate::state::Config;
 use crate::types::secret_contract::SecretContract;
 
-/// Registers a contract that is allowed to signal quest success
-///
+/// Registers the airdrop contract that quest rewards are based on, and sets the key used to
+/// read allocations from it. This contract must already be an authorized querier of the
+/// airdrop contract
 pub fn register_airdrop_contract<S: Storage, A: Api, Q: Querier>(
     deps: &mut Extern<S, A, Q>,
     env: Env,
     mut config: Config,
     contract: SecretContract,
+    entropy: String,
 ) -> StdResult<HandleResponse> {
     config.assert_admin(&env.message.sender)?;
 
-    // todo: generate this
-    config.password = Some("hello".to_string());
-
+    let key = new_airdrop_key(&mut config, &env, entropy.as_bytes());
     config.airdrop_contract = Some(contract.clone());
 
     config.save(&mut deps.storage)?;
 
-    let msg = to_binary(&SetQuestPassword {
-        password: config.password.unwrap(),
-    })?;
-
-    let set_password_msg = CosmosMsg::Wasm(WasmMsg::Execute {
+    let set_key_msg = CosmosMsg::Wasm(WasmMsg::Execute {
         contract_addr: contract.address,
         callback_code_hash: contract.hash,
-        msg,
+        msg: to_binary(&SetQuerierKey { key })?,
         send: vec![],
     });
 
     Ok(HandleResponse {
-        messages: vec![set_password_msg],
-        log: vec![log("register quest contract", "success")],
+        messages: vec![set_key_msg],
+        log: vec![log("register airdrop contract", "success")],
         ..Default::default()
     })
 }
+
+/// Generates a random key from the PRNG of the config, and moves the PRNG forward
+fn new_airdrop_key(config: &mut Config, env: &Env, entropy: &[u8]) -> String {
+    let mut rng_entropy = Vec::with_capacity(16 + env.message.sender.len() + entropy.len());
+    rng_entropy.extend_from_slice(&env.block.height.to_be_bytes());
+    rng_entropy.extend_from_slice(&env.block.time.to_be_bytes());
+    rng_entropy.extend_from_slice(env.message.sender.0.as_bytes());
+    rng_entropy.extend_from_slice(entropy);
+
+    let mut rng = Prng::new(&config.prng_seed, &rng_entropy);
+    let rand_slice = rng.rand_bytes();
+    let key = Binary::from(&sha_256(&rand_slice)[..]).to_base64();
+
+    config.prng_seed = rand_slice.to_vec();
+    config.airdrop_key = Some(key.clone());
+    key
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::handles::pay_rewards::reward_msg;
+    use crate::handles::{handle, HandleMsg};
+    use crate::state::{QuestReward, QuestStatus};
+    use crate::unittest_utils::{
+        complete_quest_helper, contract, create_quest_helper, extract_error_msg, fund_quest_helper,
+        init_helper, quest_definition, register_airdrop_helper, AIRDROP,
+    };
+    use airdrop::Deposit;
+    use cosmwasm_std::testing::mock_env;
+    use cosmwasm_std::{log, HumanAddr, Uint128};
+
+    #[test]
+    fn test_register_airdrop_contract() {
+        let mut deps = init_helper();
+        let initial_config = Config::load(&deps.storage).unwrap();
+
+        let msg = HandleMsg::RegisterAirdropContract {
+            contract: contract(AIRDROP),
+            entropy: "entropy".to_string(),
+        };
+        let result = handle(&mut deps, mock_env("alice", &[]), msg);
+        assert_eq!(
+            extract_error_msg(result),
+            "Address alice is not allowed to perform this operation"
+        );
+
+        let response = register_airdrop_helper(&mut deps).unwrap();
+        let config = Config::load(&deps.storage).unwrap();
+        assert_eq!(config.airdrop_contract, Some(contract(AIRDROP)));
+        assert_ne!(config.prng_seed, initial_config.prng_seed);
+
+        // the key of the config is the one sent to the airdrop contract
+        let key = config.airdrop_key.clone().unwrap();
+        assert_eq!(
+            response.messages,
+            vec![CosmosMsg::Wasm(WasmMsg::Execute {
+                contract_addr: HumanAddr::from(AIRDROP),
+                callback_code_hash: format!("{}_hash", AIRDROP),
+                msg: to_binary(&SetQuerierKey { key: key.clone() }).unwrap(),
+                send: vec![],
+            })]
+        );
+
+        // registering again replaces the key
+        register_airdrop_helper(&mut deps).unwrap();
+        let config = Config::load(&deps.storage).unwrap();
+        assert_ne!(config.airdrop_key, Some(key));
+    }
+
+    #[test]
+    fn test_multiplier_reward_needs_airdrop_key() {
+        let mut deps = init_helper();
+        create_quest_helper(&mut deps, quest_definition(0, QuestReward::Multiplier(3))).unwrap();
+
+        let result = complete_quest_helper(&mut deps, 0, "alice", 0);
+        assert_eq!(
+            extract_error_msg(result),
+            "Cannot complete quest before airdrop contract is set up"
+        );
+
+        // the rewards are based on the allocations read with the key
+        register_airdrop_helper(&mut deps).unwrap();
+        fund_quest_helper(&mut deps, 0, 1_000).unwrap();
+        let response = complete_quest_helper(&mut deps, 0, "alice", 0).unwrap();
+        assert_eq!(response.log, vec![log("completed", 0)]);
+        let deposit = Deposit {
+            to: HumanAddr::from("alice"),
+            amount: Uint128(300),
+        };
+        let config = Config::load(&deps.storage).unwrap();
+        assert_eq!(
+            response.messages,
+            vec![reward_msg(config, vec![deposit]).unwrap()]
+        );
+
+        let status = QuestStatus::load(&deps.storage, &HumanAddr::from("alice")).unwrap();
+        assert_eq!(status.rewarded, 300);
+    }
+}
@@ -1,6 +1,6 @@
 use cosmwasm_std::{log, Api, Env, Extern, HandleResponse, HumanAddr, Querier, StdResult, Storage};
 
-use crate::state::{add_quest_contract, Config};
+use crate::state::{remove_quest_contract as remove_registration, Config};
 
 /// Remove a contract from quest signaling
 pub fn remove_quest_contract<S: Storage, A: Api, Q: Querier>(
@@ -11,7 +11,7 @@ pub fn remove_quest_contract<S: Storage, A: Api, Q: Querier>(
 ) -> StdResult<HandleResponse> {
     config.assert_admin(&env.message.sender)?;
 
-    add_quest_contract(&mut deps.storage, &address, 0u8)?;
+    remove_registration(&mut deps.storage, &address);
 
     Ok(HandleResponse {
         log: vec![log("change quest contract", "success")],
@@ -1,8 +1,12 @@
-use cosmwasm_std::{Api, Env, Extern, HumanAddr, InitResponse, Querier, StdResult, Storage};
+use cosmwasm_std::{
+    Api, Binary, Env, Extern, HumanAddr, InitResponse, Querier, StdResult, Storage,
+};
 use schemars::JsonSchema;
+use secret_toolkit::crypto::sha_256;
 use serde::Deserialize;
 
-use crate::state::{add_quest_contract, add_quest_weight, Config};
+use crate::migration::{save_version, STORAGE_VERSION};
+use crate::state::{add_quest_contract, Config, Quest, QuestDefinition};
 use crate::types::secret_contract::SecretContract;
 
 #[derive(Deserialize, Clone, Debug, PartialEq, JsonSchema)]
@@ -11,19 +15,17 @@ pub struct QuestContract {
     pub quest: u8,
 }
 
-#[derive(Deserialize, Clone, Debug, PartialEq, JsonSchema)]
-pub struct QuestWeight {
-    pub quest: u8,
-    pub weight: u8,
-}
-
 #[derive(Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 pub struct InitMsg {
     pub admin: Option<HumanAddr>,
     pub token: SecretContract,
     pub platform: SecretContract,
+    /// Airdrop round whose allocations are used as the base of quest rewards. Defaults to 0
+    pub airdrop_round: Option<u32>,
     pub quest_contracts: Vec<QuestContract>,
-    pub quest_weights: Vec<QuestWeight>,
+    pub quests: Vec<QuestDefinition>,
+    /// Seed of the PRNG that generates the key to query the airdrop contract with
+    pub prng_seed: Binary,
 }
 
 pub fn init<S: Storage, A: Api, Q: Querier>(
@@ -32,9 +34,16 @@ pub fn init<S: Storage, A: Api, Q: Querier>(
     msg: InitMsg,
 ) -> StdResult<InitResponse> {
     let admin = msg.admin.unwrap_or(env.message.sender);
-    let config = Config::new(admin, msg.token, msg.platform);
+    let config = Config::new(
+        admin,
+        msg.token,
+        msg.platform,
+        msg.airdrop_round.unwrap_or_default(),
+        sha_256(msg.prng_seed.as_slice()).to_vec(),
+    );
 
     config.save(&mut deps.storage)?;
+    save_version(&mut deps.storage, STORAGE_VERSION)?;
 
     for quest_contract in msg.quest_contracts {
         add_quest_contract(
@@ -44,12 +53,21 @@ pub fn init<S: Storage, A: Api, Q: Querier>(
         )?;
     }
 
-    for quest_weight in msg.quest_weights {
-        add_quest_weight(&mut deps.storage, quest_weight.quest, quest_weight.weight)?;
+    for quest in msg.quests {
+        Quest::new(quest)?.save(&mut deps.storage)?;
     }
 
+    // quest budgets are funded through `Receive`
+    let register_msg = secret_toolkit::snip20::register_receive_msg(
+        env.contract_code_hash,
+        None,
+        256,
+        config.token.hash,
+        config.token.address,
+    )?;
+
     Ok(InitResponse {
-        messages: vec![],
+        messages: vec![register_msg],
         log: vec![],
     })
 }
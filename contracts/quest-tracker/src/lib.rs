@@ -1,10 +1,13 @@
 mod handles;
 mod init;
+mod migration;
 mod queries;
 mod state;
 mod types;
+#[cfg(test)]
+mod unittest_utils;
 
-pub use handles::HandleMsg;
+pub use handles::{HandleMsg, ReceiveMsg};
 pub use init::InitMsg;
 pub use queries::QueryMsg;
 
@@ -0,0 +1,242 @@
+use cosmwasm_std::{HumanAddr, ReadonlyStorage, StdError, StdResult, Storage};
+use secret_toolkit::crypto::sha_256;
+use secret_toolkit::storage::{TypedStore, TypedStoreMut};
+use serde::{Deserialize, Serialize};
+
+use crate::state::{
+    get_quest_contract, remove_quest_contract, Config, Quest, QuestDefinition, QuestReward,
+};
+use crate::types::secret_contract::SecretContract;
+
+const KEY_STORAGE_VERSION: &[u8] = b"storage_version";
+
+/// Version of the storage layout this code works with. Contracts instantiated before quest
+/// definitions have no version stored, which is version 0
+pub const STORAGE_VERSION: u32 = 1;
+
+/// `Config` as stored before quest definitions
+#[derive(Serialize, Deserialize)]
+struct ConfigV0 {
+    admin: HumanAddr,
+    token: SecretContract,
+    platform: SecretContract,
+    airdrop_contract: Option<SecretContract>,
+    password: Option<String>,
+}
+
+impl ConfigV0 {
+    fn load<S: ReadonlyStorage>(store: &S) -> StdResult<Self> {
+        TypedStore::attach(store).load(b"config")
+    }
+}
+
+pub fn load_version<S: ReadonlyStorage>(storage: &S) -> StdResult<u32> {
+    Ok(TypedStore::attach(storage)
+        .may_load(KEY_STORAGE_VERSION)?
+        .unwrap_or_default())
+}
+
+pub fn save_version<S: Storage>(storage: &mut S, version: u32) -> StdResult<()> {
+    TypedStoreMut::attach(storage).store(KEY_STORAGE_VERSION, &version)
+}
+
+/// Handles (but `Migrate`) require the storage to be up to date, `Config::load` fails on an older
+/// layout
+pub fn require_current_version<S: ReadonlyStorage>(storage: &S) -> StdResult<()> {
+    let version = load_version(storage)?;
+    if version != STORAGE_VERSION {
+        return Err(StdError::generic_err(format!(
+            "storage is at version {}, expected {}. The admin has to migrate it first",
+            version, STORAGE_VERSION
+        )));
+    }
+
+    Ok(())
+}
+
+/// The admin, read with the layout of the stored version
+pub fn load_admin<S: ReadonlyStorage>(storage: &S) -> StdResult<HumanAddr> {
+    match load_version(storage)? {
+        0 => Ok(ConfigV0::load(storage)?.admin),
+        1 => Ok(Config::load(storage)?.admin),
+        version => Err(unknown_version(version)),
+    }
+}
+
+/// Upgrades the storage one version at a time up to `STORAGE_VERSION`. Returns the version it
+/// started from
+pub fn migrate<S: Storage>(storage: &mut S, quest_contracts: &[HumanAddr]) -> StdResult<u32> {
+    let from = load_version(storage)?;
+    if from > STORAGE_VERSION {
+        return Err(unknown_version(from));
+    }
+
+    for version in from..STORAGE_VERSION {
+        match version {
+            0 => migrate_v0_to_v1(storage, quest_contracts)?,
+            _ => return Err(unknown_version(version)),
+        }
+        save_version(storage, version + 1)?;
+    }
+
+    Ok(from)
+}
+
+fn unknown_version(version: u32) -> StdError {
+    StdError::generic_err(format!("can't migrate from storage version {}", version))
+}
+
+/// Quest weights were stored at the root, keyed by the quest id alone
+fn load_weight_v0<S: ReadonlyStorage>(storage: &S, quest: u8) -> StdResult<Option<u8>> {
+    let weight: Option<[u8; 1]> = TypedStore::attach(storage).may_load(&quest.to_be_bytes())?;
+    Ok(weight.map(u8::from_be_bytes))
+}
+
+/// Turns every quest weight into a quest that rewards the weight as a multiplier of the
+/// airdrop allocation, with no time window, cap or prerequisites.
+///
+/// The password is dropped: multiplier rewards wait until the admin registers the airdrop
+/// contract again with `RegisterAirdropContract`, which sets the key to query it with. The hash
+/// of the password seeds the PRNG that generates that key.
+///
+/// Contracts used to be removed by registering them for quest 0, which is a quest id like any
+/// other now. Those of the `quest_contracts` still mapped to 0 are deleted
+fn migrate_v0_to_v1<S: Storage>(storage: &mut S, quest_contracts: &[HumanAddr]) -> StdResult<()> {
+    let legacy = ConfigV0::load(storage)?;
+
+    let password = legacy.password.unwrap_or_default();
+    let prng_seed = sha_256(password.as_bytes()).to_vec();
+    let mut config = Config::new(legacy.admin, legacy.token, legacy.platform, 0, prng_seed);
+    config.airdrop_contract = legacy.airdrop_contract;
+    config.save(storage)?;
+
+    for id in 0..=u8::MAX {
+        if let Some(weight) = load_weight_v0(storage, id)? {
+            Quest::new(QuestDefinition {
+                id,
+                name: format!("Quest {}", id),
+                reward: QuestReward::Multiplier(weight as u32),
+                start_time: None,
+                end_time: None,
+                max_completions: None,
+                prerequisites: vec![],
+            })?
+            .save(storage)?;
+        }
+    }
+
+    for address in quest_contracts {
+        if get_quest_contract(storage, address) == Some(0) {
+            remove_quest_contract(storage, address);
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::handles::{handle, HandleMsg};
+    use crate::state::add_quest_contract;
+    use crate::unittest_utils::{contract, extract_error_msg};
+    use cosmwasm_std::testing::{mock_dependencies, mock_env, MockApi, MockQuerier, MockStorage};
+    use cosmwasm_std::{log, Extern, HandleResponse};
+
+    /// Storage as the older version left it: weights 2 and 5 for the quests 1 and 3, and a
+    /// contract it removed by registering it for quest 0
+    fn init_v0_helper() -> Extern<MockStorage, MockApi, MockQuerier> {
+        let mut deps = mock_dependencies(20, &[]);
+        let config = ConfigV0 {
+            admin: HumanAddr::from("admin"),
+            token: contract("token"),
+            platform: contract("platform"),
+            airdrop_contract: Some(contract("airdrop")),
+            password: Some("password".to_string()),
+        };
+        TypedStoreMut::attach(&mut deps.storage)
+            .store(b"config", &config)
+            .unwrap();
+        for (quest, weight) in &[(1_u8, 2_u8), (3, 5)] {
+            TypedStoreMut::attach(&mut deps.storage)
+                .store(&quest.to_be_bytes(), &[*weight])
+                .unwrap();
+        }
+
+        let storage = &mut deps.storage;
+        add_quest_contract(storage, &HumanAddr::from("removed"), 0).unwrap();
+        add_quest_contract(storage, &HumanAddr::from("quest_contract"), 1).unwrap();
+
+        deps
+    }
+
+    fn migrate_helper(
+        deps: &mut Extern<MockStorage, MockApi, MockQuerier>,
+        sender: &str,
+    ) -> StdResult<HandleResponse> {
+        let msg = HandleMsg::Migrate {
+            quest_contracts: vec![
+                HumanAddr::from("removed"),
+                HumanAddr::from("quest_contract"),
+            ],
+        };
+        handle(deps, mock_env(sender, &[]), msg)
+    }
+
+    #[test]
+    fn test_migrate_weights_to_quests() {
+        let mut deps = init_v0_helper();
+
+        let msg = HandleMsg::CompleteQuest {
+            address: HumanAddr::from("alice"),
+        };
+        let result = handle(&mut deps, mock_env("quest_contract", &[]), msg);
+        assert_eq!(
+            extract_error_msg(result),
+            "storage is at version 0, expected 1. The admin has to migrate it first"
+        );
+
+        let result = migrate_helper(&mut deps, "alice");
+        assert_eq!(
+            extract_error_msg(result),
+            "Address alice is not allowed to perform this operation"
+        );
+
+        let response = migrate_helper(&mut deps, "admin").unwrap();
+        assert_eq!(
+            response.log,
+            vec![log("migrated_from", 0), log("migrated_to", 1)]
+        );
+        assert_eq!(load_version(&deps.storage).unwrap(), 1);
+
+        let config = Config::load(&deps.storage).unwrap();
+        assert_eq!(config.admin, HumanAddr::from("admin"));
+        assert_eq!(config.airdrop_contract, Some(contract("airdrop")));
+        assert_eq!(config.airdrop_key, None);
+        assert_eq!(config.prng_seed, sha_256(b"password").to_vec());
+
+        for id in 0..=u8::MAX {
+            let reward = Quest::may_load(&deps.storage, id)
+                .unwrap()
+                .map(|quest| quest.definition.reward);
+            let expected = match id {
+                1 => Some(QuestReward::Multiplier(2)),
+                3 => Some(QuestReward::Multiplier(5)),
+                _ => None,
+            };
+            assert_eq!(reward, expected);
+        }
+
+        let quest_contract = HumanAddr::from("quest_contract");
+        assert_eq!(get_quest_contract(&deps.storage, &quest_contract), Some(1));
+        let removed = HumanAddr::from("removed");
+        assert_eq!(get_quest_contract(&deps.storage, &removed), None);
+
+        // migrating an up to date storage changes nothing
+        let response = migrate_helper(&mut deps, "admin").unwrap();
+        assert_eq!(
+            response.log,
+            vec![log("migrated_from", 1), log("migrated_to", 1)]
+        );
+    }
+}
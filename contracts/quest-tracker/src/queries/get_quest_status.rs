@@ -1,12 +1,15 @@
 use crate::queries::QuestStatusResponse;
-use crate::state::get_quest_status;
-use cosmwasm_std::{Api, Extern, HumanAddr, Querier, StdResult, Storage};
+use crate::state::QuestStatus;
+use cosmwasm_std::{Api, Extern, HumanAddr, Querier, StdResult, Storage, Uint128};
 
 pub fn query_get_quest_status<S: Storage, A: Api, Q: Querier>(
     deps: &Extern<S, A, Q>,
     address: HumanAddr,
 ) -> StdResult<QuestStatusResponse> {
-    let quest = get_quest_status(&deps.storage, &address);
+    let status = QuestStatus::load(&deps.storage, &address)?;
 
-    Ok(QuestStatusResponse { quest })
+    Ok(QuestStatusResponse {
+        completed: status.completed_quests(),
+        rewarded: Uint128(status.rewarded),
+    })
 }
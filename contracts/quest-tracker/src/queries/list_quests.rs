@@ -0,0 +1,23 @@
+use crate::queries::ListQuestsResponse;
+use crate::state::{quest_ids, Quest};
+use cosmwasm_std::{Api, Extern, Querier, StdResult, Storage};
+
+const DEFAULT_LIMIT: u8 = 10;
+const MAX_LIMIT: u8 = 30;
+
+pub fn query_list_quests<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    start_after: Option<u8>,
+    limit: Option<u8>,
+) -> StdResult<ListQuestsResponse> {
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+
+    let quests = quest_ids(&deps.storage)?
+        .into_iter()
+        .filter(|id| start_after.is_none_or(|start_after| *id > start_after))
+        .take(limit)
+        .map(|id| Quest::load(&deps.storage, id))
+        .collect::<StdResult<Vec<_>>>()?;
+
+    Ok(ListQuestsResponse { quests })
+}
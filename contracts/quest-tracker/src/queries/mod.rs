@@ -1,21 +1,57 @@
 mod get_quest_status;
+mod list_quests;
+mod quest_budget;
 
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
-use cosmwasm_std::{to_binary, Api, Binary, Extern, HumanAddr, Querier, StdResult, Storage};
+use cosmwasm_std::{
+    to_binary, Api, Binary, Extern, HumanAddr, Querier, StdResult, Storage, Uint128,
+};
 
 use crate::queries::get_quest_status::query_get_quest_status;
+use crate::queries::list_quests::query_list_quests;
+use crate::queries::quest_budget::query_quest_budget;
+use crate::state::Quest;
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 #[serde(rename_all = "snake_case")]
 pub enum QueryMsg {
-    GetQuestStatus { address: HumanAddr },
+    GetQuestStatus {
+        address: HumanAddr,
+    },
+    /// Quests in ascending order of id
+    ListQuests {
+        start_after: Option<u8>,
+        limit: Option<u8>,
+    },
+    QuestBudget {
+        quest: u8,
+    },
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 pub struct QuestStatusResponse {
-    quest: u8,
+    /// Ids of all the quests the address completed
+    pub completed: Vec<u8>,
+    /// Total amount the address was rewarded for completing quests
+    pub rewarded: Uint128,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct ListQuestsResponse {
+    pub quests: Vec<Quest>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct QuestBudgetResponse {
+    pub funded: Uint128,
+    pub paid_out: Uint128,
+    /// Rewards of completions that are waiting for more funds
+    pub owed: Uint128,
+    /// Amount of completions waiting for more funds
+    pub pending: u32,
+    pub available: Uint128,
 }
 
 pub fn query<S: Storage, A: Api, Q: Querier>(
@@ -24,5 +60,9 @@ pub fn query<S: Storage, A: Api, Q: Querier>(
 ) -> StdResult<Binary> {
     match msg {
         QueryMsg::GetQuestStatus { address } => to_binary(&query_get_quest_status(deps, address)?),
+        QueryMsg::ListQuests { start_after, limit } => {
+            to_binary(&query_list_quests(deps, start_after, limit)?)
+        }
+        QueryMsg::QuestBudget { quest } => to_binary(&query_quest_budget(deps, quest)?),
     }
 }
@@ -0,0 +1,18 @@
+use crate::queries::QuestBudgetResponse;
+use crate::state::{PendingRewards, QuestBudget};
+use cosmwasm_std::{Api, Extern, Querier, StdResult, Storage, Uint128};
+
+pub fn query_quest_budget<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    quest: u8,
+) -> StdResult<QuestBudgetResponse> {
+    let budget = QuestBudget::load(&deps.storage, quest)?;
+
+    Ok(QuestBudgetResponse {
+        available: Uint128(budget.available()),
+        pending: PendingRewards::len(&deps.storage, quest)?,
+        funded: budget.funded,
+        paid_out: budget.paid_out,
+        owed: budget.owed,
+    })
+}
@@ -2,13 +2,18 @@ use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
 use crate::types::secret_contract::SecretContract;
-use cosmwasm_std::{HumanAddr, ReadonlyStorage, StdError, StdResult, Storage};
+use cosmwasm_std::{HumanAddr, ReadonlyStorage, StdError, StdResult, Storage, Uint128};
 
 use cosmwasm_storage::{PrefixedStorage, ReadonlyPrefixedStorage};
-use secret_toolkit::storage::{TypedStore, TypedStoreMut};
+use secret_toolkit::storage::{DequeStore, DequeStoreMut, TypedStore, TypedStoreMut};
 
 const QUEST_CONTRACT: &[u8] = b"quest_contract";
 const QUEST_STATUS: &[u8] = b"quest_status";
+const QUEST_COMPLETIONS: &[u8] = b"quest_completions";
+const QUESTS: &[u8] = b"quests";
+const QUEST_IDS: &[u8] = b"quest_ids";
+const QUEST_BUDGETS: &[u8] = b"quest_budgets";
+const PENDING_REWARDS: &[u8] = b"pending_rewards";
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 pub struct Config {
@@ -16,19 +21,32 @@ pub struct Config {
     pub token: SecretContract,
     pub platform: SecretContract,
     pub airdrop_contract: Option<SecretContract>,
-    pub password: Option<String>,
+    /// Airdrop round whose allocations are used as the base of quest rewards
+    pub airdrop_round: u32,
+    /// Key used to authenticate allocation queries to the airdrop contract
+    pub airdrop_key: Option<String>,
+    /// Seed of the PRNG that generates `airdrop_key`
+    pub prng_seed: Vec<u8>,
 }
 
 impl Config {
     const STORE_KEY: &'static [u8] = b"config";
 
-    pub fn new(admin: HumanAddr, token: SecretContract, platform: SecretContract) -> Self {
+    pub fn new(
+        admin: HumanAddr,
+        token: SecretContract,
+        platform: SecretContract,
+        airdrop_round: u32,
+        prng_seed: Vec<u8>,
+    ) -> Self {
         Self {
             admin,
             token,
             platform,
             airdrop_contract: None,
-            password: None,
+            airdrop_round,
+            airdrop_key: None,
+            prng_seed,
         }
     }
 
@@ -61,44 +79,327 @@ pub fn add_quest_contract<S: Storage>(
     typed_store.store(address_to_bytes(address), &quest.to_be_bytes())
 }
 
-pub fn get_quest_contract<S: ReadonlyStorage>(store: &S, address: &HumanAddr) -> u8 {
+pub fn remove_quest_contract<S: Storage>(store: &mut S, address: &HumanAddr) {
+    let mut store = PrefixedStorage::new(QUEST_CONTRACT, store);
+    let mut typed_store = TypedStoreMut::<[u8; 1], _>::attach(&mut store);
+    typed_store.remove(address_to_bytes(address))
+}
+
+/// The quest a contract signals, or None if it is not a quest contract
+pub fn get_quest_contract<S: ReadonlyStorage>(store: &S, address: &HumanAddr) -> Option<u8> {
     let store = ReadonlyPrefixedStorage::new(QUEST_CONTRACT, store);
     let typed_store = TypedStore::attach(&store);
     let result = typed_store.may_load(address_to_bytes(address));
 
-    result.unwrap_or(None).unwrap_or(0)
+    result.unwrap_or(None).map(u8::from_be_bytes)
 }
 
-pub fn add_quest_weight<S: Storage>(store: &mut S, quest: u8, weight: u8) -> StdResult<()> {
-    let mut typed_store = TypedStoreMut::attach(store);
-    typed_store.store(&quest.to_be_bytes(), &weight.to_be_bytes())
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum QuestReward {
+    /// Multiple of the address's airdrop allocation
+    Multiplier(u32),
+    /// Fixed amount of LGND
+    Fixed(Uint128),
 }
 
-pub fn get_quest_weight<S: ReadonlyStorage>(store: &S, quest: u8) -> u8 {
-    let typed_store = TypedStore::attach(store);
-    let result = typed_store.may_load(&quest.to_be_bytes());
+/// A quest as set by the admin
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct QuestDefinition {
+    pub id: u8,
+    pub name: String,
+    pub reward: QuestReward,
+    pub start_time: Option<u64>,
+    pub end_time: Option<u64>,
+    /// Maximum amount of times the quest can be completed, across all addresses
+    pub max_completions: Option<u32>,
+    /// Quests that must be completed before this one
+    pub prerequisites: Vec<u8>,
+}
 
-    result.unwrap_or(None).unwrap_or(0)
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct Quest {
+    pub definition: QuestDefinition,
+    pub completions: u32,
 }
 
-pub fn set_quest_status<S: Storage>(
-    store: &mut S,
-    address: &HumanAddr,
-    quest: u8,
-) -> StdResult<()> {
-    let mut store = PrefixedStorage::new(QUEST_STATUS, store);
-    let mut typed_store = TypedStoreMut::attach(&mut store);
-    typed_store.store(address_to_bytes(address), &quest.to_be_bytes())
+impl Quest {
+    pub fn new(definition: QuestDefinition) -> StdResult<Self> {
+        let quest = Self {
+            definition,
+            completions: 0,
+        };
+        quest.validate()?;
+        Ok(quest)
+    }
+
+    pub fn load<S: ReadonlyStorage>(store: &S, id: u8) -> StdResult<Self> {
+        Self::may_load(store, id)?
+            .ok_or_else(|| StdError::generic_err(format!("Quest {} does not exist", id)))
+    }
+
+    pub fn may_load<S: ReadonlyStorage>(store: &S, id: u8) -> StdResult<Option<Self>> {
+        let store = ReadonlyPrefixedStorage::new(QUESTS, store);
+        TypedStore::attach(&store).may_load(&id.to_be_bytes())
+    }
+
+    pub fn save<S: Storage>(&self, store: &mut S) -> StdResult<()> {
+        let id = self.definition.id;
+
+        let mut ids = quest_ids(store)?;
+        if let Err(index) = ids.binary_search(&id) {
+            ids.insert(index, id);
+            TypedStoreMut::attach(store).store(QUEST_IDS, &ids)?;
+        }
+
+        let mut store = PrefixedStorage::new(QUESTS, store);
+        TypedStoreMut::attach(&mut store).store(&id.to_be_bytes(), self)
+    }
+
+    fn validate(&self) -> StdResult<()> {
+        let definition = &self.definition;
+        if let (Some(start_time), Some(end_time)) = (definition.start_time, definition.end_time) {
+            if end_time <= start_time {
+                return Err(StdError::generic_err("Quest must end after it starts"));
+            }
+        }
+        if definition.prerequisites.contains(&definition.id) {
+            return Err(StdError::generic_err(
+                "Quest can not be a prerequisite of itself",
+            ));
+        }
+        Ok(())
+    }
+
+    /// Check that the quest can be completed at `time` by an address with `status`. Returns why
+    /// it can't otherwise
+    pub fn check_can_complete(&self, time: u64, status: &QuestStatus) -> Result<(), String> {
+        let definition = &self.definition;
+        if definition.start_time.is_some_and(|start| time < start) {
+            return Err(format!("Quest {} has not started yet", definition.id));
+        }
+        if definition.end_time.is_some_and(|end| time >= end) {
+            return Err(format!("Quest {} has ended", definition.id));
+        }
+        if definition
+            .max_completions
+            .is_some_and(|max| self.completions >= max)
+        {
+            return Err(format!(
+                "Quest {} reached its maximum completions",
+                definition.id
+            ));
+        }
+        if let Some(missing) = definition
+            .prerequisites
+            .iter()
+            .find(|quest| !status.is_completed(**quest))
+        {
+            return Err(format!("Quest {} must be completed first", missing));
+        }
+        Ok(())
+    }
 }
 
-pub fn get_quest_status<S: ReadonlyStorage>(store: &S, address: &HumanAddr) -> u8 {
-    let store = ReadonlyPrefixedStorage::new(QUEST_STATUS, store);
-    let typed_store = TypedStore::attach(&store);
-    let result = typed_store.may_load(address_to_bytes(address));
+/// Ids of all the defined quests, in ascending order
+pub fn quest_ids<S: ReadonlyStorage>(store: &S) -> StdResult<Vec<u8>> {
+    TypedStore::attach(store)
+        .may_load(QUEST_IDS)
+        .map(Option::unwrap_or_default)
+}
+
+/// Funds deposited for the rewards of a quest, and what was done with them
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, JsonSchema)]
+pub struct QuestBudget {
+    /// Total amount deposited for the quest's rewards
+    pub funded: Uint128,
+    /// Total amount sent to the platform for completions
+    pub paid_out: Uint128,
+    /// Rewards of completions that are waiting for more funds
+    pub owed: Uint128,
+}
+
+impl QuestBudget {
+    pub fn load<S: ReadonlyStorage>(store: &S, quest: u8) -> StdResult<Self> {
+        let store = ReadonlyPrefixedStorage::new(QUEST_BUDGETS, store);
+        TypedStore::attach(&store)
+            .may_load(&quest.to_be_bytes())
+            .map(Option::unwrap_or_default)
+    }
+
+    pub fn save<S: Storage>(&self, store: &mut S, quest: u8) -> StdResult<()> {
+        let mut store = PrefixedStorage::new(QUEST_BUDGETS, store);
+        TypedStoreMut::attach(&mut store).store(&quest.to_be_bytes(), self)
+    }
+
+    pub fn available(&self) -> u128 {
+        self.funded.u128() - self.paid_out.u128()
+    }
+
+    pub fn fund(&mut self, amount: u128) -> StdResult<()> {
+        self.funded = Uint128(
+            self.funded
+                .u128()
+                .checked_add(amount)
+                .ok_or_else(|| StdError::generic_err("Quest budget exceeded u128::MAX"))?,
+        );
+        Ok(())
+    }
+
+    /// Pay `amount` out of the budget, if there is enough left
+    pub fn try_pay(&mut self, amount: u128) -> bool {
+        if amount > self.available() {
+            return false;
+        }
+        self.paid_out = Uint128(self.paid_out.u128() + amount);
+        true
+    }
+}
+
+/// A reward that could not be paid when the quest was completed because the budget ran out
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct PendingReward {
+    pub address: HumanAddr,
+    pub amount: u128,
+}
+
+pub struct PendingRewards;
+
+impl PendingRewards {
+    pub fn len<S: ReadonlyStorage>(store: &S, quest: u8) -> StdResult<u32> {
+        let store = ReadonlyPrefixedStorage::multilevel(&[PENDING_REWARDS, &[quest]], store);
+        match DequeStore::<PendingReward, _>::attach(&store) {
+            None => Ok(0),
+            Some(rewards) => Ok(rewards?.len()),
+        }
+    }
+
+    pub fn peek<S: ReadonlyStorage>(store: &S, quest: u8) -> StdResult<Option<PendingReward>> {
+        let store = ReadonlyPrefixedStorage::multilevel(&[PENDING_REWARDS, &[quest]], store);
+        let rewards = match DequeStore::<PendingReward, _>::attach(&store) {
+            None => return Ok(None),
+            Some(rewards) => rewards?,
+        };
+
+        rewards.iter().next().transpose()
+    }
+
+    pub fn pop<S: Storage>(store: &mut S, quest: u8) -> StdResult<PendingReward> {
+        let mut store = PrefixedStorage::multilevel(&[PENDING_REWARDS, &[quest]], store);
+        DequeStoreMut::attach_or_create(&mut store)?.pop_front()
+    }
+
+    pub fn push<S: Storage>(store: &mut S, quest: u8, reward: &PendingReward) -> StdResult<()> {
+        let mut store = PrefixedStorage::multilevel(&[PENDING_REWARDS, &[quest]], store);
+        DequeStoreMut::attach_or_create(&mut store)?.push_back(reward)
+    }
+}
+
+/// The quests an address completed, and how much it was rewarded for them
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct QuestStatus {
+    /// Bit `n` is set once quest `n` was completed
+    completed: [u64; 4],
+    pub rewarded: u128,
+}
+
+impl QuestStatus {
+    pub fn load<S: ReadonlyStorage>(store: &S, address: &HumanAddr) -> StdResult<Self> {
+        let completions = ReadonlyPrefixedStorage::new(QUEST_COMPLETIONS, store);
+        if let Some(status) =
+            TypedStore::attach(&completions).may_load(address_to_bytes(address))?
+        {
+            return Ok(status);
+        }
 
-    result.unwrap_or(None).unwrap_or(0)
+        // addresses that completed a quest before completions were tracked per quest. The older
+        // version stored 0 for no quest
+        let mut status = Self::default();
+        let legacy = ReadonlyPrefixedStorage::new(QUEST_STATUS, store);
+        let legacy: Option<[u8; 1]> =
+            TypedStore::attach(&legacy).may_load(address_to_bytes(address))?;
+        if let Some([quest]) = legacy {
+            if quest != 0 {
+                status.set_completed(quest);
+            }
+        }
+
+        Ok(status)
+    }
+
+    pub fn save<S: Storage>(&self, store: &mut S, address: &HumanAddr) -> StdResult<()> {
+        let mut store = PrefixedStorage::new(QUEST_COMPLETIONS, store);
+        TypedStoreMut::attach(&mut store).store(address_to_bytes(address), self)
+    }
+
+    pub fn is_completed(&self, quest: u8) -> bool {
+        self.completed[quest as usize / 64] & (1 << (quest % 64)) != 0
+    }
+
+    pub fn set_completed(&mut self, quest: u8) {
+        self.completed[quest as usize / 64] |= 1 << (quest % 64);
+    }
+
+    pub fn completed_quests(&self) -> Vec<u8> {
+        (0..=u8::MAX)
+            .filter(|quest| self.is_completed(*quest))
+            .collect()
+    }
 }
 
 pub fn address_to_bytes(address: &HumanAddr) -> &[u8] {
     address.0.as_bytes()
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cosmwasm_std::testing::MockStorage;
+
+    #[test]
+    fn test_quest_status_bitmap() {
+        let mut status = QuestStatus::default();
+        assert_eq!(status.completed_quests(), Vec::<u8>::new());
+
+        // quests on both sides of every word boundary
+        for quest in &[0, 63, 64, 127, 128, 255] {
+            status.set_completed(*quest);
+        }
+        status.set_completed(64);
+
+        assert_eq!(status.completed_quests(), vec![0, 63, 64, 127, 128, 255]);
+        assert!(status.is_completed(127));
+        assert!(!status.is_completed(1));
+        assert!(!status.is_completed(62));
+        assert!(!status.is_completed(65));
+        assert!(!status.is_completed(254));
+    }
+
+    #[test]
+    fn test_quest_status_legacy_fallback() {
+        let mut store = MockStorage::default();
+        let alice = HumanAddr::from("alice");
+        let bob = HumanAddr::from("bob");
+
+        let mut legacy = PrefixedStorage::new(QUEST_STATUS, &mut store);
+        let mut legacy = TypedStoreMut::attach(&mut legacy);
+        legacy.store(address_to_bytes(&alice), &[3_u8]).unwrap();
+        legacy.store(address_to_bytes(&bob), &[0_u8]).unwrap();
+
+        // 0 meant no quest in the older version
+        let status = QuestStatus::load(&store, &bob).unwrap();
+        assert_eq!(status, QuestStatus::default());
+
+        let mut status = QuestStatus::load(&store, &alice).unwrap();
+        assert_eq!(status.completed_quests(), vec![3]);
+        assert_eq!(status.rewarded, 0);
+
+        // once saved, the legacy status is no longer read
+        status.set_completed(1);
+        status.rewarded = 50;
+        status.save(&mut store, &alice).unwrap();
+        let status = QuestStatus::load(&store, &alice).unwrap();
+        assert_eq!(status.completed_quests(), vec![1, 3]);
+        assert_eq!(status.rewarded, 50);
+    }
+}
@@ -0,0 +1,213 @@
+use cosmwasm_std::testing::{
+    mock_dependencies, mock_env, MockApi, MockQuerier, MockStorage, MOCK_CONTRACT_ADDR,
+};
+use cosmwasm_std::{
+    from_binary, from_slice, to_binary, Binary, CosmosMsg, Empty, Env, Extern, HandleResponse,
+    HumanAddr, Querier, QuerierResult, QueryRequest, StdError, StdResult, SystemError, Uint128,
+    WasmMsg, WasmQuery,
+};
+
+use crate::handles::{handle, HandleMsg, ReceiveMsg};
+use crate::init::{init, InitMsg, QuestContract};
+use crate::state::{QuestDefinition, QuestReward};
+use crate::types::secret_contract::SecretContract;
+
+pub const AIRDROP: &str = "airdrop";
+
+/// Answers the smart queries to `AIRDROP` from a real airdrop contract, so multiplier rewards
+/// go through the querier authentication of the airdrop
+pub struct AirdropQuerier {
+    pub airdrop: Extern<MockStorage, MockApi, MockQuerier>,
+}
+
+impl Querier for AirdropQuerier {
+    fn raw_query(&self, bin_request: &[u8]) -> QuerierResult {
+        match from_slice(bin_request) {
+            Ok(QueryRequest::<Empty>::Wasm(WasmQuery::Smart {
+                contract_addr, msg, ..
+            })) if contract_addr.0 == AIRDROP => {
+                Ok(from_binary(&msg).and_then(|msg| airdrop::query(&self.airdrop, msg)))
+            }
+            _ => Err(SystemError::UnsupportedRequest {
+                kind: "only smart queries to the airdrop contract are supported".to_string(),
+            }),
+        }
+    }
+}
+
+pub type Deps = Extern<MockStorage, MockApi, AirdropQuerier>;
+
+pub fn contract(address: &str) -> SecretContract {
+    SecretContract {
+        address: HumanAddr::from(address),
+        hash: format!("{}_hash", address),
+    }
+}
+
+pub fn mock_env_with_time(sender: &str, time: u64) -> Env {
+    let mut env = mock_env(sender, &[]);
+    env.block.time = time;
+    env
+}
+
+/// An airdrop whose round 0 allocated 100 to alice and 200 to bob, and that lets this contract
+/// read allocations once it set its key
+fn airdrop_helper() -> Extern<MockStorage, MockApi, MockQuerier> {
+    let mut airdrop = mock_dependencies(20, &[]);
+    let init_msg = from_slice(
+        format!(
+            r#"{{
+                "admin": "admin",
+                "platform": {{ "address": "platform", "hash": "platform_hash" }},
+                "token": {{ "address": "token", "hash": "token_hash" }},
+                "queriers": ["{}"]
+            }}"#,
+            MOCK_CONTRACT_ADDR
+        )
+        .as_bytes(),
+    )
+    .unwrap();
+    airdrop::init(&mut airdrop, mock_env("admin", &[]), init_msg).unwrap();
+
+    let create_round = airdrop::HandleMsg::CreateRound {
+        budget: Uint128(1_000),
+        start_time: 0,
+        end_time: 100,
+        merkle_root: None,
+        vesting: None,
+    };
+    airdrop::handle(&mut airdrop, mock_env_with_time("admin", 0), create_round).unwrap();
+
+    let confirm_airdrop = from_slice(
+        br#"{ "confirm_airdrop": { "round": 0, "airdrops": [
+            { "address": "alice", "to": "alice", "amount": "100" },
+            { "address": "bob", "to": "bob", "amount": "200" }
+        ] } }"#,
+    )
+    .unwrap();
+    airdrop::handle(
+        &mut airdrop,
+        mock_env_with_time("admin", 10),
+        confirm_airdrop,
+    )
+    .unwrap();
+
+    airdrop
+}
+
+/// Registers `quest_contract_<n>` for the quests 0 to 2, without creating any quest
+pub fn init_helper() -> Deps {
+    let mut deps = Extern {
+        storage: MockStorage::default(),
+        api: MockApi::new(20),
+        querier: AirdropQuerier {
+            airdrop: airdrop_helper(),
+        },
+    };
+
+    let msg = InitMsg {
+        admin: Some(HumanAddr::from("admin")),
+        token: contract("token"),
+        platform: contract("platform"),
+        airdrop_round: None,
+        quest_contracts: (0..3)
+            .map(|quest| QuestContract {
+                contract: quest_contract(quest),
+                quest,
+            })
+            .collect(),
+        quests: vec![],
+        prng_seed: Binary::from(b"seed".to_vec()),
+    };
+    init(&mut deps, mock_env("admin", &[]), msg).unwrap();
+
+    deps
+}
+
+pub fn quest_contract(quest: u8) -> HumanAddr {
+    HumanAddr(format!("quest_contract_{}", quest))
+}
+
+/// A quest without a time window, completion cap or prerequisites
+pub fn quest_definition(id: u8, reward: QuestReward) -> QuestDefinition {
+    QuestDefinition {
+        id,
+        name: format!("Quest {}", id),
+        reward,
+        start_time: None,
+        end_time: None,
+        max_completions: None,
+        prerequisites: vec![],
+    }
+}
+
+pub fn create_quest_helper(deps: &mut Deps, quest: QuestDefinition) -> StdResult<HandleResponse> {
+    handle(
+        deps,
+        mock_env("admin", &[]),
+        HandleMsg::CreateQuest { quest },
+    )
+}
+
+/// Registers the airdrop contract, and forwards the querier key it sets to the airdrop
+pub fn register_airdrop_helper(deps: &mut Deps) -> StdResult<HandleResponse> {
+    let msg = HandleMsg::RegisterAirdropContract {
+        contract: contract(AIRDROP),
+        entropy: "entropy".to_string(),
+    };
+    let response = handle(deps, mock_env("admin", &[]), msg)?;
+
+    match &response.messages[0] {
+        CosmosMsg::Wasm(WasmMsg::Execute { msg, .. }) => {
+            let msg = from_binary(msg)?;
+            airdrop::handle(
+                &mut deps.querier.airdrop,
+                mock_env(MOCK_CONTRACT_ADDR, &[]),
+                msg,
+            )?;
+        }
+        msg => {
+            return Err(StdError::generic_err(format!(
+                "unexpected message {:?}",
+                msg
+            )))
+        }
+    }
+
+    Ok(response)
+}
+
+/// Signals from the contract of `quest` that `address` completed it
+pub fn complete_quest_helper(
+    deps: &mut Deps,
+    quest: u8,
+    address: &str,
+    time: u64,
+) -> StdResult<HandleResponse> {
+    let msg = HandleMsg::CompleteQuest {
+        address: HumanAddr::from(address),
+    };
+    handle(
+        deps,
+        mock_env_with_time(quest_contract(quest).as_str(), time),
+        msg,
+    )
+}
+
+pub fn fund_quest_helper(deps: &mut Deps, quest: u8, amount: u128) -> StdResult<HandleResponse> {
+    let msg = HandleMsg::Receive {
+        sender: HumanAddr::from("funder"),
+        from: HumanAddr::from("funder"),
+        amount: Uint128(amount),
+        msg: Some(to_binary(&ReceiveMsg::FundQuest { quest })?),
+    };
+    handle(deps, mock_env("token", &[]), msg)
+}
+
+pub fn extract_error_msg<T>(result: StdResult<T>) -> String {
+    match result {
+        Err(StdError::GenericErr { msg, .. }) => msg,
+        Err(err) => panic!("unexpected error: {:?}", err),
+        Ok(_) => panic!("expected an error"),
+    }
+}
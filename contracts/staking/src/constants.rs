@@ -1,3 +1,5 @@
 pub const RESPONSE_BLOCK_SIZE: usize = 256;
 pub const REWARD_SCALE: u128 = 1_000_000_000_000_000_000; // 10 ^ 18
 pub const MULTIPLIER_SCALE: u128 = 100_000; // 10 ^ 5
+pub const MAX_REWARD_TOKENS: usize = 5;
+pub const MAX_DELEGATIONS: usize = 10;
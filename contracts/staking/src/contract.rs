@@ -18,14 +18,20 @@ use secret_toolkit::utils::{pad_handle_result, pad_query_result};
 use secret_toolkit::viewing_key::{ViewingKey, ViewingKeyStore};
 
 use crate::constants::*;
+use crate::migration::{
+    load_admin, load_version, migrate, require_current_version, save_version, STORAGE_VERSION,
+};
 use crate::msg::ResponseStatus::{NotChanged, Success};
 use crate::msg::{
-    HandleAnswer, HandleMsg, InitMsg, QueryAnswer, QueryMsg, QueryWithPermit,
-    ReceiveFromPlatformMsg, ReceiveMsg, SubscriberMsg,
+    HandleAnswer, HandleMsg, InitMsg, PayerDelegation, QueryAnswer, QueryMsg, QueryWithPermit,
+    ReceiveFromPlatformMsg, ReceiveMsg, ResponseStatus, RewardDestination, RewardTokenInfo,
+    SubscriberMsg, TokenRewards,
 };
 use crate::state::{
-    BoosterItem, BoosterItemInInventory, Config, Features, InflationSchedule, MultiplierContracts,
-    RewardPool, ScheduleUnit, Subscribers, UserBalance, PREFIX_REVOKED_PERMITS,
+    AutoCompound, BoosterItem, BoosterItemInInventory, Config, Delegations, Features,
+    InflationSchedule, LockPositions, LockTier, LockTiers, MultiplierContracts, PayerDelegations,
+    RemovedRewardToken, RewardPool, RewardToken, RewardTokenDebts, RewardTokens, ScheduleUnit,
+    Subscribers, UserBalance, PREFIX_REVOKED_PERMITS,
 };
 
 pub fn init<S: Storage, A: Api, Q: Querier>(
@@ -71,6 +77,7 @@ pub fn init<S: Storage, A: Api, Q: Querier>(
             .collect(),
     )?;
     Subscribers::save(&mut deps.storage, msg.subscribers.unwrap_or_default())?;
+    save_version(&mut deps.storage, STORAGE_VERSION)?;
 
     if let Some(multiplier_contracts) = msg.multiplier_contracts {
         MultiplierContracts::add_multiple(&mut deps.storage, multiplier_contracts)?;
@@ -125,13 +132,26 @@ pub fn handle<S: Storage, A: Api, Q: Querier>(
     env: Env,
     msg: HandleMsg,
 ) -> HandleResult {
+    if !matches!(msg, HandleMsg::Migrate {}) {
+        require_current_version(&deps.storage)?;
+    }
+
     let response = match msg {
         // yo dawg
         HandleMsg::Receive {
             from, amount, msg, ..
         } => match msg.inner {
             ReceiveMsg::ReceiveFromPlatform { from: to, msg } => match msg.inner {
-                ReceiveFromPlatformMsg::Deposit {} => deposit(deps, env, from, to, amount.u128()),
+                ReceiveFromPlatformMsg::Deposit { lock_duration } => {
+                    deposit(deps, env, from, to, amount.u128(), lock_duration, None)
+                }
+                ReceiveFromPlatformMsg::DepositFor {
+                    beneficiary,
+                    revocable,
+                } => {
+                    let payer = if revocable { Some(to) } else { None };
+                    deposit(deps, env, from, beneficiary, amount.u128(), None, payer)
+                }
             },
         },
         HandleMsg::CreateViewingKey { entropy, .. } => create_viewing_key(deps, env, entropy),
@@ -151,9 +171,21 @@ pub fn handle<S: Storage, A: Api, Q: Querier>(
             item_id,
         } => apply_multiplier(deps, env, to, multiplier, item_id),
         HandleMsg::DropMultiplier { from, item_id } => drop_multiplier(deps, env, from, item_id),
-        HandleMsg::Withdraw { amount } => withdraw(deps, env, amount.map(|some| some.u128())),
+        HandleMsg::Withdraw { amount, position } => {
+            withdraw(deps, env, amount.map(|some| some.u128()), position)
+        }
         HandleMsg::EmergencyWithdraw {} => emergency_withdraw(deps, env),
         HandleMsg::EmergencyWithdrawSkipPlatform {} => emergency_withdraw_skip_platform(deps, env),
+        HandleMsg::Compound {} => compound(deps, env),
+        HandleMsg::ClaimRewards { to, destination } => claim_rewards(deps, env, to, destination),
+        HandleMsg::RevokeDelegation { beneficiary, id } => {
+            revoke_delegation(deps, env, beneficiary, id)
+        }
+        HandleMsg::ReleaseExpiredLocks { address } => release_expired_locks(deps, env, address),
+        HandleMsg::ClaimRemovedRewardToken { token } => {
+            claim_removed_reward_token(deps, env, token)
+        }
+        HandleMsg::SetAutoCompound { enabled } => set_auto_compound(deps, env, enabled),
         HandleMsg::Features(m) => match m {
             FeatureToggleHandleMsg::Pause { features } => {
                 FeatureToggle::handle_pause(deps, &env, features)
@@ -170,6 +202,7 @@ pub fn handle<S: Storage, A: Api, Q: Querier>(
             token_vk,
             inflation,
             max_multiplier,
+            lock_tiers,
         } => change_config(
             deps,
             env,
@@ -178,13 +211,33 @@ pub fn handle<S: Storage, A: Api, Q: Querier>(
             token_vk,
             inflation,
             max_multiplier,
+            lock_tiers,
         ),
+        HandleMsg::AddRewardToken {
+            token,
+            inflation_schedule,
+        } => add_reward_token(deps, env, token, inflation_schedule),
+        HandleMsg::ChangeRewardTokenSchedule {
+            token,
+            inflation_schedule,
+        } => change_reward_token_schedule(deps, env, token, inflation_schedule),
+        HandleMsg::RemoveRewardToken { token } => remove_reward_token(deps, env, token),
+        HandleMsg::SweepRewardToken {
+            token,
+            recipient,
+            amount,
+        } => sweep_reward_token(deps, env, token, recipient, amount),
+        HandleMsg::Migrate {} => migrate_storage(deps, env),
     };
 
     pad_handle_result(response, RESPONSE_BLOCK_SIZE)
 }
 
 pub fn query<S: Storage, A: Api, Q: Querier>(deps: &Extern<S, A, Q>, msg: QueryMsg) -> QueryResult {
+    if !matches!(msg, QueryMsg::StorageVersion {}) {
+        require_current_version(&deps.storage)?;
+    }
+
     let response = match msg {
         QueryMsg::Token {} => query_token_contract(deps),
         QueryMsg::TotalLocked {} => query_total_locked(deps),
@@ -196,6 +249,9 @@ pub fn query<S: Storage, A: Api, Q: Querier>(deps: &Extern<S, A, Q>, msg: QueryM
         QueryMsg::Admin {} => query_admin(deps),
         QueryMsg::Platform {} => query_platform_contract(deps),
         QueryMsg::InflationSchedule {} => query_inflation_schedule(deps),
+        QueryMsg::LockTiers {} => query_lock_tiers(deps),
+        QueryMsg::RewardTokens {} => query_reward_tokens(deps),
+        QueryMsg::StorageVersion {} => query_storage_version(deps),
 
         QueryMsg::WithPermit { permit, query } => permit_queries(deps, permit, query),
         QueryMsg::Features(m) => match m {
@@ -230,6 +286,9 @@ pub fn authenticated_queries<S: Storage, A: Api, Q: Querier>(
             page_size,
             ..
         } => query_booster_items(deps, address, page_number, page_size),
+        QueryMsg::LockPositions { address, .. } => query_lock_positions(deps, &address),
+        QueryMsg::Delegations { address, .. } => query_delegations(deps, &address),
+        QueryMsg::PayerDelegations { address, .. } => query_payer_delegations(deps, &address),
         _ => Err(StdError::generic_err("unsupported authenticated query")),
     }
 }
@@ -242,6 +301,8 @@ fn deposit<S: Storage, A: Api, Q: Querier>(
     from: HumanAddr,
     to: HumanAddr,
     amount: u128,
+    lock_duration: Option<u64>,
+    payer: Option<HumanAddr>,
 ) -> HandleResult {
     FeatureToggle::require_not_paused(&deps.storage, vec![Features::Deposit])?;
 
@@ -264,18 +325,26 @@ fn deposit<S: Storage, A: Api, Q: Querier>(
     let mut messages: Vec<CosmosMsg> = vec![];
     let mut reward_pool = update_rewards(&deps.storage, env.block.height)?;
     let mut user_balance = UserBalance::load(&deps.storage, &to)?.unwrap_or_default();
+    let mut positions = LockPositions::load(&deps.storage, &to)?;
+    positions.release_expired(env.block.time);
 
-    let real_multiplier = min(user_balance.total_multiplier, config.max_multiplier);
     let current_user_weight = U256::from(user_balance.weight);
 
     if user_balance.locked > 0 {
         let rewards = current_user_weight * U256::from(reward_pool.acc_reward_per_share)
             / U256::from(REWARD_SCALE)
             - U256::from(user_balance.debt);
-        if rewards.as_u128() > 0 {
+        let rewards = compound_rewards(
+            &deps.storage,
+            &to,
+            &mut reward_pool,
+            &mut user_balance,
+            rewards.as_u128(),
+        )?;
+        if rewards > 0 {
             messages.push(snip20::send_msg(
                 config.platform.address,
-                Uint128(rewards.as_u128()),
+                Uint128(rewards),
                 Some(to_binary(&platform::msg::ReceiveMsg::Deposit {
                     to: to.clone(),
                 })?),
@@ -288,8 +357,23 @@ fn deposit<S: Storage, A: Api, Q: Querier>(
         }
     }
 
+    let mut logs = vec![];
     user_balance.locked += amount;
-    let new_user_weight = U256::from(user_balance.locked) * U256::from(real_multiplier);
+    if let Some(lock_duration) = lock_duration {
+        let tier = LockTiers::get(&deps.storage, lock_duration)?;
+        let id = positions.add(amount, &tier, env.block.time);
+        logs.push(log("lock_position", id));
+    }
+    positions.save(&mut deps.storage, &to)?;
+    if let Some(payer) = payer {
+        let mut delegations = Delegations::load(&deps.storage, &to)?;
+        let id = delegations.add(payer.clone(), amount)?;
+        delegations.save(&mut deps.storage, &to)?;
+        PayerDelegations::add(&mut deps.storage, &payer, &to)?;
+        logs.push(log("delegation", id));
+    }
+
+    let new_user_weight = user_weight(&user_balance, &positions, config.max_multiplier);
     let debt =
         new_user_weight * U256::from(reward_pool.acc_reward_per_share) / U256::from(REWARD_SCALE);
 
@@ -298,10 +382,20 @@ fn deposit<S: Storage, A: Api, Q: Querier>(
     user_balance.save(&mut deps.storage, &to)?;
 
     reward_pool.total_locked += amount;
+    let reward_token_messages = settle_reward_tokens(
+        &mut deps.storage,
+        env.block.height,
+        reward_pool.total_weight,
+        &to,
+        &to,
+        current_user_weight.as_u128(),
+        new_user_weight.as_u128(),
+    )?;
     reward_pool.total_weight -= current_user_weight.as_u128();
     reward_pool.total_weight += new_user_weight.as_u128();
     reward_pool.save(&mut deps.storage)?;
 
+    messages.extend(reward_token_messages);
     let subs = Subscribers::load(&deps.storage)?;
     let sub_messages: StdResult<Vec<CosmosMsg>> = subs
         .into_iter()
@@ -309,139 +403,760 @@ fn deposit<S: Storage, A: Api, Q: Querier>(
         .collect();
     messages.extend(sub_messages?);
 
+    logs.insert(0, log("new_balance", user_balance.locked));
+    logs.insert(1, log("new_weight", user_balance.weight));
+
+    Ok(HandleResponse {
+        messages,
+        log: logs,
+        data: Some(to_binary(&HandleAnswer::Deposit { status: Success })?),
+    })
+}
+
+fn withdraw<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    amount: Option<u128>,
+    position: Option<u64>,
+) -> HandleResult {
+    FeatureToggle::require_not_paused(&deps.storage, vec![Features::Withdraw])?;
+
+    let config = Config::load(&deps.storage)?;
+    let mut user_balance =
+        UserBalance::load(&deps.storage, &env.message.sender)?.unwrap_or_default();
+    let mut positions = LockPositions::load(&deps.storage, &env.message.sender)?;
+
+    // Compounded first, so that withdrawing everything includes the compounded rewards
+    let mut reward_pool = update_rewards(&deps.storage, env.block.height)?;
+    let current_user_weight = U256::from(user_balance.weight);
+    let rewards = current_user_weight * U256::from(reward_pool.acc_reward_per_share)
+        / U256::from(REWARD_SCALE)
+        - U256::from(user_balance.debt);
+    let rewards = compound_rewards(
+        &deps.storage,
+        &env.message.sender,
+        &mut reward_pool,
+        &mut user_balance,
+        rewards.as_u128(),
+    )?;
+
+    let (amount, penalty) = match position {
+        Some(id) => positions.withdraw(id, amount, env.block.time)?,
+        None => {
+            positions.release_expired(env.block.time);
+            let delegated = Delegations::load(&deps.storage, &env.message.sender)?.total();
+            let unlocked = user_balance.locked - positions.total() - delegated;
+            let amount = amount.unwrap_or(unlocked);
+            if amount > unlocked {
+                return Err(StdError::generic_err(format!(
+                    "insufficient funds to redeem: balance={}, required={}",
+                    unlocked, amount,
+                )));
+            }
+
+            (amount, 0)
+        }
+    };
+    positions.release_expired(env.block.time);
+    positions.save(&mut deps.storage, &env.message.sender)?;
+
+    user_balance.locked -= amount;
+    let new_user_weight = user_weight(&user_balance, &positions, config.max_multiplier);
+    let debt =
+        new_user_weight * U256::from(reward_pool.acc_reward_per_share) / U256::from(REWARD_SCALE);
+    user_balance.debt = debt.as_u128();
+    user_balance.weight = new_user_weight.as_u128();
+    user_balance.save(&mut deps.storage, &env.message.sender)?;
+
+    reward_pool.total_locked -= amount;
+    let reward_token_messages = settle_reward_tokens(
+        &mut deps.storage,
+        env.block.height,
+        reward_pool.total_weight,
+        &env.message.sender,
+        &env.message.sender,
+        current_user_weight.as_u128(),
+        new_user_weight.as_u128(),
+    )?;
+    reward_pool.total_weight -= current_user_weight.as_u128();
+    reward_pool.total_weight += new_user_weight.as_u128();
+    // Early withdrawal penalties go to the stakers that remain, including the sender. Without any,
+    // they wait in the residue like the rewards of an empty pool
+    match (penalty * REWARD_SCALE).checked_div(reward_pool.total_weight) {
+        Some(per_share) => reward_pool.acc_reward_per_share += per_share,
+        None => reward_pool.residue += penalty,
+    }
+    reward_pool.save(&mut deps.storage)?;
+
+    let mut messages = vec![snip20::send_msg(
+        config.platform.address,
+        Uint128(rewards + amount - penalty),
+        Some(to_binary(&platform::msg::ReceiveMsg::Deposit {
+            to: env.message.sender.clone(),
+        })?),
+        None,
+        None,
+        RESPONSE_BLOCK_SIZE,
+        config.token.hash,
+        config.token.address,
+    )?];
+
+    messages.extend(reward_token_messages);
+    let subs = Subscribers::load(&deps.storage)?;
+    let sub_messages: StdResult<Vec<CosmosMsg>> = subs
+        .into_iter()
+        .map(|s| create_subscriber_msg(s, &env.message.sender, user_balance.locked))
+        .collect();
+    messages.extend(sub_messages?);
+
     Ok(HandleResponse {
         messages,
         log: vec![
             log("new_balance", user_balance.locked),
             log("new_weight", user_balance.weight),
+            log("penalty", penalty),
         ],
-        data: Some(to_binary(&HandleAnswer::Deposit { status: Success })?),
+        data: Some(to_binary(&HandleAnswer::Redeem { status: Success })?),
+    })
+}
+
+/// Unlike the other handles, the sender isn't the beneficiary whose balance changes. So the
+/// response doesn't log the new balance of the beneficiary
+fn revoke_delegation<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    beneficiary: HumanAddr,
+    id: u64,
+) -> HandleResult {
+    FeatureToggle::require_not_paused(&deps.storage, vec![Features::Withdraw])?;
+
+    let config = Config::load(&deps.storage)?;
+    let mut delegations = Delegations::load(&deps.storage, &beneficiary)?;
+    let delegation = delegations.revoke(id, &env.message.sender, &beneficiary)?;
+    delegations.save(&mut deps.storage, &beneficiary)?;
+    PayerDelegations::remove(&mut deps.storage, &delegation.payer, &beneficiary)?;
+    let amount = delegation.amount;
+
+    let mut reward_pool = update_rewards(&deps.storage, env.block.height)?;
+    let mut user_balance = UserBalance::load(&deps.storage, &beneficiary)?.unwrap_or_default();
+    let mut positions = LockPositions::load(&deps.storage, &beneficiary)?;
+    positions.release_expired(env.block.time);
+    positions.save(&mut deps.storage, &beneficiary)?;
+
+    let current_user_weight = U256::from(user_balance.weight);
+    let rewards = current_user_weight * U256::from(reward_pool.acc_reward_per_share)
+        / U256::from(REWARD_SCALE)
+        - U256::from(user_balance.debt);
+    let rewards = compound_rewards(
+        &deps.storage,
+        &beneficiary,
+        &mut reward_pool,
+        &mut user_balance,
+        rewards.as_u128(),
+    )?;
+
+    user_balance.locked -= amount;
+    let new_user_weight = user_weight(&user_balance, &positions, config.max_multiplier);
+    let debt =
+        new_user_weight * U256::from(reward_pool.acc_reward_per_share) / U256::from(REWARD_SCALE);
+    user_balance.debt = debt.as_u128();
+    user_balance.weight = new_user_weight.as_u128();
+    user_balance.save(&mut deps.storage, &beneficiary)?;
+
+    reward_pool.total_locked -= amount;
+    let reward_token_messages = settle_reward_tokens(
+        &mut deps.storage,
+        env.block.height,
+        reward_pool.total_weight,
+        &beneficiary,
+        &beneficiary,
+        current_user_weight.as_u128(),
+        new_user_weight.as_u128(),
+    )?;
+    reward_pool.total_weight -= current_user_weight.as_u128();
+    reward_pool.total_weight += new_user_weight.as_u128();
+    reward_pool.save(&mut deps.storage)?;
+
+    let mut messages = vec![snip20::send_msg(
+        config.platform.address.clone(),
+        Uint128(amount),
+        Some(to_binary(&platform::msg::ReceiveMsg::Deposit {
+            to: delegation.payer,
+        })?),
+        None,
+        None,
+        RESPONSE_BLOCK_SIZE,
+        config.token.hash.clone(),
+        config.token.address.clone(),
+    )?];
+    if rewards > 0 {
+        messages.push(snip20::send_msg(
+            config.platform.address,
+            Uint128(rewards),
+            Some(to_binary(&platform::msg::ReceiveMsg::Deposit {
+                to: beneficiary.clone(),
+            })?),
+            None,
+            None,
+            RESPONSE_BLOCK_SIZE,
+            config.token.hash,
+            config.token.address,
+        )?);
+    }
+
+    messages.extend(reward_token_messages);
+    let subs = Subscribers::load(&deps.storage)?;
+    let sub_messages: StdResult<Vec<CosmosMsg>> = subs
+        .into_iter()
+        .map(|s| create_subscriber_msg(s, &beneficiary, user_balance.locked))
+        .collect();
+    messages.extend(sub_messages?);
+
+    Ok(HandleResponse {
+        messages,
+        log: vec![],
+        data: Some(to_binary(&HandleAnswer::RevokeDelegation {
+            status: Success,
+        })?),
+    })
+}
+
+/// Like `revoke_delegation`, the sender isn't the user whose weight changes
+fn release_expired_locks<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    address: HumanAddr,
+) -> HandleResult {
+    let mut positions = LockPositions::load(&deps.storage, &address)?;
+    if !positions.release_expired(env.block.time) {
+        return Ok(HandleResponse {
+            messages: vec![],
+            log: vec![],
+            data: Some(to_binary(&HandleAnswer::ReleaseExpiredLocks {
+                status: NotChanged,
+            })?),
+        });
+    }
+    positions.save(&mut deps.storage, &address)?;
+
+    let config = Config::load(&deps.storage)?;
+    let mut reward_pool = update_rewards(&deps.storage, env.block.height)?;
+    let mut user_balance = UserBalance::load(&deps.storage, &address)?.unwrap_or_default();
+
+    let current_user_weight = U256::from(user_balance.weight);
+    let rewards = current_user_weight * U256::from(reward_pool.acc_reward_per_share)
+        / U256::from(REWARD_SCALE)
+        - U256::from(user_balance.debt);
+    let rewards = compound_rewards(
+        &deps.storage,
+        &address,
+        &mut reward_pool,
+        &mut user_balance,
+        rewards.as_u128(),
+    )?;
+
+    let new_user_weight = user_weight(&user_balance, &positions, config.max_multiplier);
+    let debt =
+        new_user_weight * U256::from(reward_pool.acc_reward_per_share) / U256::from(REWARD_SCALE);
+    user_balance.debt = debt.as_u128();
+    user_balance.weight = new_user_weight.as_u128();
+    user_balance.save(&mut deps.storage, &address)?;
+
+    let reward_token_messages = settle_reward_tokens(
+        &mut deps.storage,
+        env.block.height,
+        reward_pool.total_weight,
+        &address,
+        &address,
+        current_user_weight.as_u128(),
+        new_user_weight.as_u128(),
+    )?;
+    reward_pool.total_weight -= current_user_weight.as_u128();
+    reward_pool.total_weight += new_user_weight.as_u128();
+    reward_pool.save(&mut deps.storage)?;
+
+    let mut messages = vec![];
+    if rewards > 0 {
+        messages.push(snip20::send_msg(
+            config.platform.address,
+            Uint128(rewards),
+            Some(to_binary(&platform::msg::ReceiveMsg::Deposit {
+                to: address.clone(),
+            })?),
+            None,
+            None,
+            RESPONSE_BLOCK_SIZE,
+            config.token.hash,
+            config.token.address,
+        )?);
+    }
+
+    messages.extend(reward_token_messages);
+    let subs = Subscribers::load(&deps.storage)?;
+    let sub_messages: StdResult<Vec<CosmosMsg>> = subs
+        .into_iter()
+        .map(|s| create_subscriber_msg(s, &address, user_balance.locked))
+        .collect();
+    messages.extend(sub_messages?);
+
+    Ok(HandleResponse {
+        messages,
+        log: vec![],
+        data: Some(to_binary(&HandleAnswer::ReleaseExpiredLocks {
+            status: Success,
+        })?),
+    })
+}
+
+fn compound<S: Storage, A: Api, Q: Querier>(deps: &mut Extern<S, A, Q>, env: Env) -> HandleResult {
+    let (response, status) = claim(deps, env, None, RewardDestination::Restake)?;
+
+    Ok(HandleResponse {
+        data: Some(to_binary(&HandleAnswer::Compound { status })?),
+        ..response
+    })
+}
+
+fn claim_rewards<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    to: Option<HumanAddr>,
+    destination: RewardDestination,
+) -> HandleResult {
+    let (response, status) = claim(deps, env, to, destination)?;
+
+    Ok(HandleResponse {
+        data: Some(to_binary(&HandleAnswer::ClaimRewards { status })?),
+        ..response
+    })
+}
+
+/// Pays out the pending rewards of the sender to `destination`, for `to` (the sender if not
+/// specified). The response has no data
+fn claim<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    to: Option<HumanAddr>,
+    destination: RewardDestination,
+) -> StdResult<(HandleResponse, ResponseStatus)> {
+    let restake = matches!(destination, RewardDestination::Restake);
+    let feature = if restake {
+        Features::Deposit
+    } else {
+        Features::Withdraw
+    };
+    FeatureToggle::require_not_paused(&deps.storage, vec![feature])?;
+
+    let to = to.unwrap_or_else(|| env.message.sender.clone());
+    if restake && to != env.message.sender {
+        return Err(StdError::generic_err(
+            "rewards can only be restaked by their owner",
+        ));
+    }
+
+    let config = Config::load(&deps.storage)?;
+    let mut reward_pool = update_rewards(&deps.storage, env.block.height)?;
+    let mut user_balance =
+        UserBalance::load(&deps.storage, &env.message.sender)?.unwrap_or_default();
+    let mut positions = LockPositions::load(&deps.storage, &env.message.sender)?;
+    let released = positions.release_expired(env.block.time);
+
+    let current_user_weight = U256::from(user_balance.weight);
+    let rewards = (current_user_weight * U256::from(reward_pool.acc_reward_per_share)
+        / U256::from(REWARD_SCALE)
+        - U256::from(user_balance.debt))
+    .as_u128();
+    if rewards == 0 && !released {
+        // The weight doesn't change, only the other reward tokens may have rewards to pay out
+        let messages = settle_reward_tokens(
+            &mut deps.storage,
+            env.block.height,
+            reward_pool.total_weight,
+            &env.message.sender,
+            &to,
+            current_user_weight.as_u128(),
+            current_user_weight.as_u128(),
+        )?;
+        let status = if messages.is_empty() {
+            NotChanged
+        } else {
+            Success
+        };
+        let response = HandleResponse {
+            messages,
+            log: vec![],
+            data: None,
+        };
+
+        return Ok((response, status));
+    }
+    positions.save(&mut deps.storage, &env.message.sender)?;
+
+    let mut messages = vec![];
+    if rewards > 0 {
+        match destination {
+            RewardDestination::Platform => messages.push(snip20::send_msg(
+                config.platform.address,
+                Uint128(rewards),
+                Some(to_binary(&platform::msg::ReceiveMsg::Deposit {
+                    to: to.clone(),
+                })?),
+                None,
+                None,
+                RESPONSE_BLOCK_SIZE,
+                config.token.hash,
+                config.token.address,
+            )?),
+            RewardDestination::Wallet => messages.push(snip20::transfer_msg(
+                to.clone(),
+                Uint128(rewards),
+                None,
+                None,
+                RESPONSE_BLOCK_SIZE,
+                config.token.hash,
+                config.token.address,
+            )?),
+            RewardDestination::Restake => {
+                // The rewards are already held by this contract, so they are only accounted as
+                // locked
+                user_balance.locked += rewards;
+                reward_pool.total_locked += rewards;
+            }
+        }
+    }
+
+    let new_user_weight = user_weight(&user_balance, &positions, config.max_multiplier);
+    let debt =
+        new_user_weight * U256::from(reward_pool.acc_reward_per_share) / U256::from(REWARD_SCALE);
+    user_balance.debt = debt.as_u128();
+    user_balance.weight = new_user_weight.as_u128();
+    user_balance.save(&mut deps.storage, &env.message.sender)?;
+
+    let reward_token_messages = settle_reward_tokens(
+        &mut deps.storage,
+        env.block.height,
+        reward_pool.total_weight,
+        &env.message.sender,
+        &to,
+        current_user_weight.as_u128(),
+        new_user_weight.as_u128(),
+    )?;
+    messages.extend(reward_token_messages);
+
+    reward_pool.total_weight -= current_user_weight.as_u128();
+    reward_pool.total_weight += new_user_weight.as_u128();
+    reward_pool.save(&mut deps.storage)?;
+
+    let subs = Subscribers::load(&deps.storage)?;
+    let sub_messages: StdResult<Vec<CosmosMsg>> = subs
+        .into_iter()
+        .map(|s| create_subscriber_msg(s, &env.message.sender, user_balance.locked))
+        .collect();
+    messages.extend(sub_messages?);
+
+    let response = HandleResponse {
+        messages,
+        log: vec![
+            log("new_balance", user_balance.locked),
+            log("new_weight", user_balance.weight),
+            log("rewards", rewards),
+        ],
+        data: None,
+    };
+
+    Ok((response, Success))
+}
+
+fn set_auto_compound<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    enabled: bool,
+) -> HandleResult {
+    AutoCompound::save(&mut deps.storage, &env.message.sender, enabled)?;
+
+    Ok(HandleResponse {
+        messages: vec![],
+        log: vec![],
+        data: Some(to_binary(&HandleAnswer::SetAutoCompound {
+            status: Success,
+        })?),
+    })
+}
+
+pub fn create_viewing_key<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    entropy: String,
+) -> HandleResult {
+    let key = ViewingKey::create(
+        &mut deps.storage,
+        &env,
+        &env.message.sender,
+        entropy.as_bytes(),
+    );
+
+    Ok(HandleResponse {
+        messages: vec![],
+        log: vec![],
+        data: Some(to_binary(&HandleAnswer::CreateViewingKey { key })?),
+    })
+}
+
+pub fn set_viewing_key<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    key: String,
+) -> HandleResult {
+    ViewingKey::set(&mut deps.storage, &env.message.sender, &key);
+
+    Ok(HandleResponse {
+        messages: vec![],
+        log: vec![],
+        data: Some(to_binary(&HandleAnswer::SetViewingKey { status: Success })?),
+    })
+}
+
+fn change_admin<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    address: HumanAddr,
+) -> StdResult<HandleResponse> {
+    let mut config = Config::load(&deps.storage)?;
+
+    require_admin(&config, &env)?;
+
+    config.admin = address;
+    config.save(&mut deps.storage)?;
+
+    Ok(HandleResponse {
+        messages: vec![],
+        log: vec![],
+        data: Some(to_binary(&HandleAnswer::ChangeAdmin { status: Success })?),
+    })
+}
+
+fn add_reward_token<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    token: Contract,
+    inflation_schedule: Vec<ScheduleUnit>,
+) -> StdResult<HandleResponse> {
+    let config = Config::load(&deps.storage)?;
+    require_admin(&config, &env)?;
+
+    let mut reward_tokens = RewardTokens::load(&deps.storage)?;
+    if token.address == config.token.address
+        || reward_tokens
+            .tokens
+            .iter()
+            .any(|t| t.token.address == token.address)
+    {
+        return Err(StdError::generic_err(format!(
+            "{} is already a reward token",
+            token.address
+        )));
+    }
+    // Every change of a weight pays out all the reward tokens
+    if reward_tokens.tokens.len() >= MAX_REWARD_TOKENS {
+        return Err(StdError::generic_err(format!(
+            "cannot have more than {} reward tokens",
+            MAX_REWARD_TOKENS
+        )));
+    }
+
+    reward_tokens.add(
+        token,
+        inflation_schedule.iter().map(|u| u.to_stored()).collect(),
+        env.block.height,
+    );
+    reward_tokens.save(&mut deps.storage)?;
+
+    Ok(HandleResponse {
+        messages: vec![],
+        log: vec![],
+        data: Some(to_binary(&HandleAnswer::AddRewardToken {
+            status: Success,
+        })?),
+    })
+}
+
+fn claim_removed_reward_token<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    token: HumanAddr,
+) -> HandleResult {
+    let sender = env.message.sender;
+    let reward_tokens = RewardTokens::load(&deps.storage)?;
+    let weight = UserBalance::load(&deps.storage, &sender)?
+        .unwrap_or_default()
+        .weight;
+    let mut debts = RewardTokenDebts::load(&deps.storage, &sender)?;
+
+    let mut contract = None;
+    let mut rewards = U256::zero();
+    for removed in reward_tokens
+        .removed
+        .iter()
+        .filter(|t| t.token.address == token)
+    {
+        rewards += removed_token_rewards(removed, &debts, weight);
+        let debt = U256::from(weight) * U256::from(removed.acc_reward_per_share)
+            / U256::from(REWARD_SCALE);
+        debts.claim(removed.id, debt.as_u128());
+        contract = Some(&removed.token);
+    }
+    let contract = contract
+        .ok_or_else(|| StdError::generic_err(format!("{} is not a removed reward token", token)))?;
+
+    if rewards.is_zero() {
+        return Ok(HandleResponse {
+            messages: vec![],
+            log: vec![],
+            data: Some(to_binary(&HandleAnswer::ClaimRemovedRewardToken {
+                status: NotChanged,
+            })?),
+        });
+    }
+    debts.save(&mut deps.storage, &sender)?;
+
+    Ok(HandleResponse {
+        messages: vec![snip20::transfer_msg(
+            sender,
+            Uint128(rewards.as_u128()),
+            None,
+            None,
+            RESPONSE_BLOCK_SIZE,
+            contract.hash.clone(),
+            contract.address.clone(),
+        )?],
+        log: vec![],
+        data: Some(to_binary(&HandleAnswer::ClaimRemovedRewardToken {
+            status: Success,
+        })?),
     })
 }
 
-fn withdraw<S: Storage, A: Api, Q: Querier>(
+fn change_reward_token_schedule<S: Storage, A: Api, Q: Querier>(
     deps: &mut Extern<S, A, Q>,
     env: Env,
-    amount: Option<u128>,
-) -> HandleResult {
-    FeatureToggle::require_not_paused(&deps.storage, vec![Features::Withdraw])?;
-
+    token: HumanAddr,
+    inflation_schedule: Vec<ScheduleUnit>,
+) -> StdResult<HandleResponse> {
     let config = Config::load(&deps.storage)?;
-    let mut user_balance =
-        UserBalance::load(&deps.storage, &env.message.sender)?.unwrap_or_default();
-    let amount = amount.unwrap_or(user_balance.locked);
-
-    if amount > user_balance.locked {
-        return Err(StdError::generic_err(format!(
-            "insufficient funds to redeem: balance={}, required={}",
-            user_balance.locked, amount,
-        )));
-    }
-
-    let mut reward_pool = update_rewards(&deps.storage, env.block.height)?;
-    let real_multiplier = min(user_balance.total_multiplier, config.max_multiplier);
-    let current_user_weight = U256::from(user_balance.weight);
-
-    let rewards = current_user_weight * U256::from(reward_pool.acc_reward_per_share)
-        / U256::from(REWARD_SCALE)
-        - U256::from(user_balance.debt);
-
-    user_balance.locked -= amount;
-    let new_user_weight = U256::from(user_balance.locked) * U256::from(real_multiplier);
-    let debt =
-        new_user_weight * U256::from(reward_pool.acc_reward_per_share) / U256::from(REWARD_SCALE);
-    user_balance.debt = debt.as_u128();
-    user_balance.weight = new_user_weight.as_u128();
-    user_balance.save(&mut deps.storage, &env.message.sender)?;
-
-    reward_pool.total_locked -= amount;
-    reward_pool.total_weight -= current_user_weight.as_u128();
-    reward_pool.total_weight += new_user_weight.as_u128();
-    reward_pool.save(&mut deps.storage)?;
-
-    let mut messages = vec![snip20::send_msg(
-        config.platform.address,
-        Uint128(rewards.as_u128() + amount),
-        Some(to_binary(&platform::msg::ReceiveMsg::Deposit {
-            to: env.message.sender.clone(),
-        })?),
-        None,
-        None,
-        RESPONSE_BLOCK_SIZE,
-        config.token.hash,
-        config.token.address,
-    )?];
+    require_admin(&config, &env)?;
 
-    let subs = Subscribers::load(&deps.storage)?;
-    let sub_messages: StdResult<Vec<CosmosMsg>> = subs
-        .into_iter()
-        .map(|s| create_subscriber_msg(s, &env.message.sender, user_balance.locked))
-        .collect();
-    messages.extend(sub_messages?);
+    let total_weight = RewardPool::load(&deps.storage)?.total_weight;
+    let mut reward_tokens = RewardTokens::load(&deps.storage)?;
+    let reward_token = reward_tokens.get_mut(&token)?;
+    update_reward_token(reward_token, total_weight, env.block.height);
+    reward_token.schedule = inflation_schedule.iter().map(|u| u.to_stored()).collect();
+    reward_tokens.save(&mut deps.storage)?;
 
     Ok(HandleResponse {
-        messages,
-        log: vec![
-            log("new_balance", user_balance.locked),
-            log("new_weight", user_balance.weight),
-        ],
-        data: Some(to_binary(&HandleAnswer::Redeem { status: Success })?),
+        messages: vec![],
+        log: vec![],
+        data: Some(to_binary(&HandleAnswer::ChangeRewardTokenSchedule {
+            status: Success,
+        })?),
     })
 }
 
-pub fn create_viewing_key<S: Storage, A: Api, Q: Querier>(
+fn remove_reward_token<S: Storage, A: Api, Q: Querier>(
     deps: &mut Extern<S, A, Q>,
     env: Env,
-    entropy: String,
-) -> HandleResult {
-    let key = ViewingKey::create(
-        &mut deps.storage,
-        &env,
-        &env.message.sender,
-        entropy.as_bytes(),
+    token: HumanAddr,
+) -> StdResult<HandleResponse> {
+    let config = Config::load(&deps.storage)?;
+    require_admin(&config, &env)?;
+
+    let total_weight = RewardPool::load(&deps.storage)?.total_weight;
+    let mut reward_tokens = RewardTokens::load(&deps.storage)?;
+    update_reward_token(
+        reward_tokens.get_mut(&token)?,
+        total_weight,
+        env.block.height,
     );
+    reward_tokens.remove(&token)?;
+    reward_tokens.save(&mut deps.storage)?;
 
     Ok(HandleResponse {
         messages: vec![],
         log: vec![],
-        data: Some(to_binary(&HandleAnswer::CreateViewingKey { key })?),
+        data: Some(to_binary(&HandleAnswer::RemoveRewardToken {
+            status: Success,
+        })?),
     })
 }
 
-pub fn set_viewing_key<S: Storage, A: Api, Q: Querier>(
+fn sweep_reward_token<S: Storage, A: Api, Q: Querier>(
     deps: &mut Extern<S, A, Q>,
     env: Env,
-    key: String,
-) -> HandleResult {
-    ViewingKey::set(&mut deps.storage, &env.message.sender, &key);
+    token: HumanAddr,
+    recipient: HumanAddr,
+    amount: Option<Uint128>,
+) -> StdResult<HandleResponse> {
+    let config = Config::load(&deps.storage)?;
+    require_admin(&config, &env)?;
+
+    let mut reward_tokens = RewardTokens::load(&deps.storage)?;
+    let (token, amount) =
+        reward_tokens.take_undistributed(&token, amount.map_or(u128::MAX, |a| a.u128()))?;
+    if amount == 0 {
+        return Ok(HandleResponse {
+            messages: vec![],
+            log: vec![],
+            data: Some(to_binary(&HandleAnswer::SweepRewardToken {
+                status: NotChanged,
+            })?),
+        });
+    }
+    reward_tokens.save(&mut deps.storage)?;
 
     Ok(HandleResponse {
-        messages: vec![],
+        messages: vec![snip20::transfer_msg(
+            recipient,
+            Uint128(amount),
+            None,
+            None,
+            RESPONSE_BLOCK_SIZE,
+            token.hash,
+            token.address,
+        )?],
         log: vec![],
-        data: Some(to_binary(&HandleAnswer::SetViewingKey { status: Success })?),
+        data: Some(to_binary(&HandleAnswer::SweepRewardToken {
+            status: Success,
+        })?),
     })
 }
 
-fn change_admin<S: Storage, A: Api, Q: Querier>(
+fn migrate_storage<S: Storage, A: Api, Q: Querier>(
     deps: &mut Extern<S, A, Q>,
     env: Env,
-    address: HumanAddr,
 ) -> StdResult<HandleResponse> {
-    let mut config = Config::load(&deps.storage)?;
-
-    require_admin(&config, &env)?;
+    // `Config` may not be readable before the migration
+    if load_admin(&deps.storage)? != env.message.sender {
+        return Err(StdError::generic_err(format!(
+            "not an admin: {}",
+            env.message.sender
+        )));
+    }
 
-    config.admin = address;
-    config.save(&mut deps.storage)?;
+    let from = migrate(&mut deps.storage)?;
+    let status = if from == STORAGE_VERSION {
+        NotChanged
+    } else {
+        Success
+    };
 
     Ok(HandleResponse {
         messages: vec![],
         log: vec![],
-        data: Some(to_binary(&HandleAnswer::ChangeAdmin { status: Success })?),
+        data: Some(to_binary(&HandleAnswer::Migrate { status })?),
     })
 }
 
-/// YOU SHOULD NEVER USE THIS! This will erase any eligibility for rewards you earned so far
+/// YOU SHOULD NEVER USE THIS! This will erase any eligibility for rewards you earned so far.
+/// Lock positions that didn't end pay their early withdrawal penalty, or can't be withdrawn at all
 fn emergency_withdraw<S: Storage, A: Api, Q: Querier>(
     deps: &mut Extern<S, A, Q>,
     env: Env,
@@ -452,26 +1167,63 @@ fn emergency_withdraw<S: Storage, A: Api, Q: Querier>(
     let user_balance = UserBalance::load(&deps.storage, &env.message.sender)?.unwrap_or_default();
 
     let mut reward_pool = RewardPool::load(&deps.storage)?;
+    let mut positions = LockPositions::load(&deps.storage, &env.message.sender)?;
+    let penalty = positions.total_penalty(env.block.time)?;
+    positions.positions.clear();
+    positions.save(&mut deps.storage, &env.message.sender)?;
 
+    let mut delegations = Delegations::load(&deps.storage, &env.message.sender)?;
     let mut messages = vec![];
     if user_balance.locked > 0 {
-        messages.push(snip20::send_msg(
-            config.platform.address,
-            Uint128(user_balance.locked),
-            Some(to_binary(&platform::msg::ReceiveMsg::Deposit {
-                to: env.message.sender.clone(),
-            })?),
-            None,
-            None,
-            RESPONSE_BLOCK_SIZE,
-            config.token.hash,
-            config.token.address,
-        )?);
+        let mut recipients = vec![(
+            env.message.sender.clone(),
+            user_balance.locked - delegations.total() - penalty,
+        )];
+        // Delegated tokens go back to their payers
+        recipients.extend(
+            delegations
+                .delegations
+                .iter()
+                .map(|d| (d.payer.clone(), d.amount)),
+        );
+        for (recipient, amount) in recipients.into_iter().filter(|(_, amount)| *amount > 0) {
+            messages.push(snip20::send_msg(
+                config.platform.address.clone(),
+                Uint128(amount),
+                Some(to_binary(&platform::msg::ReceiveMsg::Deposit {
+                    to: recipient,
+                })?),
+                None,
+                None,
+                RESPONSE_BLOCK_SIZE,
+                config.token.hash.clone(),
+                config.token.address.clone(),
+            )?);
+        }
 
+        // The rewards in the reward tokens are forfeited too
+        settle_reward_tokens(
+            &mut deps.storage,
+            env.block.height,
+            reward_pool.total_weight,
+            &env.message.sender,
+            &env.message.sender,
+            user_balance.weight,
+            0,
+        )?;
         reward_pool.total_locked -= user_balance.locked;
         reward_pool.total_weight -= user_balance.weight;
+        // Like in `withdraw`, the penalty goes to the stakers that remain
+        match (penalty * REWARD_SCALE).checked_div(reward_pool.total_weight) {
+            Some(per_share) => reward_pool.acc_reward_per_share += per_share,
+            None => reward_pool.residue += penalty,
+        }
         reward_pool.save(&mut deps.storage)?;
     }
+    for delegation in delegations.delegations.drain(..) {
+        PayerDelegations::remove(&mut deps.storage, &delegation.payer, &env.message.sender)?;
+    }
+    delegations.save(&mut deps.storage, &env.message.sender)?;
 
     let new_user_balance = UserBalance {
         locked: 0,
@@ -497,7 +1249,8 @@ fn emergency_withdraw<S: Storage, A: Api, Q: Querier>(
     })
 }
 
-/// YOU SHOULD NEVER USE THIS! This will erase any eligibility for rewards you earned so far
+/// YOU SHOULD NEVER USE THIS! This will erase any eligibility for rewards you earned so far.
+/// Lock positions that didn't end pay their early withdrawal penalty, or can't be withdrawn at all
 fn emergency_withdraw_skip_platform<S: Storage, A: Api, Q: Querier>(
     deps: &mut Extern<S, A, Q>,
     env: Env,
@@ -512,23 +1265,60 @@ fn emergency_withdraw_skip_platform<S: Storage, A: Api, Q: Querier>(
         UserBalance::load(&deps.storage, &env.message.sender)?.unwrap_or_default();
 
     let mut reward_pool = RewardPool::load(&deps.storage)?;
+    let mut positions = LockPositions::load(&deps.storage, &env.message.sender)?;
+    let penalty = positions.total_penalty(env.block.time)?;
+    positions.positions.clear();
+    positions.save(&mut deps.storage, &env.message.sender)?;
 
+    let mut delegations = Delegations::load(&deps.storage, &env.message.sender)?;
     let mut messages = vec![];
     if user_balance.locked > 0 {
-        messages.push(snip20::transfer_msg(
+        let mut recipients = vec![(
             env.message.sender.clone(),
-            Uint128(user_balance.locked),
-            None,
-            None,
-            RESPONSE_BLOCK_SIZE,
-            config.token.hash,
-            config.token.address,
-        )?);
+            user_balance.locked - delegations.total() - penalty,
+        )];
+        // Delegated tokens go back to their payers
+        recipients.extend(
+            delegations
+                .delegations
+                .iter()
+                .map(|d| (d.payer.clone(), d.amount)),
+        );
+        for (recipient, amount) in recipients.into_iter().filter(|(_, amount)| *amount > 0) {
+            messages.push(snip20::transfer_msg(
+                recipient,
+                Uint128(amount),
+                None,
+                None,
+                RESPONSE_BLOCK_SIZE,
+                config.token.hash.clone(),
+                config.token.address.clone(),
+            )?);
+        }
 
+        // The rewards in the reward tokens are forfeited too
+        settle_reward_tokens(
+            &mut deps.storage,
+            env.block.height,
+            reward_pool.total_weight,
+            &env.message.sender,
+            &env.message.sender,
+            user_balance.weight,
+            0,
+        )?;
         reward_pool.total_locked -= user_balance.locked;
         reward_pool.total_weight -= user_balance.weight;
+        // Like in `withdraw`, the penalty goes to the stakers that remain
+        match (penalty * REWARD_SCALE).checked_div(reward_pool.total_weight) {
+            Some(per_share) => reward_pool.acc_reward_per_share += per_share,
+            None => reward_pool.residue += penalty,
+        }
         reward_pool.save(&mut deps.storage)?;
     }
+    for delegation in delegations.delegations.drain(..) {
+        PayerDelegations::remove(&mut deps.storage, &delegation.payer, &env.message.sender)?;
+    }
+    delegations.save(&mut deps.storage, &env.message.sender)?;
 
     user_balance = UserBalance {
         locked: 0,
@@ -666,6 +1456,9 @@ fn apply_multiplier<S: Storage, A: Api, Q: Querier>(
     let mut messages: Vec<CosmosMsg> = vec![];
     let mut reward_pool = update_rewards(&deps.storage, env.block.height)?;
     let mut user_balance = UserBalance::load(&deps.storage, &to)?.unwrap_or_default();
+    let mut positions = LockPositions::load(&deps.storage, &to)?;
+    positions.release_expired(env.block.time);
+    positions.save(&mut deps.storage, &to)?;
 
     let current_user_weight = U256::from(user_balance.weight);
 
@@ -673,10 +1466,17 @@ fn apply_multiplier<S: Storage, A: Api, Q: Querier>(
         let rewards = current_user_weight * U256::from(reward_pool.acc_reward_per_share)
             / U256::from(REWARD_SCALE)
             - U256::from(user_balance.debt);
-        if rewards.as_u128() > 0 {
+        let rewards = compound_rewards(
+            &deps.storage,
+            &to,
+            &mut reward_pool,
+            &mut user_balance,
+            rewards.as_u128(),
+        )?;
+        if rewards > 0 {
             messages.push(snip20::send_msg(
                 config.platform.address,
-                Uint128(rewards.as_u128()),
+                Uint128(rewards),
                 Some(to_binary(&platform::msg::ReceiveMsg::Deposit {
                     to: to.clone(),
                 })?),
@@ -692,19 +1492,28 @@ fn apply_multiplier<S: Storage, A: Api, Q: Querier>(
     let total_multiplier = U256::from(user_balance.total_multiplier) - U256::from(MULTIPLIER_SCALE)
         + U256::from(multiplier as u128);
     user_balance.total_multiplier = total_multiplier.as_u128();
-    let real_multiplier = min(user_balance.total_multiplier, config.max_multiplier);
 
-    let new_user_weight = U256::from(user_balance.locked) * U256::from(real_multiplier);
+    let new_user_weight = user_weight(&user_balance, &positions, config.max_multiplier);
     let debt =
         new_user_weight * U256::from(reward_pool.acc_reward_per_share) / U256::from(REWARD_SCALE);
     user_balance.debt = debt.as_u128();
     user_balance.weight = new_user_weight.as_u128();
     user_balance.save(&mut deps.storage, &to)?;
 
+    let reward_token_messages = settle_reward_tokens(
+        &mut deps.storage,
+        env.block.height,
+        reward_pool.total_weight,
+        &to,
+        &to,
+        current_user_weight.as_u128(),
+        new_user_weight.as_u128(),
+    )?;
     reward_pool.total_weight -= current_user_weight.as_u128();
     reward_pool.total_weight += new_user_weight.as_u128();
     reward_pool.save(&mut deps.storage)?;
 
+    messages.extend(reward_token_messages);
     let subs = Subscribers::load(&deps.storage)?;
     let sub_messages: StdResult<Vec<CosmosMsg>> = subs
         .into_iter()
@@ -754,6 +1563,9 @@ fn drop_multiplier<S: Storage, A: Api, Q: Querier>(
 
             let config = Config::load(&deps.storage)?;
             let mut reward_pool = update_rewards(&deps.storage, env.block.height)?;
+            let mut positions = LockPositions::load(&deps.storage, &from)?;
+            positions.release_expired(env.block.time);
+            positions.save(&mut deps.storage, &from)?;
 
             let current_user_weight = U256::from(user_balance.weight);
 
@@ -761,10 +1573,17 @@ fn drop_multiplier<S: Storage, A: Api, Q: Querier>(
                 let rewards = current_user_weight * U256::from(reward_pool.acc_reward_per_share)
                     / U256::from(REWARD_SCALE)
                     - U256::from(user_balance.debt);
-                if rewards.as_u128() > 0 {
+                let rewards = compound_rewards(
+                    &deps.storage,
+                    &from,
+                    &mut reward_pool,
+                    &mut user_balance,
+                    rewards.as_u128(),
+                )?;
+                if rewards > 0 {
                     messages.push(snip20::send_msg(
                         config.platform.address,
-                        Uint128(rewards.as_u128()),
+                        Uint128(rewards),
                         Some(to_binary(&platform::msg::ReceiveMsg::Deposit {
                             to: from.clone(),
                         })?),
@@ -783,18 +1602,27 @@ fn drop_multiplier<S: Storage, A: Api, Q: Querier>(
 
             user_balance.total_multiplier = max(total_multiplier.as_u128(), MULTIPLIER_SCALE); // can`t go below 1
 
-            let real_multiplier = min(user_balance.total_multiplier, config.max_multiplier);
-            let new_user_weight = U256::from(user_balance.locked) * U256::from(real_multiplier);
+            let new_user_weight = user_weight(&user_balance, &positions, config.max_multiplier);
             let debt = new_user_weight * U256::from(reward_pool.acc_reward_per_share)
                 / U256::from(REWARD_SCALE);
             user_balance.debt = debt.as_u128();
             user_balance.weight = new_user_weight.as_u128();
             user_balance.save(&mut deps.storage, &from)?;
 
+            let reward_token_messages = settle_reward_tokens(
+                &mut deps.storage,
+                env.block.height,
+                reward_pool.total_weight,
+                &from,
+                &from,
+                current_user_weight.as_u128(),
+                new_user_weight.as_u128(),
+            )?;
             reward_pool.total_weight -= current_user_weight.as_u128();
             reward_pool.total_weight += new_user_weight.as_u128();
             reward_pool.save(&mut deps.storage)?;
 
+            messages.extend(reward_token_messages);
             let subs = Subscribers::load(&deps.storage)?;
             let sub_messages: StdResult<Vec<CosmosMsg>> = subs
                 .into_iter()
@@ -840,6 +1668,7 @@ fn remove_pauser<S: Storage, A: Api, Q: Querier>(
     FeatureToggle::handle_remove_pauser(deps, &env, address)
 }
 
+#[allow(clippy::too_many_arguments)]
 fn change_config<S: Storage, A: Api, Q: Querier>(
     deps: &mut Extern<S, A, Q>,
     env: Env,
@@ -848,6 +1677,7 @@ fn change_config<S: Storage, A: Api, Q: Querier>(
     token_vk: Option<String>,
     inflation: Option<Vec<ScheduleUnit>>,
     max_multiplier: Option<Uint128>,
+    lock_tiers: Option<Vec<LockTier>>,
 ) -> HandleResult {
     let mut config = Config::load(&deps.storage)?;
     require_admin(&config, &env)?;
@@ -897,6 +1727,11 @@ fn change_config<S: Storage, A: Api, Q: Querier>(
         config.max_multiplier = max_multiplier.u128();
     }
 
+    if let Some(lock_tiers) = lock_tiers {
+        // Like the max multiplier, existing positions keep the bonus they were created with
+        LockTiers::save(&mut deps.storage, lock_tiers)?;
+    }
+
     config.save(&mut deps.storage)?;
 
     Ok(HandleResponse {
@@ -959,6 +1794,36 @@ fn permit_queries<S: Storage, A: Api, Q: Querier>(
 
             query_booster_items(deps, account, page_number, page_size)
         }
+        QueryWithPermit::LockPositions {} => {
+            if !permit.check_permission(&TokenPermissions::Balance) {
+                return Err(StdError::generic_err(format!(
+                    "No permission to query lock positions, got permissions {:?}",
+                    permit.params.permissions
+                )));
+            }
+
+            query_lock_positions(deps, account)
+        }
+        QueryWithPermit::Delegations {} => {
+            if !permit.check_permission(&TokenPermissions::Balance) {
+                return Err(StdError::generic_err(format!(
+                    "No permission to query delegations, got permissions {:?}",
+                    permit.params.permissions
+                )));
+            }
+
+            query_delegations(deps, account)
+        }
+        QueryWithPermit::PayerDelegations {} => {
+            if !permit.check_permission(&TokenPermissions::Balance) {
+                return Err(StdError::generic_err(format!(
+                    "No permission to query delegations, got permissions {:?}",
+                    permit.params.permissions
+                )));
+            }
+
+            query_payer_delegations(deps, account)
+        }
     }
 }
 
@@ -983,6 +1848,30 @@ fn query_pending_rewards<S: Storage, A: Api, Q: Querier>(
     let new_reward_pool = update_rewards(&deps.storage, block)?;
     let user_balance = UserBalance::load(&deps.storage, address)?.unwrap_or_default();
 
+    let debts = RewardTokenDebts::load(&deps.storage, address)?;
+    let mut token_rewards = vec![];
+    let reward_tokens = RewardTokens::load(&deps.storage)?;
+    for mut reward_token in reward_tokens.tokens {
+        update_reward_token(&mut reward_token, new_reward_pool.total_weight, block);
+        let rewards = U256::from(user_balance.weight)
+            * U256::from(reward_token.acc_reward_per_share)
+            / U256::from(REWARD_SCALE)
+            - U256::from(debts.get(reward_token.id));
+        token_rewards.push(TokenRewards {
+            token: reward_token.token.address,
+            rewards: Uint128(rewards.as_u128()),
+        });
+    }
+    for removed in reward_tokens.removed {
+        let rewards = removed_token_rewards(&removed, &debts, user_balance.weight);
+        if !rewards.is_zero() {
+            token_rewards.push(TokenRewards {
+                token: removed.token.address,
+                rewards: Uint128(rewards.as_u128()),
+            });
+        }
+    }
+
     to_binary(&QueryAnswer::Rewards {
         rewards: Uint128(
             ((U256::from(user_balance.weight) * U256::from(new_reward_pool.acc_reward_per_share)
@@ -990,6 +1879,7 @@ fn query_pending_rewards<S: Storage, A: Api, Q: Querier>(
                 - U256::from(user_balance.debt))
             .as_u128(),
         ),
+        token_rewards,
     })
 }
 
@@ -1011,6 +1901,7 @@ fn query_deposit<S: Storage, A: Api, Q: Querier>(
         amount: Uint128(user_balance.locked),
         total_multiplier: Uint128(user_balance.total_multiplier),
         effective_multiplier: Uint128(effective_multiplier),
+        auto_compound: AutoCompound::load(&deps.storage, address)?,
     })
 }
 
@@ -1040,6 +1931,81 @@ fn query_inflation_schedule<S: Storage, A: Api, Q: Querier>(deps: &Extern<S, A,
     to_binary(&QueryAnswer::InflationSchedule { inflation_schedule })
 }
 
+fn query_lock_tiers<S: Storage, A: Api, Q: Querier>(deps: &Extern<S, A, Q>) -> QueryResult {
+    to_binary(&QueryAnswer::LockTiers {
+        tiers: LockTiers::load(&deps.storage)?,
+    })
+}
+
+fn query_lock_positions<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    address: &HumanAddr,
+) -> QueryResult {
+    let positions = LockPositions::load(&deps.storage, address)?;
+
+    to_binary(&QueryAnswer::LockPositions {
+        positions: positions
+            .positions
+            .iter()
+            .map(|p| p.from_stored())
+            .collect(),
+    })
+}
+
+fn query_reward_tokens<S: Storage, A: Api, Q: Querier>(deps: &Extern<S, A, Q>) -> QueryResult {
+    let tokens = RewardTokens::load(&deps.storage)?
+        .tokens
+        .into_iter()
+        .map(|t| RewardTokenInfo {
+            token: t.token,
+            inflation_schedule: t.schedule.iter().map(|u| u.from_stored()).collect(),
+        })
+        .collect();
+
+    to_binary(&QueryAnswer::RewardTokens { tokens })
+}
+
+fn query_delegations<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    address: &HumanAddr,
+) -> QueryResult {
+    let delegations = Delegations::load(&deps.storage, address)?;
+
+    to_binary(&QueryAnswer::Delegations {
+        delegations: delegations
+            .delegations
+            .iter()
+            .map(|d| d.from_stored())
+            .collect(),
+    })
+}
+
+fn query_payer_delegations<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    payer: &HumanAddr,
+) -> QueryResult {
+    let mut delegations = vec![];
+    for beneficiary in PayerDelegations::load(&deps.storage, payer)? {
+        let stored = Delegations::load(&deps.storage, &beneficiary)?;
+        if let Some(delegation) = stored.delegations.iter().find(|d| &d.payer == payer) {
+            delegations.push(PayerDelegation {
+                beneficiary,
+                id: delegation.id,
+                amount: Uint128(delegation.amount),
+            });
+        }
+    }
+
+    to_binary(&QueryAnswer::PayerDelegations { delegations })
+}
+
+fn query_storage_version<S: Storage, A: Api, Q: Querier>(deps: &Extern<S, A, Q>) -> QueryResult {
+    to_binary(&QueryAnswer::StorageVersion {
+        version: load_version(&deps.storage)?,
+        latest: STORAGE_VERSION,
+    })
+}
+
 fn query_total_locked<S: Storage, A: Api, Q: Querier>(deps: &Extern<S, A, Q>) -> QueryResult {
     let reward_pool = RewardPool::load(&deps.storage)?;
 
@@ -1137,6 +2103,139 @@ fn update_rewards<S: ReadonlyStorage>(storage: &S, block: u64) -> StdResult<Rewa
     Ok(reward_pool)
 }
 
+/// Like `update_rewards`, for a reward token other than `Config::token`
+fn update_reward_token(reward_token: &mut RewardToken, total_weight: u128, block: u64) {
+    let new_rewards = InflationSchedule::inflation_of(
+        &reward_token.schedule,
+        reward_token.last_reward_block,
+        block,
+    );
+
+    if new_rewards == 0 {
+        return;
+    }
+
+    if total_weight == 0 {
+        reward_token.last_reward_block = block;
+        reward_token.residue += new_rewards;
+        return;
+    }
+
+    reward_token.acc_reward_per_share +=
+        (new_rewards + reward_token.residue) * REWARD_SCALE / total_weight;
+    reward_token.residue = 0;
+    reward_token.last_reward_block = block;
+}
+
+/// Accumulates the rewards of the reward tokens other than `Config::token` and sends `recipient`
+/// the rewards the user earned with `current_weight`. Has to be called before
+/// `RewardPool::total_weight` changes
+fn settle_reward_tokens<S: Storage>(
+    storage: &mut S,
+    block: u64,
+    total_weight: u128,
+    user: &HumanAddr,
+    recipient: &HumanAddr,
+    current_weight: u128,
+    new_weight: u128,
+) -> StdResult<Vec<CosmosMsg>> {
+    let mut reward_tokens = RewardTokens::load(storage)?;
+    let mut debts = RewardTokenDebts::load(storage, user)?;
+    if reward_tokens.tokens.is_empty() && reward_tokens.removed.is_empty() {
+        return Ok(vec![]);
+    }
+
+    let mut messages = vec![];
+    let mut new_debts = vec![];
+    for reward_token in reward_tokens.tokens.iter_mut() {
+        update_reward_token(reward_token, total_weight, block);
+
+        let acc_reward_per_share = U256::from(reward_token.acc_reward_per_share);
+        let rewards = U256::from(current_weight) * acc_reward_per_share / U256::from(REWARD_SCALE)
+            - U256::from(debts.get(reward_token.id));
+        if !rewards.is_zero() {
+            messages.push(snip20::transfer_msg(
+                recipient.clone(),
+                Uint128(rewards.as_u128()),
+                None,
+                None,
+                RESPONSE_BLOCK_SIZE,
+                reward_token.token.hash.clone(),
+                reward_token.token.address.clone(),
+            )?);
+        }
+
+        let debt = U256::from(new_weight) * acc_reward_per_share / U256::from(REWARD_SCALE);
+        new_debts.push((reward_token.id, debt.as_u128()));
+    }
+
+    // Removed tokens may fail their transfers, so their rewards wait for `ClaimRemovedRewardToken`
+    let mut unclaimed = vec![];
+    for removed in reward_tokens.removed.iter() {
+        let rewards = removed_token_rewards(removed, &debts, current_weight);
+        if !rewards.is_zero() {
+            unclaimed.push((removed.id, rewards.as_u128()));
+        }
+
+        let debt = U256::from(new_weight) * U256::from(removed.acc_reward_per_share)
+            / U256::from(REWARD_SCALE);
+        new_debts.push((removed.id, debt.as_u128()));
+    }
+
+    debts.debts = new_debts;
+    debts.unclaimed = unclaimed;
+    debts.save(storage, user)?;
+    reward_tokens.save(storage)?;
+
+    Ok(messages)
+}
+
+/// Rewards of a user in a removed reward token, earned with `weight`
+fn removed_token_rewards(
+    removed: &RemovedRewardToken,
+    debts: &RewardTokenDebts,
+    weight: u128,
+) -> U256 {
+    U256::from(weight) * U256::from(removed.acc_reward_per_share) / U256::from(REWARD_SCALE)
+        - U256::from(debts.get(removed.id))
+        + U256::from(debts.get_unclaimed(removed.id))
+}
+
+/// Adds the rewards of a user to its locked tokens if it enabled auto compounding. Returns the
+/// rewards left to send to the platform. The caller has to update the weight of the user
+fn compound_rewards<S: ReadonlyStorage>(
+    storage: &S,
+    user: &HumanAddr,
+    reward_pool: &mut RewardPool,
+    user_balance: &mut UserBalance,
+    rewards: u128,
+) -> StdResult<u128> {
+    if rewards == 0 || !AutoCompound::load(storage, user)? {
+        return Ok(rewards);
+    }
+
+    user_balance.locked += rewards;
+    reward_pool.total_locked += rewards;
+    Ok(0)
+}
+
+/// Weight of the tokens of a user. The booster multiplier applies to all of them, and the tokens
+/// of lock positions are further multiplied by the bonus of their position
+fn user_weight(
+    user_balance: &UserBalance,
+    positions: &LockPositions,
+    max_multiplier: u128,
+) -> U256 {
+    let real_multiplier = min(user_balance.total_multiplier, max_multiplier);
+    let lock_bonus = positions.positions.iter().fold(U256::zero(), |bonus, p| {
+        bonus + U256::from(p.amount) * U256::from(p.bonus - MULTIPLIER_SCALE)
+    });
+
+    (U256::from(user_balance.locked) * U256::from(MULTIPLIER_SCALE) + lock_bonus)
+        * U256::from(real_multiplier)
+        / U256::from(MULTIPLIER_SCALE)
+}
+
 fn leave_n_most_significant_digits(num: u128, digits: u32) -> u128 {
     let base = 10_u128;
 
@@ -1,5 +1,6 @@
 mod constants;
 pub mod contract;
+pub mod migration;
 pub mod msg;
 mod staking_unittests;
 pub mod state;
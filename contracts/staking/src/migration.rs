@@ -0,0 +1,71 @@
+use cosmwasm_std::{HumanAddr, ReadonlyStorage, StdError, StdResult, Storage};
+use secret_toolkit::storage::{TypedStore, TypedStoreMut};
+
+use crate::state::{Config, InflationSchedule, RewardPool};
+
+const KEY_STORAGE_VERSION: &[u8] = b"storage_version";
+
+/// Version of the storage layout this code works with, 0 being the unversioned storage
+pub const STORAGE_VERSION: u32 = 1;
+
+pub fn load_version<S: ReadonlyStorage>(storage: &S) -> StdResult<u32> {
+    Ok(TypedStore::attach(storage)
+        .may_load(KEY_STORAGE_VERSION)?
+        .unwrap_or_default())
+}
+
+pub fn save_version<S: Storage>(storage: &mut S, version: u32) -> StdResult<()> {
+    TypedStoreMut::attach(storage).store(KEY_STORAGE_VERSION, &version)
+}
+
+/// Handles (but `Migrate`) and queries require the storage to be up to date
+pub fn require_current_version<S: ReadonlyStorage>(storage: &S) -> StdResult<()> {
+    let version = load_version(storage)?;
+    if version != STORAGE_VERSION {
+        return Err(StdError::generic_err(format!(
+            "storage is at version {}, expected {}. The admin has to migrate it first",
+            version, STORAGE_VERSION
+        )));
+    }
+
+    Ok(())
+}
+
+pub fn load_admin<S: ReadonlyStorage>(storage: &S) -> StdResult<HumanAddr> {
+    match load_version(storage)? {
+        0 | 1 => Ok(Config::load(storage)?.admin),
+        version => Err(unknown_version(version)),
+    }
+}
+
+/// Upgrades the storage one version at a time up to `STORAGE_VERSION`. Returns the version it
+/// started from
+pub fn migrate<S: Storage>(storage: &mut S) -> StdResult<u32> {
+    let from = load_version(storage)?;
+    if from > STORAGE_VERSION {
+        return Err(unknown_version(from));
+    }
+
+    for version in from..STORAGE_VERSION {
+        match version {
+            0 => migrate_v0_to_v1(storage)?,
+            _ => return Err(unknown_version(version)),
+        }
+        save_version(storage, version + 1)?;
+    }
+
+    Ok(from)
+}
+
+fn unknown_version(version: u32) -> StdError {
+    StdError::generic_err(format!("can't migrate from storage version {}", version))
+}
+
+/// Only marks the storage as versioned, after checking that the config, reward pool and inflation
+/// schedule decode
+fn migrate_v0_to_v1<S: Storage>(storage: &mut S) -> StdResult<()> {
+    Config::load(storage)?.save(storage)?;
+    RewardPool::load(storage)?.save(storage)?;
+    let schedule = InflationSchedule::load(storage)?;
+    InflationSchedule::save(storage, schedule)
+}
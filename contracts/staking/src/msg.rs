@@ -1,4 +1,6 @@
-use crate::state::{BoosterItemInInventory, Features, ScheduleUnit};
+use crate::state::{
+    BoosterItemInInventory, Delegation, Features, LockPosition, LockTier, ScheduleUnit,
+};
 use cosmwasm_std::{Binary, HumanAddr, Uint128};
 use schemars::JsonSchema;
 use secret_toolkit::permit::Permit;
@@ -22,8 +24,10 @@ pub struct InitMsg {
 #[derive(Deserialize, JsonSchema)]
 #[serde(rename_all = "snake_case")]
 pub enum HandleMsg {
+    /// Withdraw tokens that aren't in a lock position, or from the lock position `position`
     Withdraw {
         amount: Option<Uint128>,
+        position: Option<u64>,
     },
     CreateViewingKey {
         entropy: String,
@@ -35,6 +39,34 @@ pub enum HandleMsg {
     },
     EmergencyWithdraw {},
     EmergencyWithdrawSkipPlatform {},
+    /// Adds the pending rewards to the locked tokens instead of sending them to the platform
+    Compound {},
+    /// When enabled, pending rewards are compounded instead of sent to the platform whenever the
+    /// balance of the account changes
+    SetAutoCompound {
+        enabled: bool,
+    },
+    /// Pays out the pending rewards to `destination`, for `to` (the sender if not specified).
+    /// Rewards in the other reward tokens are always sent to the wallet of `to`
+    ClaimRewards {
+        to: Option<HumanAddr>,
+        destination: RewardDestination,
+    },
+    /// Returns the tokens of a delegation to its payer. Either the payer or the beneficiary can
+    /// do it, the rewards they earned so far stay with the beneficiary
+    RevokeDelegation {
+        beneficiary: HumanAddr,
+        id: u64,
+    },
+    /// Drops the expired lock positions of `address`, so their bonus stops counting in the total
+    /// weight. Anyone can call it, the pending rewards of `address` are paid out to it
+    ReleaseExpiredLocks {
+        address: HumanAddr,
+    },
+    /// Sends the sender its rewards in a reward token that was removed
+    ClaimRemovedRewardToken {
+        token: HumanAddr,
+    },
 
     // Registered commands
     Receive {
@@ -78,7 +110,35 @@ pub enum HandleMsg {
         token_vk: Option<String>,
         inflation: Option<Vec<ScheduleUnit>>,
         max_multiplier: Option<Uint128>,
+        lock_tiers: Option<Vec<LockTier>>,
+    },
+    /// Distributes `token` as rewards too, by its own schedule. The rewards have to be sent to
+    /// this contract separately.
+    ///
+    /// Every change of a weight transfers the rewards in `token`, so if its transfers fail, the
+    /// deposits, withdrawals and claims of all the users fail until the admin removes it
+    AddRewardToken {
+        token: Contract,
+        inflation_schedule: Vec<ScheduleUnit>,
+    },
+    ChangeRewardTokenSchedule {
+        token: HumanAddr,
+        inflation_schedule: Vec<ScheduleUnit>,
     },
+    /// Stops distributing `token`. The users claim the rewards they earned until then with
+    /// `ClaimRemovedRewardToken`
+    RemoveRewardToken {
+        token: HumanAddr,
+    },
+    /// Sends `recipient` the rewards of a removed reward token that weren't distributed, up to
+    /// `amount` if specified
+    SweepRewardToken {
+        token: HumanAddr,
+        recipient: HumanAddr,
+        amount: Option<Uint128>,
+    },
+    /// Upgrades the storage to the version of the contract's code
+    Migrate {},
 }
 
 #[derive(Serialize, JsonSchema)]
@@ -99,6 +159,28 @@ pub enum HandleAnswer {
     ApplyMultiplier { status: ResponseStatus },
     DropMultiplier { status: ResponseStatus },
     ChangeConfig { status: ResponseStatus },
+    Migrate { status: ResponseStatus },
+    Compound { status: ResponseStatus },
+    SetAutoCompound { status: ResponseStatus },
+    ClaimRewards { status: ResponseStatus },
+    RevokeDelegation { status: ResponseStatus },
+    ReleaseExpiredLocks { status: ResponseStatus },
+    ClaimRemovedRewardToken { status: ResponseStatus },
+    AddRewardToken { status: ResponseStatus },
+    ChangeRewardTokenSchedule { status: ResponseStatus },
+    RemoveRewardToken { status: ResponseStatus },
+    SweepRewardToken { status: ResponseStatus },
+}
+
+#[derive(Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum RewardDestination {
+    /// Deposited to the platform, like the rewards paid out by the other handles
+    Platform,
+    /// Sent to the wallet
+    Wallet,
+    /// Added to the locked tokens of the sender. `to` has to be the sender
+    Restake,
 }
 
 #[derive(Deserialize, JsonSchema)]
@@ -112,7 +194,15 @@ pub enum ReceiveMsg {
 
 #[derive(Deserialize, JsonSchema)]
 pub enum ReceiveFromPlatformMsg {
-    Deposit {},
+    /// Deposit tokens, locked for `lock_duration` seconds (which must be one of the lock tiers) if
+    /// specified
+    Deposit { lock_duration: Option<u64> },
+    /// Deposit tokens for `beneficiary`, who earns their rewards. If `revocable`, the tokens stay
+    /// the sender's: the beneficiary can't withdraw them and the sender can revoke them at any time
+    DepositFor {
+        beneficiary: HumanAddr,
+        revocable: bool,
+    },
 }
 
 #[derive(Deserialize, JsonSchema)]
@@ -132,6 +222,9 @@ pub enum QueryMsg {
     Token {},
     Platform {},
     InflationSchedule {},
+    LockTiers {},
+    RewardTokens {},
+    StorageVersion {},
 
     // Authenticated
     Rewards {
@@ -149,6 +242,20 @@ pub enum QueryMsg {
         page_number: Option<u32>,
         page_size: u32,
     },
+    LockPositions {
+        address: HumanAddr,
+        key: String,
+    },
+    /// Revocable delegations to `address`
+    Delegations {
+        address: HumanAddr,
+        key: String,
+    },
+    /// Revocable delegations paid by `address`
+    PayerDelegations {
+        address: HumanAddr,
+        key: String,
+    },
 
     // Permits
     /// Permit queries. See more: [Permits API](https://github.com/SecretFoundation/SNIPs/blob/master/SNIP-24.md)
@@ -164,6 +271,9 @@ impl QueryMsg {
             QueryMsg::Rewards { address, key, .. } => (address, key.clone()),
             QueryMsg::Balance { address, key } => (address, key.clone()),
             QueryMsg::BoosterItems { address, key, .. } => (address, key.clone()),
+            QueryMsg::LockPositions { address, key } => (address, key.clone()),
+            QueryMsg::Delegations { address, key } => (address, key.clone()),
+            QueryMsg::PayerDelegations { address, key } => (address, key.clone()),
             _ => panic!("This should never happen"),
         }
     }
@@ -181,6 +291,9 @@ pub enum QueryWithPermit {
         page_number: Option<u32>,
         page_size: u32,
     },
+    LockPositions {},
+    Delegations {},
+    PayerDelegations {},
 }
 
 #[derive(Serialize, Deserialize, JsonSchema)]
@@ -192,11 +305,14 @@ pub enum QueryAnswer {
     },
     Rewards {
         rewards: Uint128,
+        /// Rewards in the reward tokens other than `token`
+        token_rewards: Vec<TokenRewards>,
     },
     Balance {
         amount: Uint128,
         total_multiplier: Uint128,
         effective_multiplier: Uint128,
+        auto_compound: bool,
     },
     BoosterItems {
         items: Vec<BoosterItemInInventory>,
@@ -223,12 +339,54 @@ pub enum QueryAnswer {
     InflationSchedule {
         inflation_schedule: Vec<ScheduleUnit>,
     },
+    LockTiers {
+        tiers: Vec<LockTier>,
+    },
+    RewardTokens {
+        tokens: Vec<RewardTokenInfo>,
+    },
+    LockPositions {
+        positions: Vec<LockPosition>,
+    },
+    Delegations {
+        delegations: Vec<Delegation>,
+    },
+    PayerDelegations {
+        delegations: Vec<PayerDelegation>,
+    },
+    StorageVersion {
+        version: u32,
+        latest: u32,
+    },
 
     QueryError {
         msg: String,
     },
 }
 
+#[derive(Serialize, Deserialize, JsonSchema)]
+#[cfg_attr(test, derive(PartialEq, Debug))]
+pub struct TokenRewards {
+    pub token: HumanAddr,
+    pub rewards: Uint128,
+}
+
+/// A delegation, as seen by its payer
+#[derive(Serialize, Deserialize, JsonSchema)]
+#[cfg_attr(test, derive(PartialEq, Debug))]
+pub struct PayerDelegation {
+    pub beneficiary: HumanAddr,
+    pub id: u64,
+    pub amount: Uint128,
+}
+
+#[derive(Serialize, Deserialize, JsonSchema)]
+#[cfg_attr(test, derive(PartialEq, Debug))]
+pub struct RewardTokenInfo {
+    pub token: Contract,
+    pub inflation_schedule: Vec<ScheduleUnit>,
+}
+
 #[derive(Serialize, Deserialize, Clone, PartialEq, JsonSchema, Debug)]
 #[serde(rename_all = "snake_case")]
 pub enum ResponseStatus {
@@ -20,28 +20,40 @@ fn create_subscriber_msg(sub: Contract, user: &HumanAddr, new_stake: u128) -> St
 }
 
 mod tests {
+    use cosmwasm_std::testing::{mock_dependencies, mock_env, MockApi, MockQuerier, MockStorage};
     use cosmwasm_std::StdError::Unauthorized;
     use cosmwasm_std::{
-        from_binary, to_binary, Api, Extern, HumanAddr, Querier, StdResult, Storage, Uint128,
+        from_binary, to_binary, Api, CosmosMsg, Extern, HumanAddr, Querier, StdResult, Storage,
+        Uint128,
     };
+    use cosmwasm_storage::PrefixedStorage;
     use secret_toolkit::snip20;
     use secret_toolkit::utils::types::Contract;
 
     use crate::constants::RESPONSE_BLOCK_SIZE;
-    use crate::contract::query;
+    use crate::contract::{handle, query};
+    use crate::migration::STORAGE_VERSION;
     use crate::msg::ResponseStatus::{NotChanged, Success};
-    use crate::msg::{HandleAnswer, QueryAnswer, QueryMsg, ResponseStatus};
+    use crate::msg::{
+        HandleAnswer, HandleMsg, PayerDelegation, QueryAnswer, QueryMsg, ResponseStatus,
+        RewardDestination, RewardTokenInfo, TokenRewards,
+    };
     use crate::staking_unittests::create_subscriber_msg;
     use crate::state::{
-        BoosterItemInInventory, Config, Features, MultiplierContracts, ScheduleUnit,
+        BoosterItemInInventory, Config, Delegation, Features, LockPosition, LockTier,
+        MultiplierContracts, RewardPool, ScheduleUnit,
     };
     use crate::unittest_utils::{
-        add_multiplier_contracts_helper, add_subscriber_contracts_helper, apply_multiplier_helper,
-        change_max_mul_config_helper, deposit_helper, drop_multiplier_helper,
-        emergency_withdraw_helper, emergency_withdraw_skip_platform_helper,
-        extract_generic_error_msg, init_helper, remove_multiplier_contracts_helper,
-        remove_subscriber_contracts_helper, set_viewing_key_helper, unpause_feature_helper,
-        withdraw_helper,
+        add_multiplier_contracts_helper, add_reward_token_helper, add_subscriber_contracts_helper,
+        apply_multiplier_helper, change_lock_tiers_config_helper, change_max_mul_config_helper,
+        claim_rewards_helper, compound_helper, deposit_for_helper, deposit_helper,
+        drop_multiplier_helper, emergency_withdraw_helper, emergency_withdraw_skip_platform_helper,
+        extract_generic_error_msg, init_helper, lock_deposit_helper, migrate_helper,
+        mock_env_with_height, mock_env_with_height_and_time, release_expired_locks_helper,
+        remove_multiplier_contracts_helper, remove_subscriber_contracts_helper,
+        revoke_delegation_helper, set_auto_compound_helper, set_viewing_key_helper,
+        sweep_reward_token_helper, unpause_feature_helper, withdraw_all_helper, withdraw_helper,
+        withdraw_position_helper,
     };
 
     fn assert_total_locked<S: Storage, A: Api, Q: Querier>(
@@ -113,12 +125,15 @@ mod tests {
 
         let rewards_answer = from_binary::<QueryAnswer>(&query_response)?;
         // println!("{:?}", rewards_answer);
-        assert_eq!(
-            rewards_answer,
-            QueryAnswer::Rewards {
-                rewards: Uint128(rewards)
-            }
-        );
+        if let QueryAnswer::Rewards {
+            rewards: actual_rewards,
+            ..
+        } = rewards_answer
+        {
+            assert_eq!(actual_rewards, Uint128(rewards));
+        } else {
+            panic!("wrong queryAnswer variant");
+        }
 
         let query_response = query(
             &deps,
@@ -1155,4 +1170,938 @@ mod tests {
 
         Ok(())
     }
+
+    fn lock_tiers() -> Vec<LockTier> {
+        vec![
+            LockTier {
+                duration: 100,
+                bonus: Uint128(150_000),
+                early_withdrawal_penalty_bps: None,
+            },
+            LockTier {
+                duration: 1000,
+                bonus: Uint128(200_000),
+                early_withdrawal_penalty_bps: Some(1000),
+            },
+        ]
+    }
+
+    fn assert_lock_positions<S: Storage, A: Api, Q: Querier>(
+        deps: &Extern<S, A, Q>,
+        user: &str,
+        expected_positions: Vec<LockPosition>,
+    ) -> StdResult<()> {
+        let query_response = query(
+            deps,
+            QueryMsg::LockPositions {
+                address: HumanAddr::from(user),
+                key: "viewkey".to_string(),
+            },
+        )?;
+
+        assert_eq!(
+            from_binary::<QueryAnswer>(&query_response)?,
+            QueryAnswer::LockPositions {
+                positions: expected_positions
+            }
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_change_lock_tiers_config() -> StdResult<()> {
+        let mut deps = init_helper(None, None, None, None)?;
+
+        let mut tiers = lock_tiers();
+        tiers[0].bonus = Uint128(99_999);
+        assert_eq!(
+            extract_generic_error_msg(change_lock_tiers_config_helper(&mut deps, tiers)),
+            "lock tier bonus cannot be smaller than 1"
+        );
+
+        let mut tiers = lock_tiers();
+        tiers[1].duration = 100;
+        assert_eq!(
+            extract_generic_error_msg(change_lock_tiers_config_helper(&mut deps, tiers)),
+            "duplicate lock tier of 100 seconds"
+        );
+
+        change_lock_tiers_config_helper(&mut deps, lock_tiers())?;
+        assert_eq!(
+            from_binary::<QueryAnswer>(&query(&deps, QueryMsg::LockTiers {})?)?,
+            QueryAnswer::LockTiers {
+                tiers: lock_tiers()
+            }
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_lock_positions_weight() -> StdResult<()> {
+        let mut deps = init_helper(None, None, None, Some(300_000))?;
+        change_lock_tiers_config_helper(&mut deps, lock_tiers())?;
+        set_viewing_key_helper(&mut deps, "whale", "viewkey")?;
+
+        deposit_helper(&mut deps, "user", 1000, Some(0))?;
+        lock_deposit_helper(&mut deps, "whale", 1000, Some(0), Some(100), 12_345)?;
+        assert_total_locked(&deps, 2000, 100_000_000 + 150_000_000)?;
+        assert_lock_positions(
+            &deps,
+            "whale",
+            vec![LockPosition {
+                id: 0,
+                amount: Uint128(1000),
+                bonus: Uint128(150_000),
+                unlock_ts: 12_445,
+                early_withdrawal_penalty_bps: None,
+            }],
+        )?;
+
+        assert_eq!(
+            extract_generic_error_msg(lock_deposit_helper(
+                &mut deps,
+                "whale",
+                1000,
+                Some(0),
+                Some(50),
+                12_345
+            )),
+            "there is no lock tier of 50 seconds"
+        );
+
+        // the bonus of the position stacks with the boosters
+        add_multiplier_contracts_helper(&mut deps, "admin", vec![HumanAddr::from("c")])?;
+        apply_multiplier_helper(&mut deps, "c", 200_000, Some(0), "id", "whale")?;
+        assert_total_locked(&deps, 2000, 100_000_000 + 300_000_000)?;
+
+        assert_eq!(
+            extract_generic_error_msg(withdraw_helper(&mut deps, "whale", 1, Some(0))),
+            "insufficient funds to redeem: balance=0, required=1"
+        );
+        assert_eq!(
+            extract_generic_error_msg(withdraw_position_helper(
+                &mut deps,
+                "whale",
+                None,
+                0,
+                Some(0),
+                12_444
+            )),
+            "lock position 0 is locked until 12445"
+        );
+
+        // expired positions are withdrawn without a penalty and lose their bonus
+        withdraw_position_helper(&mut deps, "whale", Some(400), 0, Some(0), 12_445)?;
+        assert_total_locked(&deps, 1600, 100_000_000 + 120_000_000)?;
+        assert_lock_positions(&deps, "whale", vec![])?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_release_expired_locks() -> StdResult<()> {
+        let mut deps = init_helper(None, None, Some(vec![ScheduleUnit::new(20, 1000)]), None)?;
+        change_lock_tiers_config_helper(&mut deps, lock_tiers())?;
+        set_viewing_key_helper(&mut deps, "user", "viewkey")?;
+
+        deposit_helper(&mut deps, "user", 1000, Some(0))?;
+        lock_deposit_helper(&mut deps, "whale", 1000, Some(0), Some(100), 12_345)?;
+
+        let (_, answer) =
+            release_expired_locks_helper(&mut deps, "keeper", "whale", Some(5), 12_444)?;
+        assert_eq!(
+            answer,
+            HandleAnswer::ReleaseExpiredLocks { status: NotChanged }
+        );
+
+        // anyone can drop the bonus of the expired lock, the whale's rewards are paid out
+        let (messages, answer) =
+            release_expired_locks_helper(&mut deps, "keeper", "whale", Some(10), 12_445)?;
+        assert_eq!(
+            answer,
+            HandleAnswer::ReleaseExpiredLocks { status: Success }
+        );
+        assert_eq!(
+            messages,
+            vec![snip20::send_msg(
+                HumanAddr::from("platform"),
+                Uint128(6000),
+                Some(to_binary(&platform::msg::ReceiveMsg::Deposit {
+                    to: HumanAddr::from("whale")
+                })?),
+                None,
+                None,
+                RESPONSE_BLOCK_SIZE,
+                "".to_string(),
+                HumanAddr::from("token"),
+            )?]
+        );
+        assert_total_locked(&deps, 2000, 200_000_000)?;
+
+        // the user's rewards are no longer diluted by the bonus
+        assert_rewards_balance(&deps, "user", 1000, 100_000, 4000, 10)?;
+        assert_rewards_balance(&deps, "user", 1000, 100_000, 9000, 20)?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_lock_position_early_withdrawal_penalty() -> StdResult<()> {
+        let mut deps = init_helper(None, None, None, None)?;
+        change_lock_tiers_config_helper(&mut deps, lock_tiers())?;
+        set_viewing_key_helper(&mut deps, "user", "viewkey")?;
+
+        deposit_helper(&mut deps, "user", 1000, Some(0))?;
+        lock_deposit_helper(&mut deps, "whale", 1000, Some(0), Some(1000), 12_345)?;
+        assert_total_locked(&deps, 2000, 100_000_000 + 200_000_000)?;
+
+        let (messages, answer) =
+            withdraw_position_helper(&mut deps, "whale", Some(600), 0, Some(0), 12_845)?;
+        assert_eq!(
+            messages[0],
+            snip20::send_msg(
+                HumanAddr::from("platform"),
+                Uint128(540), // withdrawn amount - 10% penalty
+                Some(to_binary(&platform::msg::ReceiveMsg::Deposit {
+                    to: HumanAddr::from("whale")
+                })?),
+                None,
+                None,
+                RESPONSE_BLOCK_SIZE,
+                "".to_string(),
+                HumanAddr::from("token"),
+            )?,
+        );
+        assert_eq!(answer, HandleAnswer::Redeem { status: Success });
+        assert_total_locked(&deps, 1400, 100_000_000 + 80_000_000)?;
+
+        // the penalty is distributed right away, even without inflation
+        assert_eq!(RewardPool::load(&deps.storage)?.residue, 0);
+        assert_rewards_balance(&deps, "user", 1000, 100_000, 33, 0)?;
+
+        withdraw_position_helper(&mut deps, "whale", None, 0, Some(0), 12_845)?;
+        assert_rewards_balance(&deps, "user", 1000, 100_000, 73, 0)?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_compound() -> StdResult<()> {
+        let mut deps = init_helper(None, None, Some(vec![ScheduleUnit::new(10, 1000)]), None)?;
+        set_viewing_key_helper(&mut deps, "user", "viewkey")?;
+
+        deposit_helper(&mut deps, "user", 1000, Some(0))?;
+        deposit_helper(&mut deps, "whale", 1000, Some(0))?;
+        assert_rewards_balance(&deps, "user", 1000, 100_000, 1000, 2)?;
+
+        let (messages, answer) = compound_helper(&mut deps, "user", Some(2))?;
+        assert_eq!(messages, vec![]);
+        assert_eq!(answer, HandleAnswer::Compound { status: Success });
+        assert_rewards_balance(&deps, "user", 2000, 100_000, 0, 2)?;
+        assert_total_locked(&deps, 3000, 300_000_000)?;
+
+        let (_, answer) = compound_helper(&mut deps, "user", Some(2))?;
+        assert_eq!(answer, HandleAnswer::Compound { status: NotChanged });
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_claim_rewards() -> StdResult<()> {
+        let mut deps = init_helper(None, None, Some(vec![ScheduleUnit::new(10, 1000)]), None)?;
+        set_viewing_key_helper(&mut deps, "user", "viewkey")?;
+
+        deposit_helper(&mut deps, "user", 1000, Some(0))?;
+        deposit_helper(&mut deps, "whale", 1000, Some(0))?;
+
+        let (messages, answer) =
+            claim_rewards_helper(&mut deps, "user", None, RewardDestination::Wallet, Some(2))?;
+        assert_eq!(
+            messages,
+            vec![snip20::transfer_msg(
+                HumanAddr::from("user"),
+                Uint128(1000),
+                None,
+                None,
+                RESPONSE_BLOCK_SIZE,
+                "".to_string(),
+                HumanAddr::from("token"),
+            )?]
+        );
+        assert_eq!(answer, HandleAnswer::ClaimRewards { status: Success });
+        assert_rewards_balance(&deps, "user", 1000, 100_000, 0, 2)?;
+
+        let (messages, _) = claim_rewards_helper(
+            &mut deps,
+            "user",
+            Some("friend"),
+            RewardDestination::Platform,
+            Some(4),
+        )?;
+        assert_eq!(
+            messages,
+            vec![snip20::send_msg(
+                HumanAddr::from("platform"),
+                Uint128(1000),
+                Some(to_binary(&platform::msg::ReceiveMsg::Deposit {
+                    to: HumanAddr::from("friend")
+                })?),
+                None,
+                None,
+                RESPONSE_BLOCK_SIZE,
+                "".to_string(),
+                HumanAddr::from("token"),
+            )?]
+        );
+
+        assert_eq!(
+            extract_generic_error_msg(claim_rewards_helper(
+                &mut deps,
+                "user",
+                Some("friend"),
+                RewardDestination::Restake,
+                Some(6)
+            )),
+            "rewards can only be restaked by their owner"
+        );
+        let (messages, _) =
+            claim_rewards_helper(&mut deps, "user", None, RewardDestination::Restake, Some(6))?;
+        assert_eq!(messages, vec![]);
+        assert_rewards_balance(&deps, "user", 2000, 100_000, 0, 6)?;
+        assert_total_locked(&deps, 3000, 300_000_000)?;
+
+        // nothing is saved nor sent to the subscribers without rewards
+        add_subscriber_contracts_helper(
+            &mut deps,
+            "admin",
+            vec![Contract {
+                address: HumanAddr::from("sub"),
+                hash: "hash".to_string(),
+            }],
+        )?;
+        let (messages, answer) =
+            claim_rewards_helper(&mut deps, "user", None, RewardDestination::Wallet, Some(6))?;
+        assert_eq!(messages, vec![]);
+        assert_eq!(answer, HandleAnswer::ClaimRewards { status: NotChanged });
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_auto_compound() -> StdResult<()> {
+        let mut deps = init_helper(None, None, Some(vec![ScheduleUnit::new(10, 1000)]), None)?;
+        set_viewing_key_helper(&mut deps, "user", "viewkey")?;
+
+        deposit_helper(&mut deps, "user", 1000, Some(0))?;
+        deposit_helper(&mut deps, "whale", 1000, Some(0))?;
+        assert_eq!(
+            set_auto_compound_helper(&mut deps, "user", true)?,
+            HandleAnswer::SetAutoCompound { status: Success }
+        );
+        let balance = from_binary::<QueryAnswer>(&query(
+            &deps,
+            QueryMsg::Balance {
+                address: HumanAddr::from("user"),
+                key: "viewkey".to_string(),
+            },
+        )?)?;
+        if let QueryAnswer::Balance { auto_compound, .. } = balance {
+            assert!(auto_compound);
+        } else {
+            panic!("wrong queryAnswer variant");
+        }
+
+        // the rewards are compounded instead of sent
+        let (messages, _) = deposit_helper(&mut deps, "user", 500, Some(2))?;
+        assert_eq!(messages, vec![]);
+        assert_rewards_balance(&deps, "user", 2500, 100_000, 0, 2)?;
+
+        // while the withdrawn tokens are sent as usual
+        let (messages, _) = withdraw_helper(&mut deps, "user", 100, Some(2))?;
+        assert_eq!(
+            messages,
+            vec![snip20::send_msg(
+                HumanAddr::from("platform"),
+                Uint128(100),
+                Some(to_binary(&platform::msg::ReceiveMsg::Deposit {
+                    to: HumanAddr::from("user")
+                })?),
+                None,
+                None,
+                RESPONSE_BLOCK_SIZE,
+                "".to_string(),
+                HumanAddr::from("token"),
+            )?]
+        );
+
+        // other users keep receiving their rewards
+        let (messages, _) = deposit_helper(&mut deps, "whale", 500, Some(2))?;
+        assert_eq!(messages.len(), 1);
+        assert_total_locked(&deps, 3900, 390_000_000)?;
+
+        // withdrawing everything includes the rewards compounded along the way
+        let (messages, _) = withdraw_all_helper(&mut deps, "user", Some(4))?;
+        assert_eq!(
+            messages,
+            vec![snip20::send_msg(
+                HumanAddr::from("platform"),
+                Uint128(2400 + 1230),
+                Some(to_binary(&platform::msg::ReceiveMsg::Deposit {
+                    to: HumanAddr::from("user")
+                })?),
+                None,
+                None,
+                RESPONSE_BLOCK_SIZE,
+                "".to_string(),
+                HumanAddr::from("token"),
+            )?]
+        );
+        assert_rewards_balance(&deps, "user", 0, 100_000, 0, 4)?;
+
+        Ok(())
+    }
+
+    fn assert_token_rewards<S: Storage, A: Api, Q: Querier>(
+        deps: &Extern<S, A, Q>,
+        user: &str,
+        height: u64,
+        expected_token_rewards: Vec<(&str, u128)>,
+    ) -> StdResult<()> {
+        let query_response = query(
+            deps,
+            QueryMsg::Rewards {
+                address: HumanAddr::from(user),
+                key: "viewkey".to_string(),
+                height,
+            },
+        )?;
+
+        if let QueryAnswer::Rewards { token_rewards, .. } = from_binary(&query_response)? {
+            let expected_token_rewards: Vec<TokenRewards> = expected_token_rewards
+                .into_iter()
+                .map(|(token, rewards)| TokenRewards {
+                    token: HumanAddr::from(token),
+                    rewards: Uint128(rewards),
+                })
+                .collect();
+            assert_eq!(token_rewards, expected_token_rewards);
+        } else {
+            panic!("wrong queryAnswer variant");
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_add_reward_token() -> StdResult<()> {
+        let mut deps = init_helper(None, None, None, None)?;
+
+        let result = handle(
+            &mut deps,
+            mock_env("user", &[]),
+            HandleMsg::AddRewardToken {
+                token: Contract {
+                    address: HumanAddr::from("partner"),
+                    hash: "hash".to_string(),
+                },
+                inflation_schedule: vec![],
+            },
+        );
+        assert_eq!(extract_generic_error_msg(result), "not an admin: user");
+
+        assert_eq!(
+            extract_generic_error_msg(add_reward_token_helper(&mut deps, "token", vec![], None)),
+            "token is already a reward token"
+        );
+
+        add_reward_token_helper(&mut deps, "partner", vec![ScheduleUnit::new(10, 100)], None)?;
+        assert_eq!(
+            extract_generic_error_msg(add_reward_token_helper(&mut deps, "partner", vec![], None)),
+            "partner is already a reward token"
+        );
+        assert_eq!(
+            from_binary::<QueryAnswer>(&query(&deps, QueryMsg::RewardTokens {})?)?,
+            QueryAnswer::RewardTokens {
+                tokens: vec![RewardTokenInfo {
+                    token: Contract {
+                        address: HumanAddr::from("partner"),
+                        hash: "hash".to_string(),
+                    },
+                    inflation_schedule: vec![ScheduleUnit::new(10, 100)],
+                }]
+            }
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_reward_tokens() -> StdResult<()> {
+        let mut deps = init_helper(None, None, Some(vec![ScheduleUnit::new(10, 1000)]), None)?;
+        set_viewing_key_helper(&mut deps, "user", "viewkey")?;
+        set_viewing_key_helper(&mut deps, "whale", "viewkey")?;
+
+        deposit_helper(&mut deps, "user", 1000, Some(0))?;
+        deposit_helper(&mut deps, "whale", 1000, Some(0))?;
+
+        // stakers earn the reward token from its registration on
+        add_reward_token_helper(
+            &mut deps,
+            "partner",
+            vec![ScheduleUnit::new(10, 100)],
+            Some(2),
+        )?;
+        assert_token_rewards(&deps, "user", 4, vec![("partner", 100)])?;
+        assert_rewards_balance(&deps, "user", 1000, 100_000, 2000, 4)?;
+
+        let (messages, _) = deposit_helper(&mut deps, "user", 1000, Some(4))?;
+        assert_eq!(messages.len(), 2);
+        assert_eq!(
+            messages[1],
+            snip20::transfer_msg(
+                HumanAddr::from("user"),
+                Uint128(100),
+                None,
+                None,
+                RESPONSE_BLOCK_SIZE,
+                "hash".to_string(),
+                HumanAddr::from("partner"),
+            )?
+        );
+        assert_token_rewards(&deps, "user", 4, vec![("partner", 0)])?;
+        assert_token_rewards(&deps, "user", 7, vec![("partner", 200)])?;
+
+        let result = handle(
+            &mut deps,
+            mock_env_with_height("admin", 7),
+            HandleMsg::RemoveRewardToken {
+                token: HumanAddr::from("partner"),
+            },
+        )?;
+        assert_eq!(
+            from_binary::<HandleAnswer>(&result.data.unwrap())?,
+            HandleAnswer::RemoveRewardToken { status: Success }
+        );
+        assert_token_rewards(&deps, "user", 9, vec![("partner", 200)])?;
+        assert_token_rewards(&deps, "whale", 9, vec![("partner", 200)])?;
+
+        // the rewards of a removed token are no longer sent when the weight changes
+        let (messages, _) = deposit_helper(&mut deps, "user", 1000, Some(8))?;
+        assert_eq!(messages.len(), 1);
+        assert_token_rewards(&deps, "user", 9, vec![("partner", 200)])?;
+
+        let claim_removed = |deps: &mut Extern<MockStorage, MockApi, MockQuerier>| {
+            handle(
+                deps,
+                mock_env_with_height("user", 9),
+                HandleMsg::ClaimRemovedRewardToken {
+                    token: HumanAddr::from("partner"),
+                },
+            )
+        };
+        let result = claim_removed(&mut deps)?;
+        assert_eq!(
+            result.messages,
+            vec![snip20::transfer_msg(
+                HumanAddr::from("user"),
+                Uint128(200),
+                None,
+                None,
+                RESPONSE_BLOCK_SIZE,
+                "hash".to_string(),
+                HumanAddr::from("partner"),
+            )?]
+        );
+        assert_token_rewards(&deps, "user", 9, vec![])?;
+        assert_eq!(
+            from_binary::<HandleAnswer>(&claim_removed(&mut deps)?.data.unwrap())?,
+            HandleAnswer::ClaimRemovedRewardToken { status: NotChanged }
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_sweep_reward_token() -> StdResult<()> {
+        let mut deps = init_helper(None, None, None, None)?;
+
+        add_reward_token_helper(
+            &mut deps,
+            "partner",
+            vec![ScheduleUnit::new(10, 100)],
+            Some(2),
+        )?;
+        assert_eq!(
+            extract_generic_error_msg(sweep_reward_token_helper(
+                &mut deps, "admin", "partner", None
+            )),
+            "partner is not a removed reward token"
+        );
+
+        // nobody staked, so nothing was distributed
+        handle(
+            &mut deps,
+            mock_env_with_height("admin", 7),
+            HandleMsg::RemoveRewardToken {
+                token: HumanAddr::from("partner"),
+            },
+        )?;
+        assert_eq!(
+            extract_generic_error_msg(sweep_reward_token_helper(
+                &mut deps, "user", "partner", None
+            )),
+            "not an admin: user"
+        );
+
+        let transfer = |amount| {
+            snip20::transfer_msg(
+                HumanAddr::from("treasury"),
+                Uint128(amount),
+                None,
+                None,
+                RESPONSE_BLOCK_SIZE,
+                "hash".to_string(),
+                HumanAddr::from("partner"),
+            )
+        };
+        assert_eq!(
+            sweep_reward_token_helper(&mut deps, "admin", "partner", Some(300))?,
+            (
+                vec![transfer(300)?],
+                HandleAnswer::SweepRewardToken { status: Success }
+            )
+        );
+        assert_eq!(
+            sweep_reward_token_helper(&mut deps, "admin", "partner", None)?,
+            (
+                vec![transfer(500)?],
+                HandleAnswer::SweepRewardToken { status: Success }
+            )
+        );
+        assert_eq!(
+            sweep_reward_token_helper(&mut deps, "admin", "partner", None)?,
+            (
+                vec![],
+                HandleAnswer::SweepRewardToken { status: NotChanged }
+            )
+        );
+
+        Ok(())
+    }
+
+    fn assert_delegations<S: Storage, A: Api, Q: Querier>(
+        deps: &Extern<S, A, Q>,
+        beneficiary: &str,
+        expected_delegations: Vec<Delegation>,
+    ) -> StdResult<()> {
+        let query_response = query(
+            deps,
+            QueryMsg::Delegations {
+                address: HumanAddr::from(beneficiary),
+                key: "viewkey".to_string(),
+            },
+        )?;
+
+        assert_eq!(
+            from_binary::<QueryAnswer>(&query_response)?,
+            QueryAnswer::Delegations {
+                delegations: expected_delegations
+            }
+        );
+
+        Ok(())
+    }
+
+    fn assert_payer_delegations<S: Storage, A: Api, Q: Querier>(
+        deps: &Extern<S, A, Q>,
+        payer: &str,
+        expected_delegations: Vec<(&str, u64, u128)>,
+    ) -> StdResult<()> {
+        let query_response = query(
+            deps,
+            QueryMsg::PayerDelegations {
+                address: HumanAddr::from(payer),
+                key: "viewkey".to_string(),
+            },
+        )?;
+
+        let expected_delegations = expected_delegations
+            .into_iter()
+            .map(|(beneficiary, id, amount)| PayerDelegation {
+                beneficiary: HumanAddr::from(beneficiary),
+                id,
+                amount: Uint128(amount),
+            })
+            .collect();
+        assert_eq!(
+            from_binary::<QueryAnswer>(&query_response)?,
+            QueryAnswer::PayerDelegations {
+                delegations: expected_delegations
+            }
+        );
+
+        Ok(())
+    }
+
+    fn platform_deposit_msg(to: &str, amount: u128) -> StdResult<CosmosMsg> {
+        snip20::send_msg(
+            HumanAddr::from("platform"),
+            Uint128(amount),
+            Some(to_binary(&platform::msg::ReceiveMsg::Deposit {
+                to: HumanAddr::from(to),
+            })?),
+            None,
+            None,
+            RESPONSE_BLOCK_SIZE,
+            "".to_string(),
+            HumanAddr::from("token"),
+        )
+    }
+
+    #[test]
+    fn test_delegations() -> StdResult<()> {
+        let mut deps = init_helper(None, None, Some(vec![ScheduleUnit::new(10, 1000)]), None)?;
+        set_viewing_key_helper(&mut deps, "member", "viewkey")?;
+        set_viewing_key_helper(&mut deps, "dao", "viewkey")?;
+
+        deposit_for_helper(&mut deps, "dao", "member", 1000, true, Some(0))?;
+        deposit_for_helper(&mut deps, "dao", "member", 500, false, Some(0))?;
+        assert_rewards_balance(&deps, "member", 1500, 100_000, 0, 0)?;
+        assert_delegations(
+            &deps,
+            "member",
+            vec![Delegation {
+                id: 0,
+                payer: HumanAddr::from("dao"),
+                amount: Uint128(1000),
+            }],
+        )?;
+        assert_payer_delegations(&deps, "dao", vec![("member", 0, 1000)])?;
+
+        assert_eq!(
+            extract_generic_error_msg(withdraw_helper(&mut deps, "member", 1000, Some(0))),
+            "insufficient funds to redeem: balance=500, required=1000"
+        );
+        assert_eq!(
+            extract_generic_error_msg(revoke_delegation_helper(
+                &mut deps,
+                "stranger",
+                "member",
+                0,
+                Some(0)
+            )),
+            "delegation 0 can only be revoked by its payer or beneficiary"
+        );
+
+        // the rewards earned with the delegated tokens stay with the beneficiary
+        let (messages, answer) = revoke_delegation_helper(&mut deps, "dao", "member", 0, Some(3))?;
+        assert_eq!(answer, HandleAnswer::RevokeDelegation { status: Success });
+        assert_eq!(
+            messages,
+            vec![
+                platform_deposit_msg("dao", 1000)?,
+                platform_deposit_msg("member", 3000)?
+            ]
+        );
+        assert_rewards_balance(&deps, "member", 500, 100_000, 0, 3)?;
+        assert_delegations(&deps, "member", vec![])?;
+        assert_payer_delegations(&deps, "dao", vec![])?;
+        assert_eq!(
+            extract_generic_error_msg(revoke_delegation_helper(
+                &mut deps, "dao", "member", 0, None
+            )),
+            "no delegation with id 0"
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_delegation_limits() -> StdResult<()> {
+        let mut deps = init_helper(None, None, None, None)?;
+        set_viewing_key_helper(&mut deps, "payer0", "viewkey")?;
+        set_viewing_key_helper(&mut deps, "payer3", "viewkey")?;
+
+        for i in 0..10 {
+            deposit_for_helper(&mut deps, &format!("payer{}", i), "member", 1, true, None)?;
+        }
+        assert_eq!(
+            extract_generic_error_msg(deposit_for_helper(
+                &mut deps, "payer10", "member", 1, true, None
+            )),
+            "cannot have more than 10 delegations"
+        );
+
+        // a payer tops up its delegation
+        deposit_for_helper(&mut deps, "payer0", "member", 1, true, None)?;
+        assert_payer_delegations(&deps, "payer0", vec![("member", 0, 2)])?;
+
+        // the beneficiary makes room by returning a delegation
+        let (messages, answer) = revoke_delegation_helper(&mut deps, "member", "member", 3, None)?;
+        assert_eq!(answer, HandleAnswer::RevokeDelegation { status: Success });
+        assert_eq!(messages, vec![platform_deposit_msg("payer3", 1)?]);
+        assert_payer_delegations(&deps, "payer3", vec![])?;
+        deposit_for_helper(&mut deps, "payer10", "member", 1, true, None)?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_emergency_withdraw_delegations() -> StdResult<()> {
+        let mut deps = init_helper(None, None, None, None)?;
+        unpause_feature_helper(&mut deps, Features::EmergencyWithdraw)?;
+        set_viewing_key_helper(&mut deps, "dao", "viewkey")?;
+
+        deposit_helper(&mut deps, "member", 500, Some(0))?;
+        deposit_for_helper(&mut deps, "dao", "member", 200, true, Some(0))?;
+
+        let (messages, _) = emergency_withdraw_helper(&mut deps, "member")?;
+        assert_eq!(
+            messages,
+            vec![
+                platform_deposit_msg("member", 500)?,
+                platform_deposit_msg("dao", 200)?
+            ]
+        );
+        assert_total_locked(&deps, 0, 0)?;
+        assert_payer_delegations(&deps, "dao", vec![])?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_emergency_withdraw_lock_positions() -> StdResult<()> {
+        let mut deps = init_helper(None, None, None, None)?;
+        change_lock_tiers_config_helper(&mut deps, lock_tiers())?;
+        unpause_feature_helper(&mut deps, Features::EmergencyWithdraw)?;
+        unpause_feature_helper(&mut deps, Features::EmergencyWithdrawSkipPlatform)?;
+        set_viewing_key_helper(&mut deps, "user", "viewkey")?;
+
+        lock_deposit_helper(&mut deps, "user", 1000, Some(0), Some(100), 12_345)?;
+        deposit_helper(&mut deps, "whale", 500, Some(0))?;
+        lock_deposit_helper(&mut deps, "whale", 1000, Some(0), Some(1000), 12_345)?;
+
+        // a position without an early withdrawal penalty stays locked
+        for msg in [
+            HandleMsg::EmergencyWithdraw {},
+            HandleMsg::EmergencyWithdrawSkipPlatform {},
+        ] {
+            let env = mock_env_with_height_and_time("user", 0, 12_400);
+            assert_eq!(
+                extract_generic_error_msg(handle(&mut deps, env, msg)),
+                "lock position 0 is locked until 12445"
+            );
+        }
+        assert_total_locked(&deps, 2500, 150_000_000 + 50_000_000 + 200_000_000)?;
+
+        // the others pay the penalty, which goes to the stakers that remain
+        let env = mock_env_with_height_and_time("whale", 0, 12_845);
+        let result = handle(&mut deps, env, HandleMsg::EmergencyWithdraw {})?;
+        assert_eq!(result.messages, vec![platform_deposit_msg("whale", 1400)?]);
+        assert_total_locked(&deps, 1000, 150_000_000)?;
+        // 10% of the 1000 in the position, rounded down per share
+        assert_rewards_balance(&deps, "user", 1000, 100_000, 99, 0)?;
+
+        let env = mock_env_with_height_and_time("user", 0, 12_445);
+        handle(&mut deps, env, HandleMsg::EmergencyWithdraw {})?;
+        assert_total_locked(&deps, 0, 0)?;
+
+        Ok(())
+    }
+
+    // Storage of a contract instantiated before the storage was versioned, as `TypedStore` wrote it
+    const LEGACY_CONFIG: &[u8] = b"\x05\0\0\0\0\0\0\0admin\
+        \x05\0\0\0\0\0\0\0token\x04\0\0\0\0\0\0\0hash\
+        \x08\0\0\0\0\0\0\0platform\x04\0\0\0\0\0\0\0hash\
+        \x02\0\0\0\0\0\0\0vk\
+        \x03\0\0\0\0\0\0\0\x01\x02\x03\
+        \x0f\0\0\0\0\0\0\0cosmos2contract\
+        \xe0\x93\x04\0\0\0\0\0\0\0\0\0\0\0\0\0";
+    const LEGACY_REWARD_POOL: &[u8] = b"\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\
+        \x64\0\0\0\0\0\0\0\
+        \xe8\x03\0\0\0\0\0\0\0\0\0\0\0\0\0\0\
+        \0\xe1\xf5\x05\0\0\0\0\0\0\0\0\0\0\0\0\
+        \0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0";
+    const LEGACY_REWARD_SCHEDULE: &[u8] = b"\x01\0\0\0\0\0\0\0\
+        \xe8\x03\0\0\0\0\0\0\
+        \x0a\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0";
+    const LEGACY_USER_BALANCE: &[u8] = b"\xe8\x03\0\0\0\0\0\0\0\0\0\0\0\0\0\0\
+        \0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\0\
+        \xa0\x86\x01\0\0\0\0\0\0\0\0\0\0\0\0\0\
+        \0\xe1\xf5\x05\0\0\0\0\0\0\0\0\0\0\0\0";
+
+    fn legacy_deps() -> Extern<MockStorage, MockApi, MockQuerier> {
+        let mut deps = mock_dependencies(20, &[]);
+        deps.storage.set(b"config", LEGACY_CONFIG);
+        deps.storage.set(b"reward_pool", LEGACY_REWARD_POOL);
+        deps.storage.set(b"reward_schedule", LEGACY_REWARD_SCHEDULE);
+        PrefixedStorage::new(b"user_balances", &mut deps.storage)
+            .set(b"alice", LEGACY_USER_BALANCE);
+
+        deps
+    }
+
+    #[test]
+    fn test_migrate_legacy_storage() -> StdResult<()> {
+        let mut deps = legacy_deps();
+
+        let version = from_binary::<QueryAnswer>(&query(&deps, QueryMsg::StorageVersion {})?)?;
+        assert_eq!(
+            version,
+            QueryAnswer::StorageVersion {
+                version: 0,
+                latest: STORAGE_VERSION
+            }
+        );
+        assert!(
+            extract_generic_error_msg(query(&deps, QueryMsg::TotalLocked {}))
+                .contains("has to migrate")
+        );
+        assert!(
+            extract_generic_error_msg(set_viewing_key_helper(&mut deps, "alice", "viewkey"))
+                .contains("has to migrate")
+        );
+
+        assert_eq!(
+            extract_generic_error_msg(migrate_helper(&mut deps, "alice")),
+            "not an admin: alice"
+        );
+        assert_eq!(
+            migrate_helper(&mut deps, "admin")?,
+            HandleAnswer::Migrate { status: Success }
+        );
+        assert_eq!(
+            migrate_helper(&mut deps, "admin")?,
+            HandleAnswer::Migrate { status: NotChanged }
+        );
+
+        let version = from_binary::<QueryAnswer>(&query(&deps, QueryMsg::StorageVersion {})?)?;
+        assert_eq!(
+            version,
+            QueryAnswer::StorageVersion {
+                version: STORAGE_VERSION,
+                latest: STORAGE_VERSION
+            }
+        );
+
+        let config = Config::load(&deps.storage)?;
+        assert_eq!(config.admin, HumanAddr::from("admin"));
+        assert_eq!(config.platform.address, HumanAddr::from("platform"));
+        assert_eq!(config.prng_seed, vec![1, 2, 3]);
+        assert_eq!(config.max_multiplier, 300_000);
+        assert_eq!(
+            from_binary::<QueryAnswer>(&query(&deps, QueryMsg::InflationSchedule {})?)?,
+            QueryAnswer::InflationSchedule {
+                inflation_schedule: vec![ScheduleUnit::new(1000, 10)]
+            }
+        );
+        assert_total_locked(&deps, 1000, 100_000_000)?;
+
+        set_viewing_key_helper(&mut deps, "alice", "viewkey")?;
+        assert_rewards_balance(&deps, "alice", 1000, 100_000, 0, 100)?;
+
+        Ok(())
+    }
 }
@@ -1,3 +1,5 @@
+use std::cmp::min;
+
 use cosmwasm_std::{HumanAddr, ReadonlyStorage, StdError, StdResult, Storage, Uint128};
 use cosmwasm_storage::{PrefixedStorage, ReadonlyPrefixedStorage};
 use schemars::JsonSchema;
@@ -6,6 +8,8 @@ use secret_toolkit::utils::types::Contract;
 use secret_toolkit_incubator::cashmap::{CashMap, ReadOnlyCashMap};
 use serde::{Deserialize, Serialize};
 
+use crate::constants::{MAX_DELEGATIONS, MULTIPLIER_SCALE};
+
 const PREFIX_CONFIG: &[u8] = b"config";
 const PREFIX_USER_BALANCES: &[u8] = b"user_balances";
 const PREFIX_REWARD_POOL: &[u8] = b"reward_pool";
@@ -14,6 +18,13 @@ const PREFIX_SUBSCRIBERS: &[u8] = b"subscribers";
 const PREFIX_MULTIPLIER_CONTRACTS: &[u8] = b"multiplier_contracts";
 const PREFIX_BOOSTER_ITEMS: &[u8] = b"booster_items";
 const PREFIX_USER_INVENTORY: &[u8] = b"user_inventory";
+const PREFIX_LOCK_TIERS: &[u8] = b"lock_tiers";
+const PREFIX_LOCK_POSITIONS: &[u8] = b"lock_positions";
+const PREFIX_AUTO_COMPOUND: &[u8] = b"auto_compound";
+const PREFIX_REWARD_TOKENS: &[u8] = b"reward_tokens";
+const PREFIX_REWARD_TOKEN_DEBTS: &[u8] = b"reward_token_debts";
+const PREFIX_DELEGATIONS: &[u8] = b"delegations";
+const PREFIX_PAYER_DELEGATIONS: &[u8] = b"payer_delegations";
 pub const PREFIX_REVOKED_PERMITS: &str = "revoked_permits";
 
 #[derive(Serialize, Deserialize, JsonSchema)]
@@ -145,13 +156,16 @@ impl InflationSchedule {
         from_block: u64,
         to_block: u64,
     ) -> StdResult<u128> {
+        let schedule = Self::load(storage)?;
+        Ok(Self::inflation_of(&schedule, from_block, to_block))
+    }
+
+    pub fn inflation_of(schedule: &[StoredScheduleUnit], from_block: u64, to_block: u64) -> u128 {
         if to_block <= from_block {
-            return Ok(0);
+            return 0;
         }
 
         let mut from_block = from_block;
-        let schedule = Self::load(storage)?;
-
         let mut amount = 0;
         // Going serially assuming that schedule is not a big vector
         for unit in schedule {
@@ -166,7 +180,7 @@ impl InflationSchedule {
             }
         }
 
-        Ok(amount)
+        amount
     }
 }
 
@@ -397,3 +411,508 @@ pub enum Features {
     EmergencyWithdraw,
     EmergencyWithdrawSkipPlatform,
 }
+
+/// A lock duration deposits can choose, and the weight bonus it gives
+#[derive(Serialize, Deserialize, JsonSchema, Clone, Debug)]
+#[cfg_attr(test, derive(PartialEq))]
+pub struct LockTier {
+    /// Lock duration, in seconds
+    pub duration: u64,
+    /// Weight multiplier of the locked tokens (100_000 is 1). Stacks with booster items
+    pub bonus: Uint128,
+    /// Penalty (in basis points) of withdrawing before the lock ends. If not set, locked tokens
+    /// can't be withdrawn before the lock ends
+    pub early_withdrawal_penalty_bps: Option<u16>,
+}
+
+pub struct LockTiers {}
+
+impl LockTiers {
+    pub fn load<S: ReadonlyStorage>(storage: &S) -> StdResult<Vec<LockTier>> {
+        Ok(TypedStore::attach(storage)
+            .may_load(PREFIX_LOCK_TIERS)?
+            .unwrap_or_default())
+    }
+
+    pub fn save<S: Storage>(storage: &mut S, tiers: Vec<LockTier>) -> StdResult<()> {
+        for (i, tier) in tiers.iter().enumerate() {
+            if tier.bonus.u128() < MULTIPLIER_SCALE {
+                return Err(StdError::generic_err(
+                    "lock tier bonus cannot be smaller than 1",
+                ));
+            }
+            if tier.early_withdrawal_penalty_bps.unwrap_or_default() > 10_000 {
+                return Err(StdError::generic_err(
+                    "early withdrawal penalty cannot be more than 10000 basis points",
+                ));
+            }
+            if tiers[..i].iter().any(|t| t.duration == tier.duration) {
+                return Err(StdError::generic_err(format!(
+                    "duplicate lock tier of {} seconds",
+                    tier.duration
+                )));
+            }
+        }
+
+        TypedStoreMut::attach(storage).store(PREFIX_LOCK_TIERS, &tiers)
+    }
+
+    pub fn get<S: ReadonlyStorage>(storage: &S, duration: u64) -> StdResult<LockTier> {
+        Self::load(storage)?
+            .into_iter()
+            .find(|t| t.duration == duration)
+            .ok_or_else(|| {
+                StdError::generic_err(format!("there is no lock tier of {} seconds", duration))
+            })
+    }
+}
+
+#[derive(Serialize, Deserialize, JsonSchema, Debug)]
+#[cfg_attr(test, derive(PartialEq))]
+pub struct LockPosition {
+    pub id: u64,
+    pub amount: Uint128,
+    /// Weight multiplier of the position (100_000 is 1)
+    pub bonus: Uint128,
+    /// Time (in seconds) the lock ends at
+    pub unlock_ts: u64,
+    pub early_withdrawal_penalty_bps: Option<u16>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct StoredLockPosition {
+    pub id: u64,
+    pub amount: u128,
+    pub bonus: u128,
+    pub unlock_ts: u64,
+    pub early_withdrawal_penalty_bps: Option<u16>,
+}
+
+impl StoredLockPosition {
+    /// Penalty of withdrawing `amount` at `now`. Fails if the lock didn't end and the position
+    /// can't be withdrawn early
+    pub fn penalty(&self, amount: u128, now: u64) -> StdResult<u128> {
+        if self.unlock_ts <= now {
+            return Ok(0);
+        }
+
+        match self.early_withdrawal_penalty_bps {
+            None => Err(StdError::generic_err(format!(
+                "lock position {} is locked until {}",
+                self.id, self.unlock_ts
+            ))),
+            Some(bps) => Ok(amount * bps as u128 / 10_000),
+        }
+    }
+
+    pub fn from_stored(&self) -> LockPosition {
+        LockPosition {
+            id: self.id,
+            amount: Uint128(self.amount),
+            bonus: Uint128(self.bonus),
+            unlock_ts: self.unlock_ts,
+            early_withdrawal_penalty_bps: self.early_withdrawal_penalty_bps,
+        }
+    }
+}
+
+/// Time-locked deposits of a user. Their tokens are included in `UserBalance::locked`
+#[derive(Serialize, Deserialize, Default)]
+pub struct LockPositions {
+    next_id: u64,
+    pub positions: Vec<StoredLockPosition>,
+}
+
+impl LockPositions {
+    pub fn load<S: ReadonlyStorage>(storage: &S, user: &HumanAddr) -> StdResult<Self> {
+        let store = ReadonlyPrefixedStorage::new(PREFIX_LOCK_POSITIONS, storage);
+        Ok(TypedStore::attach(&store)
+            .may_load(user.0.as_bytes())?
+            .unwrap_or_default())
+    }
+
+    pub fn save<S: Storage>(&self, storage: &mut S, user: &HumanAddr) -> StdResult<()> {
+        let mut store = PrefixedStorage::new(PREFIX_LOCK_POSITIONS, storage);
+        TypedStoreMut::attach(&mut store).store(user.0.as_bytes(), self)
+    }
+
+    /// Returns the id of the new position
+    pub fn add(&mut self, amount: u128, tier: &LockTier, now: u64) -> u64 {
+        let id = self.next_id;
+        self.next_id += 1;
+        self.positions.push(StoredLockPosition {
+            id,
+            amount,
+            bonus: tier.bonus.u128(),
+            unlock_ts: now + tier.duration,
+            early_withdrawal_penalty_bps: tier.early_withdrawal_penalty_bps,
+        });
+
+        id
+    }
+
+    /// Drops the positions whose lock ended, their tokens become regular locked tokens. Like the
+    /// max multiplier, this only applies when the user's weight is updated. Returns whether any
+    /// position was dropped
+    pub fn release_expired(&mut self, now: u64) -> bool {
+        let count = self.positions.len();
+        self.positions.retain(|p| p.unlock_ts > now);
+        self.positions.len() != count
+    }
+
+    /// Tokens in positions, which can't be withdrawn without choosing the position
+    pub fn total(&self) -> u128 {
+        self.positions.iter().map(|p| p.amount).sum()
+    }
+
+    /// Takes `amount` (all if not specified) out of a position. Returns the amount and the
+    /// penalty of withdrawing it early
+    pub fn withdraw(&mut self, id: u64, amount: Option<u128>, now: u64) -> StdResult<(u128, u128)> {
+        let index = self
+            .positions
+            .iter()
+            .position(|p| p.id == id)
+            .ok_or_else(|| StdError::generic_err(format!("no lock position with id {}", id)))?;
+        let position = &mut self.positions[index];
+
+        let amount = amount.unwrap_or(position.amount);
+        if amount > position.amount {
+            return Err(StdError::generic_err(format!(
+                "insufficient funds in lock position: balance={}, required={}",
+                position.amount, amount,
+            )));
+        }
+
+        let penalty = position.penalty(amount, now)?;
+
+        position.amount -= amount;
+        if position.amount == 0 {
+            self.positions.remove(index);
+        }
+
+        Ok((amount, penalty))
+    }
+
+    /// Penalty of withdrawing all the positions at `now`
+    pub fn total_penalty(&self, now: u64) -> StdResult<u128> {
+        self.positions
+            .iter()
+            .map(|p| p.penalty(p.amount, now))
+            .sum()
+    }
+}
+
+/// Users that compound their rewards instead of having them sent to the platform
+pub struct AutoCompound {}
+
+impl AutoCompound {
+    pub fn load<S: ReadonlyStorage>(storage: &S, user: &HumanAddr) -> StdResult<bool> {
+        let store = ReadonlyPrefixedStorage::new(PREFIX_AUTO_COMPOUND, storage);
+        Ok(TypedStore::attach(&store)
+            .may_load(user.0.as_bytes())?
+            .unwrap_or_default())
+    }
+
+    pub fn save<S: Storage>(storage: &mut S, user: &HumanAddr, enabled: bool) -> StdResult<()> {
+        let mut store = PrefixedStorage::new(PREFIX_AUTO_COMPOUND, storage);
+        TypedStoreMut::attach(&mut store).store(user.0.as_bytes(), &enabled)
+    }
+}
+
+/// A reward token other than `Config::token`, distributed pro rata to the weight of the users
+/// along with it. Rewards are accumulated like in `RewardPool`, using `RewardPool::total_weight`
+#[derive(Serialize, Deserialize)]
+pub struct RewardToken {
+    /// Identifies the registration of the token, so the debts of a token that was removed don't
+    /// apply if it's registered again
+    pub id: u64,
+    pub token: Contract,
+    pub schedule: Vec<StoredScheduleUnit>,
+    pub residue: u128,
+    pub last_reward_block: u64,
+    pub acc_reward_per_share: u128,
+}
+
+/// A reward token that was removed. Its rewards stopped accumulating, but the users can still
+/// claim the ones they earned
+#[derive(Serialize, Deserialize)]
+pub struct RemovedRewardToken {
+    pub id: u64,
+    pub token: Contract,
+    pub acc_reward_per_share: u128,
+    /// The residue and the rest of the schedule, which the admin can sweep
+    pub undistributed: u128,
+}
+
+#[derive(Serialize, Deserialize, Default)]
+pub struct RewardTokens {
+    next_id: u64,
+    pub tokens: Vec<RewardToken>,
+    pub removed: Vec<RemovedRewardToken>,
+}
+
+impl RewardTokens {
+    pub fn load<S: ReadonlyStorage>(storage: &S) -> StdResult<Self> {
+        Ok(TypedStore::attach(storage)
+            .may_load(PREFIX_REWARD_TOKENS)?
+            .unwrap_or_default())
+    }
+
+    pub fn save<S: Storage>(&self, storage: &mut S) -> StdResult<()> {
+        TypedStoreMut::attach(storage).store(PREFIX_REWARD_TOKENS, self)
+    }
+
+    pub fn add(&mut self, token: Contract, schedule: Vec<StoredScheduleUnit>, block: u64) {
+        self.tokens.push(RewardToken {
+            id: self.next_id,
+            token,
+            schedule,
+            residue: 0,
+            last_reward_block: block,
+            acc_reward_per_share: 0,
+        });
+        self.next_id += 1;
+    }
+
+    pub fn get_mut(&mut self, address: &HumanAddr) -> StdResult<&mut RewardToken> {
+        self.tokens
+            .iter_mut()
+            .find(|t| &t.token.address == address)
+            .ok_or_else(|| StdError::generic_err(format!("{} is not a reward token", address)))
+    }
+
+    /// Moves a token to `removed`. Its rewards have to be accumulated up to the current block first
+    pub fn remove(&mut self, address: &HumanAddr) -> StdResult<()> {
+        let index = self
+            .tokens
+            .iter()
+            .position(|t| &t.token.address == address)
+            .ok_or_else(|| StdError::generic_err(format!("{} is not a reward token", address)))?;
+        let reward_token = self.tokens.remove(index);
+        let undistributed = reward_token.residue
+            + InflationSchedule::inflation_of(
+                &reward_token.schedule,
+                reward_token.last_reward_block,
+                u64::MAX,
+            );
+        self.removed.push(RemovedRewardToken {
+            id: reward_token.id,
+            token: reward_token.token,
+            acc_reward_per_share: reward_token.acc_reward_per_share,
+            undistributed,
+        });
+
+        Ok(())
+    }
+
+    /// Takes up to `max` of the undistributed rewards of the removed registrations of a token
+    pub fn take_undistributed(
+        &mut self,
+        address: &HumanAddr,
+        max: u128,
+    ) -> StdResult<(Contract, u128)> {
+        let mut token = None;
+        let mut amount = 0;
+        for reward_token in self
+            .removed
+            .iter_mut()
+            .filter(|t| &t.token.address == address)
+        {
+            let taken = min(reward_token.undistributed, max - amount);
+            reward_token.undistributed -= taken;
+            amount += taken;
+            token = Some(reward_token.token.clone());
+        }
+
+        let token = token.ok_or_else(|| {
+            StdError::generic_err(format!("{} is not a removed reward token", address))
+        })?;
+        Ok((token, amount))
+    }
+}
+
+/// Debts of a user in the reward tokens, by `RewardToken::id`. Like `UserBalance::debt`
+#[derive(Serialize, Deserialize, Default)]
+pub struct RewardTokenDebts {
+    pub debts: Vec<(u64, u128)>,
+    /// Rewards in removed tokens earned before the last change of the user's weight
+    pub unclaimed: Vec<(u64, u128)>,
+}
+
+impl RewardTokenDebts {
+    pub fn load<S: ReadonlyStorage>(storage: &S, user: &HumanAddr) -> StdResult<Self> {
+        let store = ReadonlyPrefixedStorage::new(PREFIX_REWARD_TOKEN_DEBTS, storage);
+        Ok(TypedStore::attach(&store)
+            .may_load(user.0.as_bytes())?
+            .unwrap_or_default())
+    }
+
+    pub fn save<S: Storage>(&self, storage: &mut S, user: &HumanAddr) -> StdResult<()> {
+        let mut store = PrefixedStorage::new(PREFIX_REWARD_TOKEN_DEBTS, storage);
+        TypedStoreMut::attach(&mut store).store(user.0.as_bytes(), self)
+    }
+
+    /// Tokens registered after the last change of the user's weight have no debt
+    pub fn get(&self, id: u64) -> u128 {
+        self.debts
+            .iter()
+            .find(|(token_id, _)| *token_id == id)
+            .map_or(0, |(_, debt)| *debt)
+    }
+
+    pub fn get_unclaimed(&self, id: u64) -> u128 {
+        self.unclaimed
+            .iter()
+            .find(|(token_id, _)| *token_id == id)
+            .map_or(0, |(_, rewards)| *rewards)
+    }
+
+    /// Resets the debt of a removed token once its rewards are claimed
+    pub fn claim(&mut self, id: u64, debt: u128) {
+        self.unclaimed.retain(|(token_id, _)| *token_id != id);
+        self.debts.retain(|(token_id, _)| *token_id != id);
+        self.debts.push((id, debt));
+    }
+}
+
+/// Tokens staked for a user (the beneficiary) by another account, which can revoke them
+#[derive(Serialize, Deserialize, JsonSchema, Debug)]
+#[cfg_attr(test, derive(PartialEq))]
+pub struct Delegation {
+    pub id: u64,
+    pub payer: HumanAddr,
+    pub amount: Uint128,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct StoredDelegation {
+    pub id: u64,
+    pub payer: HumanAddr,
+    pub amount: u128,
+}
+
+impl StoredDelegation {
+    pub fn from_stored(&self) -> Delegation {
+        Delegation {
+            id: self.id,
+            payer: self.payer.clone(),
+            amount: Uint128(self.amount),
+        }
+    }
+}
+
+/// Revocable delegations to a user. Their tokens are included in `UserBalance::locked`, but the
+/// user can't withdraw them
+#[derive(Serialize, Deserialize, Default)]
+pub struct Delegations {
+    next_id: u64,
+    pub delegations: Vec<StoredDelegation>,
+}
+
+impl Delegations {
+    pub fn load<S: ReadonlyStorage>(storage: &S, beneficiary: &HumanAddr) -> StdResult<Self> {
+        let store = ReadonlyPrefixedStorage::new(PREFIX_DELEGATIONS, storage);
+        Ok(TypedStore::attach(&store)
+            .may_load(beneficiary.0.as_bytes())?
+            .unwrap_or_default())
+    }
+
+    pub fn save<S: Storage>(&self, storage: &mut S, beneficiary: &HumanAddr) -> StdResult<()> {
+        let mut store = PrefixedStorage::new(PREFIX_DELEGATIONS, storage);
+        TypedStoreMut::attach(&mut store).store(beneficiary.0.as_bytes(), self)
+    }
+
+    /// Adds `amount` to the delegation of `payer`, which is created if it has none. Returns the id
+    /// of the delegation
+    pub fn add(&mut self, payer: HumanAddr, amount: u128) -> StdResult<u64> {
+        if let Some(delegation) = self.delegations.iter_mut().find(|d| d.payer == payer) {
+            delegation.amount += amount;
+            return Ok(delegation.id);
+        }
+        // Every withdrawal and claim of the beneficiary loads all of them
+        if self.delegations.len() >= MAX_DELEGATIONS {
+            return Err(StdError::generic_err(format!(
+                "cannot have more than {} delegations",
+                MAX_DELEGATIONS
+            )));
+        }
+
+        let id = self.next_id;
+        self.next_id += 1;
+        self.delegations
+            .push(StoredDelegation { id, payer, amount });
+
+        Ok(id)
+    }
+
+    pub fn total(&self) -> u128 {
+        self.delegations.iter().map(|d| d.amount).sum()
+    }
+
+    /// Removes a delegation, on behalf of its payer or of `beneficiary`
+    pub fn revoke(
+        &mut self,
+        id: u64,
+        sender: &HumanAddr,
+        beneficiary: &HumanAddr,
+    ) -> StdResult<StoredDelegation> {
+        let index = self
+            .delegations
+            .iter()
+            .position(|d| d.id == id)
+            .ok_or_else(|| StdError::generic_err(format!("no delegation with id {}", id)))?;
+        if &self.delegations[index].payer != sender && sender != beneficiary {
+            return Err(StdError::generic_err(format!(
+                "delegation {} can only be revoked by its payer or beneficiary",
+                id
+            )));
+        }
+
+        Ok(self.delegations.remove(index))
+    }
+}
+
+/// Beneficiaries of the revocable delegations of a payer, which has one delegation to each of them
+pub struct PayerDelegations {}
+
+impl PayerDelegations {
+    pub fn load<S: ReadonlyStorage>(storage: &S, payer: &HumanAddr) -> StdResult<Vec<HumanAddr>> {
+        let store = ReadonlyPrefixedStorage::new(PREFIX_PAYER_DELEGATIONS, storage);
+        Ok(TypedStore::attach(&store)
+            .may_load(payer.0.as_bytes())?
+            .unwrap_or_default())
+    }
+
+    fn save<S: Storage>(
+        storage: &mut S,
+        payer: &HumanAddr,
+        beneficiaries: &[HumanAddr],
+    ) -> StdResult<()> {
+        let mut store = PrefixedStorage::new(PREFIX_PAYER_DELEGATIONS, storage);
+        TypedStoreMut::attach(&mut store).store(payer.0.as_bytes(), &beneficiaries.to_vec())
+    }
+
+    pub fn add<S: Storage>(
+        storage: &mut S,
+        payer: &HumanAddr,
+        beneficiary: &HumanAddr,
+    ) -> StdResult<()> {
+        let mut beneficiaries = Self::load(storage, payer)?;
+        if !beneficiaries.contains(beneficiary) {
+            beneficiaries.push(beneficiary.clone());
+            Self::save(storage, payer, &beneficiaries)?;
+        }
+
+        Ok(())
+    }
+
+    pub fn remove<S: Storage>(
+        storage: &mut S,
+        payer: &HumanAddr,
+        beneficiary: &HumanAddr,
+    ) -> StdResult<()> {
+        let mut beneficiaries = Self::load(storage, payer)?;
+        beneficiaries.retain(|b| b != beneficiary);
+        Self::save(storage, payer, &beneficiaries)
+    }
+}
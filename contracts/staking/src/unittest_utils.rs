@@ -15,8 +15,10 @@ use secret_toolkit::utils::types::Contract;
 
 use crate::contract::{handle, init};
 use crate::msg::HandleMsg::{Receive, SetViewingKey};
-use crate::msg::{HandleAnswer, HandleMsg, InitMsg, ReceiveFromPlatformMsg, ReceiveMsg};
-use crate::state::{Features, ScheduleUnit};
+use crate::msg::{
+    HandleAnswer, HandleMsg, InitMsg, ReceiveFromPlatformMsg, ReceiveMsg, RewardDestination,
+};
+use crate::state::{Features, LockTier, ScheduleUnit};
 
 pub fn extract_answer(hr: HandleResponse) -> StdResult<HandleAnswer> {
     let HandleResponse { data, .. } = hr;
@@ -29,10 +31,14 @@ pub fn extract_messages(hr: HandleResponse) -> Vec<CosmosMsg> {
 }
 
 pub fn mock_env_with_height<U: Into<HumanAddr>>(sender: U, height: u64) -> Env {
+    mock_env_with_height_and_time(sender, height, 12_345)
+}
+
+pub fn mock_env_with_height_and_time<U: Into<HumanAddr>>(sender: U, height: u64, time: u64) -> Env {
     Env {
         block: BlockInfo {
             height,
-            time: 12_345,
+            time,
             chain_id: "cosmos-testnet-14002".to_string(),
         },
         message: MessageInfo {
@@ -167,17 +173,56 @@ pub fn deposit_helper(
     depositor: &str,
     amount: u128,
     block_height: Option<u64>,
+) -> StdResult<(Vec<CosmosMsg>, HandleAnswer)> {
+    lock_deposit_helper(deps, depositor, amount, block_height, None, 12_345)
+}
+
+pub fn lock_deposit_helper(
+    deps: &mut Extern<MockStorage, MockApi, MockQuerier>,
+    depositor: &str,
+    amount: u128,
+    block_height: Option<u64>,
+    lock_duration: Option<u64>,
+    time: u64,
 ) -> StdResult<(Vec<CosmosMsg>, HandleAnswer)> {
     let result = handle(
         deps,
-        mock_env_with_height(HumanAddr::from("token"), block_height.unwrap_or(0)),
+        mock_env_with_height_and_time(HumanAddr::from("token"), block_height.unwrap_or(0), time),
         Receive {
             sender: HumanAddr::from("not-used"),
             from: HumanAddr::from("platform"),
             amount: Uint128::from(amount),
             msg: Base64JsonOf::from(ReceiveMsg::ReceiveFromPlatform {
                 from: HumanAddr::from(depositor),
-                msg: Base64JsonOf::from(ReceiveFromPlatformMsg::Deposit {}),
+                msg: Base64JsonOf::from(ReceiveFromPlatformMsg::Deposit { lock_duration }),
+            }),
+        },
+    )?;
+
+    Ok((extract_messages(result.clone()), extract_answer(result)?))
+}
+
+pub fn deposit_for_helper(
+    deps: &mut Extern<MockStorage, MockApi, MockQuerier>,
+    payer: &str,
+    beneficiary: &str,
+    amount: u128,
+    revocable: bool,
+    block_height: Option<u64>,
+) -> StdResult<(Vec<CosmosMsg>, HandleAnswer)> {
+    let result = handle(
+        deps,
+        mock_env_with_height(HumanAddr::from("token"), block_height.unwrap_or(0)),
+        Receive {
+            sender: HumanAddr::from("not-used"),
+            from: HumanAddr::from("platform"),
+            amount: Uint128::from(amount),
+            msg: Base64JsonOf::from(ReceiveMsg::ReceiveFromPlatform {
+                from: HumanAddr::from(payer),
+                msg: Base64JsonOf::from(ReceiveFromPlatformMsg::DepositFor {
+                    beneficiary: HumanAddr::from(beneficiary),
+                    revocable,
+                }),
             }),
         },
     )?;
@@ -185,6 +230,43 @@ pub fn deposit_helper(
     Ok((extract_messages(result.clone()), extract_answer(result)?))
 }
 
+pub fn revoke_delegation_helper(
+    deps: &mut Extern<MockStorage, MockApi, MockQuerier>,
+    payer: &str,
+    beneficiary: &str,
+    id: u64,
+    block_height: Option<u64>,
+) -> StdResult<(Vec<CosmosMsg>, HandleAnswer)> {
+    let result = handle(
+        deps,
+        mock_env_with_height(HumanAddr::from(payer), block_height.unwrap_or(0)),
+        HandleMsg::RevokeDelegation {
+            beneficiary: HumanAddr::from(beneficiary),
+            id,
+        },
+    )?;
+
+    Ok((extract_messages(result.clone()), extract_answer(result)?))
+}
+
+pub fn release_expired_locks_helper(
+    deps: &mut Extern<MockStorage, MockApi, MockQuerier>,
+    sender: &str,
+    address: &str,
+    block_height: Option<u64>,
+    time: u64,
+) -> StdResult<(Vec<CosmosMsg>, HandleAnswer)> {
+    let result = handle(
+        deps,
+        mock_env_with_height_and_time(HumanAddr::from(sender), block_height.unwrap_or(0), time),
+        HandleMsg::ReleaseExpiredLocks {
+            address: HumanAddr::from(address),
+        },
+    )?;
+
+    Ok((extract_messages(result.clone()), extract_answer(result)?))
+}
+
 pub fn withdraw_helper(
     deps: &mut Extern<MockStorage, MockApi, MockQuerier>,
     redeemer: &str,
@@ -196,6 +278,44 @@ pub fn withdraw_helper(
         mock_env_with_height(HumanAddr::from(redeemer), block_height.unwrap_or(0)),
         HandleMsg::Withdraw {
             amount: Some(Uint128::from(amount)),
+            position: None,
+        },
+    )?;
+
+    Ok((extract_messages(result.clone()), extract_answer(result)?))
+}
+
+pub fn withdraw_all_helper(
+    deps: &mut Extern<MockStorage, MockApi, MockQuerier>,
+    redeemer: &str,
+    block_height: Option<u64>,
+) -> StdResult<(Vec<CosmosMsg>, HandleAnswer)> {
+    let result = handle(
+        deps,
+        mock_env_with_height(HumanAddr::from(redeemer), block_height.unwrap_or(0)),
+        HandleMsg::Withdraw {
+            amount: None,
+            position: None,
+        },
+    )?;
+
+    Ok((extract_messages(result.clone()), extract_answer(result)?))
+}
+
+pub fn withdraw_position_helper(
+    deps: &mut Extern<MockStorage, MockApi, MockQuerier>,
+    redeemer: &str,
+    amount: Option<u128>,
+    position: u64,
+    block_height: Option<u64>,
+    time: u64,
+) -> StdResult<(Vec<CosmosMsg>, HandleAnswer)> {
+    let result = handle(
+        deps,
+        mock_env_with_height_and_time(HumanAddr::from(redeemer), block_height.unwrap_or(0), time),
+        HandleMsg::Withdraw {
+            amount: amount.map(Uint128::from),
+            position: Some(position),
         },
     )?;
 
@@ -242,6 +362,53 @@ pub fn drop_multiplier_helper(
     Ok((extract_messages(result.clone()), extract_answer(result)?))
 }
 
+pub fn compound_helper(
+    deps: &mut Extern<MockStorage, MockApi, MockQuerier>,
+    user: &str,
+    block_height: Option<u64>,
+) -> StdResult<(Vec<CosmosMsg>, HandleAnswer)> {
+    let result = handle(
+        deps,
+        mock_env_with_height(HumanAddr::from(user), block_height.unwrap_or(0)),
+        HandleMsg::Compound {},
+    )?;
+
+    Ok((extract_messages(result.clone()), extract_answer(result)?))
+}
+
+pub fn claim_rewards_helper(
+    deps: &mut Extern<MockStorage, MockApi, MockQuerier>,
+    user: &str,
+    to: Option<&str>,
+    destination: RewardDestination,
+    block_height: Option<u64>,
+) -> StdResult<(Vec<CosmosMsg>, HandleAnswer)> {
+    let result = handle(
+        deps,
+        mock_env_with_height(HumanAddr::from(user), block_height.unwrap_or(0)),
+        HandleMsg::ClaimRewards {
+            to: to.map(HumanAddr::from),
+            destination,
+        },
+    )?;
+
+    Ok((extract_messages(result.clone()), extract_answer(result)?))
+}
+
+pub fn set_auto_compound_helper(
+    deps: &mut Extern<MockStorage, MockApi, MockQuerier>,
+    user: &str,
+    enabled: bool,
+) -> StdResult<HandleAnswer> {
+    let result = handle(
+        deps,
+        mock_env(HumanAddr::from(user), &[]),
+        HandleMsg::SetAutoCompound { enabled },
+    )?;
+
+    extract_answer(result)
+}
+
 pub fn emergency_withdraw_helper(
     deps: &mut Extern<MockStorage, MockApi, MockQuerier>,
     withdrawer: &str,
@@ -296,8 +463,82 @@ pub fn change_max_mul_config_helper(
             token_vk: None,
             inflation: None,
             max_multiplier: Some(Uint128::from(new_max_multiplier as u128)),
+            lock_tiers: None,
         },
     )?;
 
     Ok((extract_messages(result.clone()), extract_answer(result)?))
 }
+
+pub fn change_lock_tiers_config_helper(
+    deps: &mut Extern<MockStorage, MockApi, MockQuerier>,
+    lock_tiers: Vec<LockTier>,
+) -> StdResult<(Vec<CosmosMsg>, HandleAnswer)> {
+    let result = handle(
+        deps,
+        mock_env(HumanAddr::from("admin"), &[]),
+        HandleMsg::ChangeConfig {
+            admin: None,
+            platform: None,
+            token_vk: None,
+            inflation: None,
+            max_multiplier: None,
+            lock_tiers: Some(lock_tiers),
+        },
+    )?;
+
+    Ok((extract_messages(result.clone()), extract_answer(result)?))
+}
+
+pub fn add_reward_token_helper(
+    deps: &mut Extern<MockStorage, MockApi, MockQuerier>,
+    token: &str,
+    inflation_schedule: Vec<ScheduleUnit>,
+    block_height: Option<u64>,
+) -> StdResult<HandleAnswer> {
+    let result = handle(
+        deps,
+        mock_env_with_height(HumanAddr::from("admin"), block_height.unwrap_or(0)),
+        HandleMsg::AddRewardToken {
+            token: Contract {
+                address: HumanAddr::from(token),
+                hash: "hash".to_string(),
+            },
+            inflation_schedule,
+        },
+    )?;
+
+    extract_answer(result)
+}
+
+pub fn sweep_reward_token_helper(
+    deps: &mut Extern<MockStorage, MockApi, MockQuerier>,
+    from: &str,
+    token: &str,
+    amount: Option<u128>,
+) -> StdResult<(Vec<CosmosMsg>, HandleAnswer)> {
+    let result = handle(
+        deps,
+        mock_env(HumanAddr::from(from), &[]),
+        HandleMsg::SweepRewardToken {
+            token: HumanAddr::from(token),
+            recipient: HumanAddr::from("treasury"),
+            amount: amount.map(Uint128),
+        },
+    )?;
+
+    Ok((extract_messages(result.clone()), extract_answer(result)?))
+}
+
+pub fn migrate_helper(
+    deps: &mut Extern<MockStorage, MockApi, MockQuerier>,
+    from: &str,
+) -> StdResult<HandleAnswer> {
+    let result = handle(
+        deps,
+        mock_env(HumanAddr::from(from), &[]),
+        HandleMsg::Migrate {},
+    )?;
+
+    extract_answer(result)
+}
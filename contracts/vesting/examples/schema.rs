@@ -3,7 +3,7 @@ use std::fs::create_dir_all;
 
 use cosmwasm_schema::{export_schema, remove_schemas, schema_for};
 
-use vesting::handle::{HandleMsg, HandleResp};
+use vesting::handle::{HandleMsg, HandleResp, ReceiveMsg};
 use vesting::init::InitMsg;
 use vesting::query::{QueryMsg, QueryResp};
 
@@ -16,6 +16,7 @@ fn main() {
     export_schema(&schema_for!(InitMsg), &out_dir);
     export_schema(&schema_for!(HandleMsg), &out_dir);
     export_schema(&schema_for!(HandleResp), &out_dir);
+    export_schema(&schema_for!(ReceiveMsg), &out_dir);
     export_schema(&schema_for!(QueryMsg), &out_dir);
     export_schema(&schema_for!(QueryResp), &out_dir);
 }
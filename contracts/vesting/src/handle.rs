@@ -1,26 +1,38 @@
+use std::cmp::min;
+
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
 use cosmwasm_std::{
-    to_binary, Api, Env, Extern, HandleResponse, HumanAddr, Querier, StdError, StdResult, Storage,
-    Uint128,
+    from_binary, to_binary, Api, Binary, BlockInfo, Env, Extern, HandleResponse, HumanAddr,
+    Querier, StdError, StdResult, Storage, Uint128,
 };
 use secret_toolkit::snip20;
 use secret_toolkit::viewing_key::{ViewingKey, ViewingKeyStore};
 
 use crate::config::{Config, ContractMode};
+use crate::state::AirdropContracts;
 use crate::types::Contract;
-use crate::vesting::{NewSchedule, Vesting};
+use crate::vesting::{AirdropSchedule, NewSchedule, StoredSchedule, Vesting};
 
 #[derive(Deserialize, Debug, JsonSchema)]
 #[serde(rename_all = "snake_case")]
 pub enum HandleMsg {
     // User commands
-    /// Claim available funds from the vesting account.
+    /// Claim available funds from the vesting account, including its airdrop schedules.
     ///
     /// If `amount` is not specified, all available funds are withdrawn.
     Claim { amount: Option<Uint128> },
 
+    // Cross-contract commands
+    /// Receive vesting tokens through the SNIP-20 `Send` interface
+    Receive {
+        sender: HumanAddr,
+        from: HumanAddr,
+        amount: Uint128,
+        msg: Option<Binary>,
+    },
+
     // Authentication
     /// Create a random viewing key using entropy
     CreateViewingKey { entropy: String },
@@ -44,6 +56,23 @@ pub enum HandleMsg {
     SetContractMode { mode: ContractMode },
     /// Change the contract's admin address
     ChangeAdmin { address: HumanAddr },
+    /// Allow airdrop contracts to create vesting schedules
+    AddAirdropContracts { addresses: Vec<HumanAddr> },
+    /// Remove airdrop contracts from the allowed set
+    RemoveAirdropContracts { addresses: Vec<HumanAddr> },
+}
+
+#[derive(Serialize, Deserialize, Debug, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ReceiveMsg {
+    /// Create vesting schedules for the airdrops of a round. Each account gets one schedule per
+    /// round of each airdrop contract, which is extended if the account receives more airdrops in
+    /// the same round before it starts. Only accepted from registered airdrop contracts, and the
+    /// allocations must add up to the amount that was sent
+    AddAirdropSchedules {
+        round: u32,
+        schedules: Vec<(HumanAddr, NewSchedule)>,
+    },
 }
 
 #[derive(Serialize, Debug, JsonSchema)]
@@ -64,7 +93,7 @@ pub fn handle<S: Storage, A: Api, Q: Querier>(
     config.last_block = env.block.clone();
 
     if let ContractMode::PausedClaims | ContractMode::Emergency = config.mode {
-        if let Claim { .. } | AddAccounts { .. } | RemoveAccounts { .. } = &msg {
+        if let Claim { .. } | Receive { .. } | AddAccounts { .. } | RemoveAccounts { .. } = &msg {
             return Err(StdError::generic_err(
                 "This operation is not permitted because claims are paused",
             ));
@@ -73,6 +102,9 @@ pub fn handle<S: Storage, A: Api, Q: Querier>(
 
     let result = match msg {
         Claim { amount } => claim(deps, env, &config, amount),
+        Receive {
+            from, amount, msg, ..
+        } => receive(deps, env, &config, from, amount, msg),
         CreateViewingKey { entropy } => create_viewing_key(deps, env, entropy),
         SetViewingKey { key } => set_viewing_key(deps, env, key),
         SetVestingToken { contract, key } => set_vesting_token(env, &mut config, contract, key),
@@ -82,6 +114,10 @@ pub fn handle<S: Storage, A: Api, Q: Querier>(
         EmergencyRedeemAll {} => emergency_redeem(deps, &config, env),
         SetContractMode { mode } => set_contract_mode(env, &mut config, mode),
         ChangeAdmin { address } => change_admin(env, &mut config, address),
+        AddAirdropContracts { addresses } => add_airdrop_contracts(deps, env, &config, addresses),
+        RemoveAirdropContracts { addresses } => {
+            remove_airdrop_contracts(deps, env, &config, addresses)
+        }
     };
 
     config.save(&mut deps.storage)?;
@@ -96,16 +132,29 @@ fn claim<S: Storage, A: Api, Q: Querier>(
     amount: Option<Uint128>,
 ) -> StdResult<HandleResponse> {
     let claimed = Vesting::update(&mut deps.storage, |vesting| {
-        let mut schedule = vesting
-            .get_schedule(&env.message.sender)?
-            .ok_or_else(|| StdError::generic_err("You are not eligible for vesting rewards"))?;
+        let mut schedule = vesting.get_schedule(&env.message.sender)?;
+        let mut airdrop_schedules = vesting.get_airdrop_schedules(&env.message.sender)?;
+        if schedule.is_none() && airdrop_schedules.is_empty() {
+            return Err(StdError::generic_err(
+                "You are not eligible for vesting rewards",
+            ));
+        }
 
+        let schedules: Vec<&mut StoredSchedule> = schedule
+            .iter_mut()
+            .chain(airdrop_schedules.iter_mut().map(|a| &mut a.schedule))
+            .collect();
         let claimed = match amount {
-            Some(amount) => schedule.claim(amount.u128(), &env.block)?,
-            None => schedule.claim_all(&env.block),
+            Some(amount) => claim_from(schedules, amount.u128(), &env.block)?,
+            None => schedules.into_iter().map(|s| s.claim_all(&env.block)).sum(),
         };
 
-        vesting.set_schedule(env.message.sender.clone(), schedule);
+        if let Some(schedule) = schedule {
+            vesting.set_schedule(env.message.sender.clone(), schedule);
+        }
+        if !airdrop_schedules.is_empty() {
+            vesting.set_airdrop_schedules(env.message.sender.clone(), airdrop_schedules);
+        }
 
         Ok(claimed)
     })?;
@@ -129,6 +178,109 @@ fn claim<S: Storage, A: Api, Q: Querier>(
     Ok(res)
 }
 
+/// Claims `amount` from the schedules in order, failing if they don't have enough available
+fn claim_from(
+    schedules: Vec<&mut StoredSchedule>,
+    amount: u128,
+    block: &BlockInfo,
+) -> StdResult<u128> {
+    let available: u128 = schedules.iter().map(|s| s.available_at(block)).sum();
+    if amount > available {
+        return Err(StdError::generic_err(format!(
+            "Not enough funds are available to withdraw yet. {} > {}",
+            amount, available
+        )));
+    }
+
+    let mut left = amount;
+    for schedule in schedules {
+        let to_claim = min(left, schedule.available_at(block));
+        left -= schedule.claim(to_claim, block)?;
+    }
+
+    Ok(amount)
+}
+
+fn receive<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    config: &Config,
+    from: HumanAddr,
+    amount: Uint128,
+    msg: Option<Binary>,
+) -> StdResult<HandleResponse> {
+    if env.message.sender != config.vesting_token.address {
+        return Err(StdError::generic_err(format!(
+            "Only {} can be sent to this contract",
+            config.vesting_token.address
+        )));
+    }
+
+    let msg: ReceiveMsg =
+        from_binary(&msg.ok_or_else(|| StdError::generic_err("Receive requires a message"))?)?;
+
+    match msg {
+        ReceiveMsg::AddAirdropSchedules { round, schedules } => {
+            add_airdrop_schedules(deps, env, from, amount, round, schedules)
+        }
+    }
+}
+
+fn add_airdrop_schedules<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    from: HumanAddr,
+    amount: Uint128,
+    round: u32,
+    schedules: Vec<(HumanAddr, NewSchedule)>,
+) -> StdResult<HandleResponse> {
+    AirdropContracts::assert_airdrop_contract(&deps.storage, &from)?;
+
+    let mut total = 0_u128;
+    for (_, schedule) in schedules.iter() {
+        total = total
+            .checked_add(schedule.allocation.u128())
+            .ok_or_else(|| StdError::generic_err("Airdrop allocations exceeded u128::MAX"))?;
+    }
+    if total != amount.u128() {
+        return Err(StdError::generic_err(format!(
+            "Airdrop allocations add up to {} but {} was sent",
+            total, amount
+        )));
+    }
+
+    Vesting::update(&mut deps.storage, |vesting| {
+        for (address, schedule) in schedules {
+            let mut airdrop_schedules = vesting.get_airdrop_schedules(&address)?;
+            let prev_schedule = airdrop_schedules
+                .iter_mut()
+                .find(|a| a.airdrop == from && a.round == round);
+            match prev_schedule {
+                Some(prev_schedule) => {
+                    prev_schedule.schedule.extend(schedule.into(), &env.block)?
+                }
+                None => airdrop_schedules.push(AirdropSchedule {
+                    airdrop: from.clone(),
+                    round,
+                    schedule: schedule.into(),
+                }),
+            }
+
+            vesting.set_airdrop_schedules(address, airdrop_schedules);
+        }
+
+        Ok(())
+    })?;
+
+    let res = HandleResponse {
+        messages: vec![],
+        log: vec![],
+        data: Some(to_binary(&HandleResp::Success)?),
+    };
+
+    Ok(res)
+}
+
 fn create_viewing_key<S: Storage, A: Api, Q: Querier>(
     deps: &mut Extern<S, A, Q>,
     env: Env,
@@ -361,3 +513,163 @@ fn change_admin(env: Env, config: &mut Config, address: HumanAddr) -> StdResult<
 
     Ok(res)
 }
+
+fn add_airdrop_contracts<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    config: &Config,
+    addresses: Vec<HumanAddr>,
+) -> StdResult<HandleResponse> {
+    config.assert_admin(&env.message.sender)?;
+
+    AirdropContracts::set_multiple(&mut deps.storage, addresses)?;
+
+    let res = HandleResponse {
+        messages: vec![],
+        log: vec![],
+        data: Some(to_binary(&HandleResp::Success)?),
+    };
+
+    Ok(res)
+}
+
+fn remove_airdrop_contracts<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    config: &Config,
+    addresses: Vec<HumanAddr>,
+) -> StdResult<HandleResponse> {
+    config.assert_admin(&env.message.sender)?;
+
+    AirdropContracts::remove_multiple(&mut deps.storage, addresses);
+
+    let res = HandleResponse {
+        messages: vec![],
+        log: vec![],
+        data: Some(to_binary(&HandleResp::Success)?),
+    };
+
+    Ok(res)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::init::{init, InitMsg};
+    use cosmwasm_std::from_slice;
+    use cosmwasm_std::testing::{mock_dependencies, mock_env, MockApi, MockQuerier, MockStorage};
+
+    const MONTH: u64 = 30 * 24 * 60 * 60;
+
+    fn env_at(sender: &str, time: u64) -> Env {
+        let mut env = mock_env(sender, &[]);
+        env.block.time = time;
+        env
+    }
+
+    fn setup() -> Extern<MockStorage, MockApi, MockQuerier> {
+        let mut deps = mock_dependencies(20, &[]);
+        let msg: InitMsg = from_slice(
+            br#"{
+                "vesting_token": { "address": "token", "hash": "hash" },
+                "vesting_token_vk": "key",
+                "schedules": [],
+                "prng_seed": "c2VlZA=="
+            }"#,
+        )
+        .unwrap();
+        init(&mut deps, env_at("admin", 0), msg).unwrap();
+
+        let msg = HandleMsg::AddAirdropContracts {
+            addresses: vec![HumanAddr::from("airdrop")],
+        };
+        handle(&mut deps, env_at("admin", 0), msg).unwrap();
+        deps
+    }
+
+    /// Sends the schedules from the airdrop contract. Each one releases half of its allocation at
+    /// the start and the other half a month later
+    fn add_airdrop_schedules(
+        deps: &mut Extern<MockStorage, MockApi, MockQuerier>,
+        time: u64,
+        round: u32,
+        schedules: &[(&str, u64, u128)],
+    ) -> StdResult<HandleResponse> {
+        let schedules: Vec<_> = schedules
+            .iter()
+            .map(|&(address, start_time, allocation)| {
+                let schedule = NewSchedule {
+                    start_time,
+                    allocation: Uint128(allocation),
+                    rate: Uint128(allocation / 2),
+                    releases: vec![30],
+                };
+                (HumanAddr::from(address), schedule)
+            })
+            .collect();
+        let amount = schedules.iter().map(|(_, s)| s.allocation.u128()).sum();
+
+        let msg = HandleMsg::Receive {
+            sender: HumanAddr::from("airdrop"),
+            from: HumanAddr::from("airdrop"),
+            amount: Uint128(amount),
+            msg: Some(to_binary(&ReceiveMsg::AddAirdropSchedules { round, schedules }).unwrap()),
+        };
+        handle(deps, env_at("token", time), msg)
+    }
+
+    fn available_at(deps: &Extern<MockStorage, MockApi, MockQuerier>, time: u64) -> Vec<u128> {
+        let block = env_at("user", time).block;
+        Vesting::get_airdrop_schedules(&deps.storage, &HumanAddr::from("user"))
+            .unwrap()
+            .iter()
+            .map(|a| a.schedule.available_at(&block))
+            .collect()
+    }
+
+    #[test]
+    fn test_airdrop_schedules_per_round() {
+        let mut deps = setup();
+
+        // two vested claims of the same round, then another in the same batch
+        add_airdrop_schedules(&mut deps, 100, 0, &[("user", 1_000, 200)]).unwrap();
+        add_airdrop_schedules(
+            &mut deps,
+            200,
+            0,
+            &[("user", 1_000, 100), ("user", 1_000, 100)],
+        )
+        .unwrap();
+        // a later round vests on its own, even after the first one started
+        add_airdrop_schedules(&mut deps, 1_500, 1, &[("user", 2_000, 100)]).unwrap();
+
+        assert_eq!(available_at(&deps, 1_500), vec![200, 0]);
+        assert_eq!(available_at(&deps, 2_000 + MONTH), vec![400, 100]);
+        assert_eq!(Vesting::get_total_allocation(&deps.storage).unwrap(), 500);
+
+        let msg = HandleMsg::Claim {
+            amount: Some(Uint128(250)),
+        };
+        handle(&mut deps, env_at("user", 2_000), msg).unwrap();
+        assert_eq!(available_at(&deps, 2_000), vec![0, 0]);
+        assert_eq!(available_at(&deps, 2_000 + MONTH), vec![200, 50]);
+
+        let msg = HandleMsg::Claim {
+            amount: Some(Uint128(251)),
+        };
+        handle(&mut deps, env_at("user", 2_000 + MONTH), msg).unwrap_err();
+
+        let msg = HandleMsg::Claim { amount: None };
+        handle(&mut deps, env_at("user", 2_000 + MONTH), msg).unwrap();
+        assert_eq!(available_at(&deps, 2_000 + MONTH), vec![0, 0]);
+    }
+
+    #[test]
+    fn test_airdrop_schedules_of_started_round() {
+        let mut deps = setup();
+
+        add_airdrop_schedules(&mut deps, 100, 0, &[("user", 1_000, 200)]).unwrap();
+        add_airdrop_schedules(&mut deps, 1_500, 0, &[("user", 1_000, 100)]).unwrap_err();
+        assert_eq!(available_at(&deps, 1_500), vec![100]);
+    }
+}
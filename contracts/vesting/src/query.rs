@@ -101,10 +101,12 @@ pub enum QueryResp {
     ContractMode(ContractMode),
 
     // User
+    /// `available` adds up the schedule set by the admin and the airdrop schedules
     Balance {
         address: HumanAddr,
         available: Uint128,
-        schedule: Schedule,
+        schedule: Option<Schedule>,
+        airdrop_schedules: Vec<AirdropVesting>,
     },
 
     // Admin
@@ -116,6 +118,15 @@ pub enum QueryResp {
     },
 }
 
+/// The schedule of the airdrops an account received in a round of an airdrop contract
+#[derive(Serialize, Debug, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub struct AirdropVesting {
+    airdrop: HumanAddr,
+    round: u32,
+    schedule: Schedule,
+}
+
 pub fn query<S: Storage, A: Api, Q: Querier>(
     deps: &Extern<S, A, Q>,
     msg: QueryMsg,
@@ -152,20 +163,35 @@ fn balance<S: Storage, A: Api, Q: Querier>(
     address: HumanAddr,
     time: Option<u64>,
 ) -> StdResult<Binary> {
-    let schedule = Vesting::get_schedule(&deps.storage, &address)?
-        .ok_or_else(|| StdError::generic_err("No vesting schedule found"))?;
+    let schedule = Vesting::get_schedule(&deps.storage, &address)?;
+    let airdrop_schedules = Vesting::get_airdrop_schedules(&deps.storage, &address)?;
+    if schedule.is_none() && airdrop_schedules.is_empty() {
+        return Err(StdError::generic_err("No vesting schedule found"));
+    }
 
     // Override the known last block time.
     // `.available_at()` only cares about `config.last_block.time`
     if let Some(time) = time {
         config.last_block.time = time;
     }
-    let available = Uint128(schedule.available_at(&config.last_block));
+    let available = schedule
+        .iter()
+        .chain(airdrop_schedules.iter().map(|a| &a.schedule))
+        .map(|s| s.available_at(&config.last_block))
+        .sum();
 
     to_binary(&QueryResp::Balance {
         address,
-        available,
-        schedule: schedule.into(),
+        available: Uint128(available),
+        schedule: schedule.map(|s| s.into()),
+        airdrop_schedules: airdrop_schedules
+            .into_iter()
+            .map(|a| AirdropVesting {
+                airdrop: a.airdrop,
+                round: a.round,
+                schedule: a.schedule.into(),
+            })
+            .collect(),
     })
 }
 
@@ -1 +1,50 @@
+use cosmwasm_std::{HumanAddr, ReadonlyStorage, StdError, StdResult, Storage};
+use cosmwasm_storage::{PrefixedStorage, ReadonlyPrefixedStorage};
+use secret_toolkit::storage::{TypedStore, TypedStoreMut};
 
+/// Airdrop contracts that are allowed to create vesting schedules by sending funds to this contract
+pub struct AirdropContracts;
+
+impl AirdropContracts {
+    const STORE_KEY: &'static [u8] = b"airdrop-contracts";
+
+    pub fn get<S: ReadonlyStorage>(store: &S, address: &HumanAddr) -> StdResult<bool> {
+        let store = ReadonlyPrefixedStorage::new(Self::STORE_KEY, store);
+        TypedStore::attach(&store)
+            .may_load(address.as_str().as_bytes())
+            .map(|maybe| maybe.unwrap_or(false))
+    }
+
+    pub fn set_multiple<S: Storage>(store: &mut S, addresses: Vec<HumanAddr>) -> StdResult<()> {
+        let mut store = PrefixedStorage::new(Self::STORE_KEY, store);
+        let mut typed_store = TypedStoreMut::attach(&mut store);
+
+        for address in addresses {
+            typed_store.store(address.as_str().as_bytes(), &true)?;
+        }
+
+        Ok(())
+    }
+
+    pub fn remove_multiple<S: Storage>(store: &mut S, addresses: Vec<HumanAddr>) {
+        let mut store = PrefixedStorage::new(Self::STORE_KEY, store);
+        let mut typed_store = TypedStoreMut::<bool, _>::attach(&mut store);
+
+        for address in addresses {
+            typed_store.remove(address.as_str().as_bytes());
+        }
+    }
+
+    pub fn assert_airdrop_contract<S: ReadonlyStorage>(
+        store: &S,
+        address: &HumanAddr,
+    ) -> StdResult<()> {
+        if !Self::get(store, address)? {
+            return Err(StdError::generic_err(format!(
+                "Address {} is not a registered airdrop contract",
+                address
+            )));
+        }
+        Ok(())
+    }
+}
@@ -110,6 +110,36 @@ impl StoredSchedule {
         Ok(to_claim)
     }
 
+    /// Add the funds of another schedule to this one, releasing both rates on each release.
+    ///
+    /// `released_at` counts the releases from the start time, so funds added to a schedule that
+    /// already started would be released retroactively. Only schedules that haven't started yet
+    /// and have the same start time and releases can be merged, like the airdrops of one round
+    pub fn extend(&mut self, other: StoredSchedule, block: &BlockInfo) -> StdResult<()> {
+        if block.time >= self.start_time {
+            return Err(StdError::generic_err(
+                "Can't extend a vesting schedule that already started",
+            ));
+        }
+        if other.start_time != self.start_time || other.releases != self.releases {
+            return Err(StdError::generic_err(
+                "Can't extend a vesting schedule with a different start time or releases",
+            ));
+        }
+
+        self.allocation = self
+            .allocation
+            .checked_add(other.allocation)
+            .ok_or_else(|| {
+                StdError::generic_err("Extending the vesting allocation exceeded u128::MAX")
+            })?;
+        self.rate = self.rate.checked_add(other.rate).ok_or_else(|| {
+            StdError::generic_err("Extending the vesting rate exceeded u128::MAX")
+        })?;
+
+        Ok(())
+    }
+
     /// Set the claimed funds to equal the released funds, and return what the difference was between them.
     pub fn claim_all(&mut self, block: &BlockInfo) -> u128 {
         let released = self.released_at(block);
@@ -120,10 +150,20 @@ impl StoredSchedule {
     }
 }
 
+/// A schedule created by a round of an airdrop contract. Each round vests separately from the
+/// other rounds and from the schedule set by the admin
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct AirdropSchedule {
+    pub airdrop: HumanAddr,
+    pub round: u32,
+    pub schedule: StoredSchedule,
+}
+
 pub struct Vesting;
 
 impl Vesting {
     const STORE_KEY: &'static [u8] = b"vesting";
+    const AIRDROP_STORE_KEY: &'static [u8] = b"vesting-airdrops";
     const TOTAL_ALLOCATION_KEY: &'static [u8] = b"vesting-total-allocation";
 
     pub fn get_total_allocation<S: ReadonlyStorage>(store: &S) -> StdResult<u128> {
@@ -141,6 +181,17 @@ impl Vesting {
         store.may_load(address.as_str().as_bytes())
     }
 
+    pub fn get_airdrop_schedules<S: ReadonlyStorage>(
+        store: &S,
+        address: &HumanAddr,
+    ) -> StdResult<Vec<AirdropSchedule>> {
+        let store = ReadonlyPrefixedStorage::new(Self::AIRDROP_STORE_KEY, store);
+        let store = TypedStore::attach(&store);
+        store
+            .may_load(address.as_str().as_bytes())
+            .map(|maybe| maybe.unwrap_or_default())
+    }
+
     /// Update the state of the vesting schedules.
     ///
     /// This method lets you describe how the vesting schedule should be changed,
@@ -163,6 +214,8 @@ impl Vesting {
         // destructure `context` because we need to release the shared lifetime on the storage.
         let mut read_schedules = context.read_schedules;
         let schedules = context.schedules;
+        let mut read_airdrop_schedules = context.read_airdrop_schedules;
+        let airdrop_schedules = context.airdrop_schedules;
 
         // Update the schedules for each of the accounts
         let mut pstore = PrefixedStorage::new(Self::STORE_KEY, store);
@@ -198,6 +251,34 @@ impl Vesting {
             }
         }
 
+        // Same for the airdrop schedules, which are all saved together for each account
+        let mut pstore = PrefixedStorage::new(Self::AIRDROP_STORE_KEY, store);
+        let mut sched_store = TypedStoreMut::attach(&mut pstore);
+        for (address, schedules) in airdrop_schedules.into_iter() {
+            let addr_bytes = address.as_str().as_bytes();
+
+            let prev_schedules: Vec<AirdropSchedule> = match read_airdrop_schedules.remove(&address)
+            {
+                Some(prev_schedules) => prev_schedules,
+                None => sched_store.may_load(addr_bytes)?.unwrap_or_default(),
+            };
+            for prev_schedule in prev_schedules.iter() {
+                total_allocation -= prev_schedule.schedule.allocation;
+            }
+
+            for schedule in schedules.iter() {
+                total_allocation = total_allocation
+                    .checked_add(schedule.schedule.allocation)
+                    .ok_or_else(|| {
+                        StdError::generic_err(format!(
+                            "Trying to allocate vesting funds for {} exceeded u128::MAX",
+                            address
+                        ))
+                    })?;
+            }
+            sched_store.store(addr_bytes, &schedules)?;
+        }
+
         // Save the new allocation
         if initial_allocation != total_allocation {
             TypedStoreMut::attach(store).store(Self::TOTAL_ALLOCATION_KEY, &total_allocation)?;
@@ -215,6 +296,10 @@ pub struct VestingContext<'store, S: ReadonlyStorage> {
     schedules: HashMap<HumanAddr, Option<StoredSchedule>>,
     /// A cache. If the user reads schedules, we won't go to the storage again for them later.
     read_schedules: HashMap<HumanAddr, Option<StoredSchedule>>,
+    /// The new airdrop schedules to write to the db
+    airdrop_schedules: HashMap<HumanAddr, Vec<AirdropSchedule>>,
+    /// A cache, like `read_schedules`
+    read_airdrop_schedules: HashMap<HumanAddr, Vec<AirdropSchedule>>,
 }
 
 impl<'store, S: ReadonlyStorage> VestingContext<'store, S> {
@@ -224,6 +309,8 @@ impl<'store, S: ReadonlyStorage> VestingContext<'store, S> {
             store,
             schedules: HashMap::new(),
             read_schedules: HashMap::new(),
+            airdrop_schedules: HashMap::new(),
+            read_airdrop_schedules: HashMap::new(),
         }
     }
 
@@ -272,4 +359,90 @@ impl<'store, S: ReadonlyStorage> VestingContext<'store, S> {
         // None marks that the account should be removed
         self.schedules.insert(address, None);
     }
+
+    /// Read the airdrop schedules of an address from the cache, like `get_schedule`
+    pub fn get_airdrop_schedules(
+        &mut self,
+        address: &HumanAddr,
+    ) -> StdResult<Vec<AirdropSchedule>> {
+        if let Some(schedules) = self.airdrop_schedules.get(address) {
+            return Ok(schedules.clone());
+        }
+        if let Some(schedules) = self.read_airdrop_schedules.get(address) {
+            return Ok(schedules.clone());
+        }
+
+        let schedules = Vesting::get_airdrop_schedules(self.store, address)?;
+        self.read_airdrop_schedules
+            .insert(address.clone(), schedules.clone());
+        Ok(schedules)
+    }
+
+    /// Mark the address's airdrop schedules to be saved in the storage
+    pub fn set_airdrop_schedules(&mut self, address: HumanAddr, schedules: Vec<AirdropSchedule>) {
+        self.airdrop_schedules.insert(address, schedules);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cosmwasm_std::testing::mock_env;
+
+    fn block_at(time: u64) -> BlockInfo {
+        let mut block = mock_env("user", &[]).block;
+        block.time = time;
+        block
+    }
+
+    fn schedule(start_time: u64, allocation: u128, rate: u128) -> StoredSchedule {
+        NewSchedule {
+            start_time,
+            allocation: Uint128(allocation),
+            rate: Uint128(rate),
+            releases: vec![30, 30, 30],
+        }
+        .into()
+    }
+
+    #[test]
+    fn test_extend_unstarted_schedule() {
+        let mut first = schedule(1_000, 400, 100);
+        first
+            .extend(schedule(1_000, 200, 50), &block_at(500))
+            .unwrap();
+
+        assert_eq!(first.released_at(&block_at(999)), 0);
+        assert_eq!(first.released_at(&block_at(1_000)), 150);
+        assert_eq!(first.released_at(&block_at(1_000 + 30 * 24 * 60 * 60)), 300);
+        assert_eq!(first.released_at(&block_at(1_000 + 90 * 24 * 60 * 60)), 600);
+    }
+
+    #[test]
+    fn test_extend_started_schedule() {
+        let mut first = schedule(1_000, 400, 100);
+        let month = 30 * 24 * 60 * 60;
+        let now = 1_000 + 2 * month;
+        assert_eq!(first.released_at(&block_at(now)), 300);
+
+        // the funds of the new round would be released for the periods that already passed
+        let err = first.extend(schedule(1_000, 200, 50), &block_at(now));
+        assert!(err.is_err());
+        let err = first.extend(schedule(now + month, 200, 50), &block_at(now));
+        assert!(err.is_err());
+        assert_eq!(first.allocation, 400);
+        assert_eq!(first.rate, 100);
+    }
+
+    #[test]
+    fn test_extend_schedule_with_other_timing() {
+        let mut first = schedule(1_000, 400, 100);
+
+        let later = schedule(2_000, 200, 50);
+        assert!(first.extend(later, &block_at(500)).is_err());
+
+        let mut other_releases = schedule(1_000, 200, 50);
+        other_releases.releases = vec![7, 7];
+        assert!(first.extend(other_releases, &block_at(500)).is_err());
+    }
 }
@@ -55,6 +55,7 @@ async function run() {
         },
         distribute_address: contract_owner.account.address,
         signer_address: Buffer.from(rawKey.publicKey.key.toString('base64'), 'base64').toString("base64"),
+        random_seed: require("crypto").randomBytes(32).toString("base64"),
         token_native_denom: process.env.LGND_NATIVE,
         viewing_key: VIEWING_KEY,
     };
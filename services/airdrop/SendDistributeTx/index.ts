@@ -12,6 +12,7 @@ import {
     CHAIN_ID,
     AIRDROP_CONTRACT_ADDRESS,
     AIRDROP_CONTRACT_HASH,
+    AIRDROP_ROUND,
 } from "../shared/consts";
 
 class ComputeError extends Error {
@@ -31,7 +32,7 @@ interface AirdropEntry {
 }
 
 interface ConfirmAirdropMsg {
-    confirm_airdrop: { airdrops: AirdropEntry[] };
+    confirm_airdrop: { round: number; airdrops: AirdropEntry[] };
 }
 
 // *************** HELPER FUNCTIONS  ********** //
@@ -58,7 +59,7 @@ let AIRDROP_ITEM_COST = 3_800; //gas
 
 const createSendTx = async (client: SecretNetworkClient, airdrops: AirdropEntry[]) => {
     let msg: ConfirmAirdropMsg = {
-        confirm_airdrop: { airdrops },
+        confirm_airdrop: { round: AIRDROP_ROUND, airdrops },
     };
 
     console.log(`sending the confirm_airdrops message: ${JSON.stringify(msg)}`);
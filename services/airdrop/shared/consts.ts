@@ -15,6 +15,7 @@ export const CHAIN_ID: string = process.env.CHAIN_ID || "secretdev-1";
 
 export const AIRDROP_CONTRACT_ADDRESS: string = process.env.AIRDROP_CONTRACT_ADDRESS || "";
 export const AIRDROP_CONTRACT_HASH: string = process.env.AIRDROP_CONTRACT_HASH || "";
+export const AIRDROP_ROUND: number = parseInt(process.env.AIRDROP_ROUND || "0");
 
 // address of the ...?
 export const PERMIT_CONTRACT_ADDRESS: string = process.env.PERMIT_CONTRACT_ADDRESS || "secret1asdf";
@@ -21,6 +21,9 @@ const STAKING_VK_LGND = "STAKING_VK"
 const OWNER_VK_LGND = "OWNER_VK"
 
 
+const CHAIN_ID = process.env.CHAIN_ID || "secretdev-1"
+const OPEN_LOOT_BOX_DOMAIN = "legendao_platform_open_loot_box_v1"
+
 const USER_1_VK_LGND = "USER_1_VK"
 const USER_1_VK_ON_PLATFORM = "USER_1_VK_PLATFORM"
 const USER_1_VK_ON_STAKING = "USER_1_VK_STAKING"
@@ -673,21 +676,25 @@ describe("Minting", () => {
 
             let ts_execute = Math.floor(Date.now() / 1000);
             
-            const open_loot_box = {
-                "open_loot_box": {
-                    "loot_box_id": third_nft.toString(),
-                    "open_lgnd_amount": "0",
-                    "open_nft_contract": {
-                        "address": snip721_other_token.contractAddress,
-                        "hash": snip721_code_hash,
-                    },
-                    "nonce": 1,
-                    "ts_execute": ts_execute,
-                    "open_nft_uri": "https://bigdick.com/".concat(third_nft.toString()),
-                }
+            // Canonical payload built by the platform (see `platform::loot_box::OpenLootBoxPayload`),
+            // field order matters
+            const open_loot_box_payload = {
+                "domain": OPEN_LOOT_BOX_DOMAIN,
+                "chain_id": CHAIN_ID,
+                "contract": platform.contractAddress,
+                "sender": user_1.account.address,
+                "loot_box_id": third_nft.toString(),
+                "open_lgnd_amount": "0",
+                "open_nft_contract": {
+                    "address": snip721_other_token.contractAddress,
+                    "hash": snip721_code_hash,
+                },
+                "open_nft_uri": "https://bigdick.com/".concat(third_nft.toString()),
+                "nonce": 0,
+                "ts_execute": ts_execute,
             }
 
-            const message = Buffer.from(JSON.stringify(open_loot_box));
+            const message = Buffer.from(JSON.stringify(open_loot_box_payload));
 
             const signature = await rawKey.sign(message);
             // const signature = await rawKey_other.sign(message);
@@ -706,8 +713,7 @@ describe("Minting", () => {
                     "open_nft_uri": "https://bigdick.com/".concat(third_nft.toString()),
                     "nonce": 0,
                     "ts_execute": ts_execute,
-                    "message": Buffer.from(JSON.stringify(open_loot_box).toString()).toString("base64"),
-                    "signature": Buffer.from(signature).toString("base64"),
+                    "signatures": [Buffer.from(signature).toString("base64")],
                 },
                 user_1,
                 undefined,
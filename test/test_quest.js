@@ -91,21 +91,25 @@ describe('Quest Tracker', function() {
                 contract: owner_address,
                 quest: QUEST_NUMBER,
             }],
-            quest_weights: [{
-                quest: QUEST_NUMBER,
-                weight: QUEST_WEIGHT
-            }]
+            quests: [{
+                id: QUEST_NUMBER,
+                name: "test quest",
+                reward: { multiplier: QUEST_WEIGHT },
+                prerequisites: [],
+            }],
+            prng_seed: toBase64("random seed"),
         }, "quest test", contract_owner);
 
         console.log(`snip20 at:  ${snip20.contractAddress}`);
         console.log(`airdrop at: ${airdrop.contractAddress}`);
         console.log(`quest at:  ${quest.contractAddress}`);
 
-        await airdrop.executeMsg("change_config", { quest_contract: quest.contractAddress },
+        await airdrop.executeMsg("add_queriers", { addresses: [quest.contractAddress] },
             contract_owner);
 
         await quest.executeMsg("register_airdrop_contract", { contract:
-                    {address: airdrop.contractAddress, hash: airdrop.contractCodeHash} },
+                    {address: airdrop.contractAddress, hash: airdrop.contractCodeHash},
+                entropy: "quest test" },
             contract_owner);
 
         // create VK
@@ -117,15 +121,26 @@ describe('Quest Tracker', function() {
             await expect(snip20_balance_query).to.respondWith({balance:{staked:amount.toString(),pending_redeem:{unbondings:[],claimable:"0"}}});
         }
 
-        // Send SNIP-20 to the quest contract's address
-        await snip20.executeMsg("transfer", { recipient: quest.contractAddress, amount: "1000" },
-            contract_owner);
+        // Fund the quest's reward budget
+        await snip20.executeMsg("send", {
+            recipient: quest.contractAddress,
+            recipient_code_hash: quest.contractCodeHash,
+            amount: "1000",
+            msg: Buffer.from(JSON.stringify({ fund_quest: { quest: QUEST_NUMBER } })).toString("base64"),
+        }, contract_owner);
 
         // Send SNIP-20 to the airdrop contract's address
         await snip20.executeMsg("transfer", { recipient: airdrop.contractAddress, amount: "1000" },
             contract_owner);
 
+        await airdrop.executeMsg("create_round", {
+            budget: "1000",
+            start_time: 0,
+            end_time: Math.floor(Date.now() / 1000) + 3600,
+        }, contract_owner);
+
         await airdrop.executeMsg("confirm_airdrop", {
+            round: 0,
             airdrops: [{
                 address: other_address,
                 to: other_address,